@@ -0,0 +1,158 @@
+//! `qubit` command line tool.
+//!
+//! Loads a router exported from a library crate with `qubit::export_router!`, and runs commands
+//! against it (such as generating bindings), without starting the server. This is achieved by
+//! building a small shim binary which depends on the library, and calls the exported entry point.
+
+mod metadata;
+mod shim;
+
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    process::{Command, ExitCode},
+};
+
+use self::{metadata::Metadata, shim::Shim};
+
+const USAGE: &str = "\
+Usage: qubit [--manifest-path <PATH>] [--package <NAME>] <COMMAND> [OPTIONS]
+
+Loads the router exported with `qubit::export_router!` from a library crate, and runs the command
+against it. Run `qubit help` to see the available commands.
+
+Options:
+  --manifest-path <PATH>  Path to the `Cargo.toml` of the crate (or workspace) to load
+  -p, --package <NAME>    Package containing the exported router
+";
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args_os().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Build the shim for the selected package, and run it with the forwarded arguments.
+fn run(args: Args) -> Result<ExitCode, Error> {
+    let metadata = Metadata::load(args.manifest_path.as_deref())?;
+    let package = metadata.select_package(args.package.as_deref())?;
+    let shim = Shim::write(&metadata, package)?;
+
+    let status = Command::new(cargo())
+        .arg("run")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(shim.manifest_path())
+        .arg("--target-dir")
+        .arg(&metadata.target_directory)
+        .arg("--")
+        .args(args.forwarded)
+        .status()?;
+
+    Ok(status
+        .code()
+        .and_then(|code| u8::try_from(code).ok())
+        .map(ExitCode::from)
+        .unwrap_or(ExitCode::FAILURE))
+}
+
+/// Cargo binary to invoke, respecting the `CARGO` environment variable if it is set.
+fn cargo() -> OsString {
+    std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into())
+}
+
+/// Arguments for the binary itself. Everything from the first unknown argument onwards will be
+/// forwarded to the shim.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Args {
+    manifest_path: Option<PathBuf>,
+    package: Option<String>,
+    forwarded: Vec<OsString>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = impl Into<OsString>>) -> Result<Self, Error> {
+        let mut args = args.into_iter().map(Into::into);
+        let mut parsed = Self::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| Error::MissingValue(arg.to_string_lossy().to_string()))
+            };
+
+            match arg.to_str() {
+                Some("--manifest-path") => parsed.manifest_path = Some(PathBuf::from(value()?)),
+                Some("-p" | "--package") => {
+                    parsed.package = Some(value()?.to_string_lossy().to_string())
+                }
+                _ => {
+                    parsed.forwarded.push(arg);
+                    parsed.forwarded.extend(args);
+                    break;
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("a value is required for `{0}`")]
+    MissingValue(String),
+    #[error(transparent)]
+    Metadata(#[from] metadata::MetadataError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::empty(&[], Args::default())]
+    #[case::forward_everything(
+        &["generate", "--out", "bindings.ts"],
+        Args { forwarded: vec!["generate".into(), "--out".into(), "bindings.ts".into()], ..Default::default() }
+    )]
+    #[case::package(
+        &["-p", "server", "schema"],
+        Args { package: Some("server".to_string()), forwarded: vec!["schema".into()], ..Default::default() }
+    )]
+    #[case::manifest_path(
+        &["--manifest-path", "server/Cargo.toml", "--package", "server", "check", "--package"],
+        Args {
+            manifest_path: Some(PathBuf::from("server/Cargo.toml")),
+            package: Some("server".to_string()),
+            forwarded: vec!["check".into(), "--package".into()],
+        }
+    )]
+    fn parse_args(#[case] args: &[&str], #[case] expected: Args) {
+        assert_eq!(Args::parse(args).unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_args_missing_value() {
+        assert!(matches!(
+            Args::parse(["--package"]),
+            Err(Error::MissingValue(_))
+        ));
+    }
+}
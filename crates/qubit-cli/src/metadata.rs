@@ -0,0 +1,171 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+
+/// Subset of the output of `cargo metadata`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Metadata {
+    pub packages: Vec<Package>,
+    pub workspace_root: PathBuf,
+    pub target_directory: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<Target>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Target {
+    pub kind: Vec<String>,
+}
+
+impl Metadata {
+    /// Run `cargo metadata` for the provided manifest, or the current directory.
+    pub fn load(manifest_path: Option<&Path>) -> Result<Self, MetadataError> {
+        let mut command = Command::new(super::cargo());
+        command.args(["metadata", "--format-version", "1", "--no-deps"]);
+
+        if let Some(manifest_path) = manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        let output = command.output()?;
+        if !output.status.success() {
+            return Err(MetadataError::Cargo(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    /// Select the package containing the router. If no name is provided, the package in the current
+    /// directory will be used, or the only package in the workspace.
+    pub fn select_package(&self, name: Option<&str>) -> Result<&Package, MetadataError> {
+        let package = match name {
+            Some(name) => self
+                .packages
+                .iter()
+                .find(|package| package.name == name)
+                .ok_or_else(|| MetadataError::UnknownPackage(name.to_string()))?,
+            None => {
+                let current_dir = std::env::current_dir()?;
+
+                match self.packages.as_slice() {
+                    [package] => package,
+                    packages => packages
+                        .iter()
+                        .find(|package| package.manifest_dir() == current_dir)
+                        .ok_or(MetadataError::AmbiguousPackage)?,
+                }
+            }
+        };
+
+        if package.lib().is_none() {
+            return Err(MetadataError::MissingLib(package.name.clone()));
+        }
+
+        Ok(package)
+    }
+}
+
+impl Package {
+    /// Directory containing the package.
+    pub fn manifest_dir(&self) -> &Path {
+        self.manifest_path
+            .parent()
+            .expect("manifest path has parent directory")
+    }
+
+    /// The library target of the package, if present.
+    pub fn lib(&self) -> Option<&Target> {
+        self.targets
+            .iter()
+            .find(|target| target.kind.iter().any(|kind| kind.ends_with("lib")))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    #[error("`cargo metadata` failed: {0}")]
+    Cargo(String),
+    #[error("invalid output from `cargo metadata`: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("package `{0}` not found in workspace")]
+    UnknownPackage(String),
+    #[error("multiple packages in workspace, select one with `--package`")]
+    AmbiguousPackage,
+    #[error("package `{0}` has no library target to load the router from")]
+    MissingLib(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn metadata() -> Metadata {
+        serde_json::from_value(json!({
+            "packages": [
+                {
+                    "name": "server",
+                    "manifest_path": "/workspace/server/Cargo.toml",
+                    "targets": [
+                        { "name": "server", "kind": ["lib"] },
+                        { "name": "server", "kind": ["bin"] },
+                    ],
+                },
+                {
+                    "name": "migrations",
+                    "manifest_path": "/workspace/migrations/Cargo.toml",
+                    "targets": [{ "name": "migrations", "kind": ["bin"] }],
+                },
+            ],
+            "workspace_root": "/workspace",
+            "target_directory": "/workspace/target",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn select_named_package() {
+        let metadata = metadata();
+        let package = metadata.select_package(Some("server")).unwrap();
+
+        assert_eq!(package.name, "server");
+        assert_eq!(package.manifest_dir(), Path::new("/workspace/server"));
+    }
+
+    #[test]
+    fn select_unknown_package() {
+        assert!(matches!(
+            metadata().select_package(Some("client")),
+            Err(MetadataError::UnknownPackage(_))
+        ));
+    }
+
+    #[test]
+    fn select_package_without_lib() {
+        assert!(matches!(
+            metadata().select_package(Some("migrations")),
+            Err(MetadataError::MissingLib(_))
+        ));
+    }
+
+    #[test]
+    fn select_ambiguous_package() {
+        assert!(matches!(
+            metadata().select_package(None),
+            Err(MetadataError::AmbiguousPackage)
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::metadata::{Metadata, Package};
+
+/// Name of the entry point created by `qubit::export_router!`, which must match
+/// `qubit::cli::ENTRY_POINT`.
+const ENTRY_POINT: &str = "__qubit_cli";
+
+/// A generated crate which depends on the package containing the router, and calls its entry
+/// point.
+pub struct Shim {
+    dir: PathBuf,
+}
+
+impl Shim {
+    /// Write the shim for the provided package into the target directory, re-using the lock file
+    /// from the workspace so the same dependency versions are resolved.
+    pub fn write(metadata: &Metadata, package: &Package) -> std::io::Result<Self> {
+        let dir = metadata
+            .target_directory
+            .join("qubit-cli")
+            .join(&package.name);
+        fs::create_dir_all(dir.join("src"))?;
+
+        write_if_changed(&dir.join("Cargo.toml"), &manifest(package))?;
+        write_if_changed(&dir.join("src/main.rs"), &main())?;
+
+        let lock_file = metadata.workspace_root.join("Cargo.lock");
+        if lock_file.exists() {
+            fs::copy(lock_file, dir.join("Cargo.lock"))?;
+        }
+
+        Ok(Self { dir })
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir.join("Cargo.toml")
+    }
+}
+
+/// Only write to a file if the contents differ, to avoid unnecessary rebuilds.
+fn write_if_changed(path: &Path, contents: &str) -> std::io::Result<()> {
+    if fs::read_to_string(path).ok().as_deref() == Some(contents) {
+        return Ok(());
+    }
+
+    fs::write(path, contents)
+}
+
+/// Manifest for the shim. The empty `workspace` table prevents it from being considered part of
+/// any parent workspace.
+fn manifest(package: &Package) -> String {
+    // JSON strings are valid TOML basic strings.
+    let path = serde_json::to_string(&package.manifest_dir()).expect("path is valid JSON");
+    let name = serde_json::to_string(&package.name).expect("name is valid JSON");
+
+    format!(
+        r#"[package]
+name = "qubit-cli-shim"
+version = "0.0.0"
+edition = "2024"
+publish = false
+
+[workspace]
+
+[dependencies]
+router = {{ package = {name}, path = {path} }}
+"#
+    )
+}
+
+fn main() -> String {
+    format!(
+        "fn main() -> std::process::ExitCode {{
+    router::{ENTRY_POINT}()
+}}
+"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::Target;
+
+    use super::*;
+
+    #[test]
+    fn shim_manifest() {
+        let package = Package {
+            name: "my-server".to_string(),
+            manifest_path: PathBuf::from("/workspace/my-server/Cargo.toml"),
+            targets: vec![Target {
+                kind: vec!["lib".to_string()],
+            }],
+        };
+
+        let manifest = manifest(&package);
+        assert!(
+            manifest
+                .contains(r#"router = { package = "my-server", path = "/workspace/my-server" }"#)
+        );
+        assert!(manifest.contains("[workspace]"));
+    }
+
+    #[test]
+    fn entry_point_matches() {
+        assert_eq!(ENTRY_POINT, qubit::cli::ENTRY_POINT);
+    }
+}
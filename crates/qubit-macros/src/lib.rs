@@ -3,6 +3,95 @@ mod macros;
 use proc_macro::TokenStream;
 
 /// See [`qubit::builder::handler`] for more information.
+///
+/// The function is replaced by a unit struct of the same name, which implements
+/// `qubit::RouterHandler` so that it can be passed to `Router::handler`. As a result, the handler
+/// can no longer be called directly, and a binding with the same name (such as
+/// `let get_user = ...`) will be treated as a pattern matching the struct, so must be renamed. Any
+/// logic which is also needed outside of the router should be moved into a plain function, which
+/// the handler calls.
+///
+/// By default, the first parameter of the handler is the context. Alternatively, any number of
+/// parameters can be marked with `#[ctx]`, and each will be built with `FromRequestExtensions`.
+/// Every other parameter will be an RPC parameter.
+///
+/// ```ignore
+/// #[handler(query)]
+/// async fn get_post(#[ctx] db: Db, #[ctx] user: Authenticated<User>, id: u32) -> Post {
+///     todo!()
+/// }
+/// ```
+///
+/// Handlers which don't need a context can be marked with `no_ctx`, so that every parameter is an
+/// RPC parameter. Without it, a primitive (such as `u32`) as the first parameter is rejected, unless
+/// it is marked with `#[ctx]` (for routers whose context is a primitive).
+///
+/// ```ignore
+/// #[handler(query, no_ctx)]
+/// async fn add(a: u32, b: u32) -> u32 {
+///     a + b
+/// }
+/// ```
+///
+/// Parameters may be destructured, in which case their name is derived from their type (such as
+/// `geo_point` for `GeoPoint`). The name of any parameter can instead be provided with
+/// `#[name = "..."]`, which is required for destructured tuples.
+///
+/// ```ignore
+/// #[handler(query)]
+/// async fn nearby(ctx: Ctx, GeoPoint { lat, lng }: GeoPoint, #[name = "range"] (min, max): (u32, u32)) {}
+/// ```
+///
+/// Trailing parameters which are an `Option`, or are marked with `#[default]`, may be omitted by
+/// the client. Omitted `#[default]` parameters are replaced with their `Default` value, so
+/// `#[default]` may only be used on these trailing parameters.
+///
+/// ```ignore
+/// #[handler(query)]
+/// async fn list_posts(ctx: Ctx, author: Option<String>, #[default] limit: u32) -> Vec<Post> {
+///     todo!()
+/// }
+/// ```
+///
+/// A limit on how often each client may call the handler can be set with `rate_limit`, in the
+/// form `<requests>/<period>` where the period is one of `s`, `min`, `h` or `day`. A `RateLimiter`
+/// must be provided to the module in order to serve it, which enforces the limit.
+///
+/// ```ignore
+/// #[handler(mutation, rate_limit = "10/min")]
+/// async fn send_email(ctx: Ctx, address: String) {}
+/// ```
+///
+/// Queries and mutations may be cancelled if they don't complete within a `timeout`, in the form
+/// `<amount><unit>` where the unit is one of `ms`, `s`, `min` or `h`. This overrides any timeout
+/// configured for the module.
+///
+/// ```ignore
+/// #[handler(query, timeout = "5s")]
+/// async fn search(ctx: Ctx, query: String) -> Vec<Post> {
+///     todo!()
+/// }
+/// ```
+///
+/// The number of calls that may run at once can be limited with `max_concurrency`. Further calls
+/// will be rejected, unless `max_wait` is provided in which case they will wait up to that
+/// duration for a running call to complete.
+///
+/// ```ignore
+/// #[handler(mutation, max_concurrency = 4, max_wait = "2s")]
+/// async fn charge_card(ctx: Ctx, amount: u32) {}
+/// ```
+///
+/// Synchronous queries and mutations run on the async runtime, so CPU-heavy work or blocking I/O
+/// will stall other calls. Marking them as `blocking` runs them on the blocking thread pool
+/// instead, once the context has been built.
+///
+/// ```ignore
+/// #[handler(query, blocking)]
+/// fn read_file(ctx: Ctx, path: String) -> String {
+///     std::fs::read_to_string(path).unwrap()
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn handler(attrs: TokenStream, item: TokenStream) -> TokenStream {
     match macros::handler(attrs.into(), item.into()) {
@@ -12,6 +101,46 @@ pub fn handler(attrs: TokenStream, item: TokenStream) -> TokenStream {
     .into()
 }
 
+/// Turn the methods of an impl block into handlers, with the type itself as the context.
+///
+/// Methods are marked with `#[query]`, `#[mutation]` or `#[subscription]`, which accept the same
+/// arguments as [`handler`](macro@handler) (other than the kind and `no_ctx`). Each must take
+/// `self`, either by reference, by value, or as `self: Arc<Self>`. As the service is cloned for
+/// each call, `self` cannot be taken mutably. Additional parameters may be marked with `#[ctx]`,
+/// as with any other handler.
+///
+/// A `router` method is added to the type, producing a router with every handler, so the impl
+/// block cannot define its own `router`. The router may have any context that the service can be
+/// extracted from, so it can be nested within the application's router.
+///
+/// ```ignore
+/// #[derive(Clone)]
+/// struct UserService {
+///     db: Db,
+/// }
+///
+/// #[service]
+/// impl UserService {
+///     #[query]
+///     async fn get(&self, id: u32) -> User {
+///         todo!()
+///     }
+///
+///     #[mutation(name = "rename")]
+///     async fn set_name(&self, id: u32, name: String) {}
+/// }
+///
+/// let router = Router::new().nest("users", UserService::router());
+/// ```
+#[proc_macro_attribute]
+pub fn service(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    match macros::service(attrs.into(), item.into()) {
+        Ok(ts) => ts,
+        Err(e) => e.into_compile_error(),
+    }
+    .into()
+}
+
 /// Mark a type to be exported to TypeScript.
 ///
 /// See [`ts_rs::TS`] for available attributes.
@@ -1,8 +1,25 @@
-use syn::{Error, FnArg, Ident, ItemFn, Pat, PatIdent, Receiver};
+use std::time::Duration;
 
-use super::parse::{Ast, HandlerKind};
+use proc_macro2::Span;
+
+use syn::{
+    Attribute, Error, Expr, ExprLit, FnArg, Ident, ItemFn, Lit, Meta, MetaNameValue, Pat, PatIdent,
+    PatType, Receiver, Token, Type, TypePath, spanned::Spanned,
+};
+
+use super::parse::{Ast, HandlerKind, RateLimit};
 
 pub fn analyse(ast: Ast) -> Result<Model, AnalyseError> {
+    // Only synchronous handlers can be moved to the blocking thread pool.
+    if ast.attrs.blocking
+        && let Some(asyncness) = ast.handler.sig.asyncness
+    {
+        return Err(AnalyseError::AsyncBlocking(asyncness));
+    }
+
+    let (extractors, param_names, optional_params) =
+        process_inputs(ast.handler.sig.inputs.iter(), ast.attrs.no_ctx)?;
+
     Ok(Model {
         name: ast.handler.sig.ident.clone(),
         rpc_name: ast
@@ -10,7 +27,16 @@ pub fn analyse(ast: Ast) -> Result<Model, AnalyseError> {
             .name
             .unwrap_or_else(|| ast.handler.sig.ident.to_string()),
         kind: ast.attrs.kind,
-        param_names: process_inputs(ast.handler.sig.inputs.iter())?,
+        kind_span: ast.kind_span,
+        rate_limit: ast.attrs.rate_limit,
+        timeout: ast.attrs.timeout,
+        max_concurrency: ast.attrs.max_concurrency,
+        max_wait: ast.attrs.max_wait,
+        no_ctx: ast.attrs.no_ctx,
+        blocking: ast.attrs.blocking,
+        extractors,
+        param_names,
+        optional_params,
         handler: ast.handler,
     })
 }
@@ -26,9 +52,38 @@ pub struct Model {
     /// Kind of the handler.
     pub kind: HandlerKind,
 
+    /// Span of the handler kind within the attributes.
+    pub kind_span: Span,
+
+    /// Maximum rate at which each client may call the handler.
+    pub rate_limit: Option<RateLimit>,
+
+    /// Maximum duration that a call to the handler may run for.
+    pub timeout: Option<Duration>,
+
+    /// Maximum number of calls to the handler that may run at once.
+    pub max_concurrency: Option<usize>,
+
+    /// Maximum duration that a call may wait for the concurrency limit.
+    pub max_wait: Option<Duration>,
+
+    /// Whether the handler doesn't take a `ctx`, so every parameter is an RPC parameter.
+    pub no_ctx: bool,
+
+    /// Whether the handler runs on the blocking thread pool.
+    pub blocking: bool,
+
+    /// Parameters marked with `#[ctx]` (with the attribute removed). If empty, the first
+    /// parameter will be used as the `ctx`.
+    pub extractors: Vec<PatType>,
+
     /// Name of all the parameters (excluding the `ctx`).
     pub param_names: Vec<Ident>,
 
+    /// Number of trailing parameters which are optional, either as an `Option` or marked with
+    /// `#[default]`.
+    pub optional_params: usize,
+
     /// The actual handler implementation.
     pub handler: ItemFn,
 }
@@ -37,54 +92,250 @@ pub struct Model {
 pub enum AnalyseError {
     #[error(transparent)]
     Input(#[from] InputError),
+    #[error("`blocking` handlers must be synchronous")]
+    AsyncBlocking(Token![async]),
 }
 
 impl From<AnalyseError> for Error {
     fn from(err: AnalyseError) -> Self {
-        match err {
-            AnalyseError::Input(input_error) => input_error.into(),
+        match &err {
+            AnalyseError::Input(input_error) => input_error.clone().into(),
+            AnalyseError::AsyncBlocking(asyncness) => {
+                Error::new_spanned(asyncness, err.to_string())
+            }
         }
     }
 }
-/// From a collection of [`FnArg`]s, extract the parameter names (excluding the `ctx` parameter).
-fn process_inputs<'a>(inputs: impl Iterator<Item = &'a FnArg>) -> Result<Vec<Ident>, InputError> {
-    let mut inputs = inputs
-        .map(|arg| {
-            let arg = match arg {
-                FnArg::Typed(arg) => arg,
-                FnArg::Receiver(receiver) => {
-                    return Err(InputError::SelfParameter(receiver.clone()));
-                }
-            };
-
-            let Pat::Ident(PatIdent { ref ident, .. }) = *arg.pat else {
-                return Err(InputError::Destructured(arg.pat.clone()));
-            };
-
-            Ok(ident.clone())
+
+/// From a collection of [`FnArg`]s, extract any parameters marked with `#[ctx]`, and the parameter
+/// names (excluding the `ctx` parameters), along with the number of trailing optional parameters.
+/// If no parameters are marked, the first parameter is assumed to be the `ctx`, unless the handler
+/// is `no_ctx`.
+fn process_inputs<'a>(
+    inputs: impl Iterator<Item = &'a FnArg>,
+    no_ctx: bool,
+) -> Result<(Vec<PatType>, Vec<Ident>, usize), InputError> {
+    let inputs = inputs
+        .map(|arg| match arg {
+            FnArg::Typed(arg) => Ok(arg),
+            FnArg::Receiver(receiver) => Err(InputError::SelfParameter(receiver.clone())),
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    if !inputs.is_empty() {
-        inputs.remove(0);
+    let (extractors, params) = inputs.into_iter().partition::<Vec<_>, _>(|arg| is_ctx(arg));
+
+    if no_ctx && let Some(extractor) = extractors.first() {
+        return Err(InputError::CtxWithNoCtx(Box::new((*extractor).clone())));
+    }
+
+    // Without any `#[ctx]` parameters, the first parameter is the `ctx`.
+    let ctx = if extractors.is_empty() && !no_ctx {
+        params.first().copied()
+    } else {
+        None
+    };
+
+    // The `ctx` isn't an RPC parameter, so it can't be named or omitted.
+    if let Some(attr) = extractors
+        .iter()
+        .copied()
+        .chain(ctx)
+        .flat_map(|arg| &arg.attrs)
+        .find(|attr| is_name(attr) || is_default(attr))
+    {
+        return Err(InputError::CtxAttribute(Box::new(attr.clone())));
+    }
+
+    let extractors = extractors
+        .into_iter()
+        .map(|arg| {
+            let mut arg = arg.clone();
+            arg.attrs.retain(|attr| !attr.path().is_ident("ctx"));
+            arg
+        })
+        .collect::<Vec<_>>();
+
+    let params = match ctx {
+        Some(ctx) if is_primitive(&ctx.ty) => return Err(InputError::PrimitiveCtx(ctx.ty.clone())),
+        Some(_) => &params[1..],
+        None => &params[..],
+    };
+
+    let mut param_names = Vec::<Ident>::new();
+    for arg in params {
+        let name = param_name(arg)?;
+
+        if param_names.contains(&name) {
+            return Err(InputError::DuplicateName(name));
+        }
+
+        param_names.push(name);
+    }
+
+    let optional_params = params
+        .iter()
+        .rev()
+        .take_while(|arg| is_optional(arg))
+        .count();
+
+    // Only trailing parameters may be omitted, so a default would never be used for any other.
+    if let Some(attr) = params[..params.len() - optional_params]
+        .iter()
+        .find_map(|arg| arg.attrs.iter().find(|attr| is_default(attr)))
+    {
+        return Err(InputError::DefaultNotTrailing(Box::new(attr.clone())));
     }
 
-    Ok(inputs)
+    Ok((extractors, param_names, optional_params))
+}
+
+/// Whether the type is a primitive (such as `u32` or `String`), which is almost certainly meant to
+/// be an RPC parameter rather than the `ctx`.
+fn is_primitive(ty: &Type) -> bool {
+    const PRIMITIVES: [&str; 18] = [
+        "bool", "char", "str", "String", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16",
+        "i32", "i64", "i128", "isize", "f32", "f64",
+    ];
+
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => path
+            .get_ident()
+            .is_some_and(|ident| PRIMITIVES.iter().any(|primitive| ident == primitive)),
+        Type::Reference(reference) => is_primitive(&reference.elem),
+        _ => false,
+    }
+}
+
+/// Whether the parameter may be omitted by the client, as it is either an `Option` or is marked
+/// with `#[default]`.
+fn is_optional(arg: &PatType) -> bool {
+    if arg.attrs.iter().any(is_default) {
+        return true;
+    }
+
+    match &*arg.ty {
+        Type::Path(TypePath { qself: None, path }) => path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Name of a parameter. This is the provided `#[name = "..."]`, the identifier of the parameter,
+/// or for destructured parameters a name derived from the type (such as `geo_point` for
+/// `GeoPoint { lat, lng }: GeoPoint`).
+pub fn param_name(arg: &PatType) -> Result<Ident, InputError> {
+    if let Some(attr) = arg.attrs.iter().find(|attr| is_name(attr)) {
+        let invalid_name = || InputError::InvalidName(Box::new(attr.clone()));
+
+        let Meta::NameValue(MetaNameValue {
+            value:
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(name),
+                    ..
+                }),
+            ..
+        }) = &attr.meta
+        else {
+            return Err(invalid_name());
+        };
+
+        return name.parse::<Ident>().map_err(|_| invalid_name());
+    }
+
+    match &*arg.pat {
+        Pat::Ident(PatIdent { ident, .. }) => Ok(ident.clone()),
+        Pat::Struct(_) | Pat::TupleStruct(_) | Pat::Tuple(_) | Pat::Slice(_) => match &*arg.ty {
+            Type::Path(TypePath { qself: None, path }) => {
+                let ty = &path.segments.last().expect("paths have a segment").ident;
+                Ok(Ident::new(&to_snake_case(&ty.to_string()), arg.pat.span()))
+            }
+            _ => Err(InputError::Unnamed(arg.pat.clone())),
+        },
+        _ => Err(InputError::Unnamed(arg.pat.clone())),
+    }
+}
+
+/// Convert an identifier such as `GeoPoint` into `geo_point`. Runs of uppercase letters are
+/// treated as a single word, so `HTTPRequest` becomes `http_request`.
+fn to_snake_case(ident: &str) -> String {
+    let mut snake_case = String::with_capacity(ident.len());
+
+    let chars = ident.chars().collect::<Vec<_>>();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            // A new word starts after a lowercase letter or digit, or at the last uppercase
+            // letter of a run which is followed by a lowercase letter.
+            let starts_word = i > 0
+                && (!chars[i - 1].is_uppercase()
+                    || chars.get(i + 1).is_some_and(|next| next.is_lowercase()));
+
+            if starts_word && !snake_case.ends_with('_') {
+                snake_case.push('_');
+            }
+
+            snake_case.extend(c.to_lowercase());
+        } else {
+            snake_case.push(c);
+        }
+    }
+
+    snake_case
+}
+
+/// Whether the attribute is `#[name = "..."]`.
+pub fn is_name(attr: &Attribute) -> bool {
+    attr.path().is_ident("name")
+}
+
+/// Whether the attribute is `#[default]`.
+pub fn is_default(attr: &Attribute) -> bool {
+    attr.path().is_ident("default")
+}
+
+/// Whether the parameter is marked with `#[ctx]`.
+pub fn is_ctx(arg: &PatType) -> bool {
+    arg.attrs.iter().any(|attr| attr.path().is_ident("ctx"))
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
 pub enum InputError {
-    #[error("handlers cannot take `self` parameter")]
+    #[error("handlers cannot take `self` parameter, use `#[service]` on the impl block instead")]
     SelfParameter(Receiver),
-    #[error("destructured parameters are not supported in handlers")]
-    Destructured(Box<Pat>),
+    #[error("cannot determine the name of this parameter, provide one with `#[name = \"...\"]`")]
+    Unnamed(Box<Pat>),
+    #[error("parameter names must be provided as `#[name = \"...\"]`, with a valid identifier")]
+    InvalidName(Box<Attribute>),
+    #[error("multiple parameters are named `{0}`")]
+    DuplicateName(Ident),
+    #[error(
+        "the first parameter is used as the `ctx`, mark it with `#[ctx]` if that is intended, or mark the handler with `no_ctx` if it doesn't take one"
+    )]
+    PrimitiveCtx(Box<Type>),
+    #[error("`no_ctx` handlers cannot take `#[ctx]` parameters")]
+    CtxWithNoCtx(Box<PatType>),
+    #[error(
+        "`#[default]` parameters must only be followed by other optional parameters, as only trailing parameters may be omitted"
+    )]
+    DefaultNotTrailing(Box<Attribute>),
+    #[error(
+        "the `ctx` is not an RPC parameter, so cannot be marked with `#[name]` or `#[default]`"
+    )]
+    CtxAttribute(Box<Attribute>),
 }
 
 impl From<InputError> for Error {
     fn from(err: InputError) -> Self {
         match &err {
             InputError::SelfParameter(receiver) => Error::new_spanned(receiver, err.to_string()),
-            InputError::Destructured(pat) => Error::new_spanned(pat, err.to_string()),
+            InputError::Unnamed(pat) => Error::new_spanned(pat, err.to_string()),
+            InputError::InvalidName(attr) => Error::new_spanned(attr, err.to_string()),
+            InputError::DuplicateName(name) => Error::new_spanned(name, err.to_string()),
+            InputError::PrimitiveCtx(ty) => Error::new_spanned(ty, err.to_string()),
+            InputError::CtxWithNoCtx(arg) => Error::new_spanned(arg, err.to_string()),
+            InputError::DefaultNotTrailing(attr) => Error::new_spanned(attr, err.to_string()),
+            InputError::CtxAttribute(attr) => Error::new_spanned(attr, err.to_string()),
         }
     }
 }
@@ -106,7 +357,9 @@ mod test {
         pub name: Ident,
         pub rpc_name: String,
         pub kind: HandlerKind,
+        pub extractors: Vec<PatType>,
         pub param_names: Vec<Ident>,
+        pub optional_params: usize,
     }
 
     impl ModelAssertion {
@@ -115,7 +368,9 @@ mod test {
                 rpc_name: name.to_string(),
                 name,
                 kind,
+                extractors: Vec::new(),
                 param_names: Vec::new(),
+                optional_params: 0,
             }
         }
 
@@ -136,10 +391,20 @@ mod test {
             self
         }
 
+        pub fn with_extractors(mut self, extractors: impl IntoIterator<Item = PatType>) -> Self {
+            self.extractors = extractors.into_iter().collect();
+            self
+        }
+
         pub fn with_param_names(mut self, param_names: impl IntoIterator<Item = Ident>) -> Self {
             self.param_names = param_names.into_iter().collect();
             self
         }
+
+        pub fn with_optional_params(mut self, optional_params: usize) -> Self {
+            self.optional_params = optional_params;
+            self
+        }
     }
 
     mod analyse {
@@ -222,17 +487,50 @@ mod test {
                 .with_rpc_name("other_name")
                 .with_param_names([parse_quote!(param_a), parse_quote!(param_b), parse_quote!(param_c)])
         )]
+        #[case::destructured_param(
+            Attributes::query(),
+            parse_quote!(async fn my_handler(ctx: Ctx, SomeType { a, b }: SomeType)),
+            ModelAssertion::query(parse_quote!(my_handler))
+                .with_param_names([parse_quote!(some_type)])
+        )]
+        #[case::extractors(
+            Attributes::query(),
+            parse_quote!(async fn my_handler(#[ctx] db: Db, #[ctx] user: User, param_a: String)),
+            ModelAssertion::query(parse_quote!(my_handler))
+                .with_extractors([parse_quote!(db: Db), parse_quote!(user: User)])
+                .with_param_names([parse_quote!(param_a)])
+        )]
+        #[case::optional_params(
+            Attributes::query(),
+            parse_quote!(async fn my_handler(ctx: Ctx, param_a: String, param_b: Option<bool>, #[default] param_c: usize)),
+            ModelAssertion::query(parse_quote!(my_handler))
+                .with_param_names([parse_quote!(param_a), parse_quote!(param_b), parse_quote!(param_c)])
+                .with_optional_params(2)
+        )]
+        #[case::no_ctx(
+            Attributes::query().with_no_ctx(),
+            parse_quote!(async fn my_handler(param_a: u32, param_b: u32)),
+            ModelAssertion::query(parse_quote!(my_handler))
+                .with_param_names([parse_quote!(param_a), parse_quote!(param_b)])
+        )]
         fn valid(
             #[case] attrs: Attributes,
             #[case] signature: Signature,
             #[case] expected: ModelAssertion,
         ) {
-            let model = analyse(Ast::new(attrs, parse_quote!(#signature { todo!() }))).unwrap();
+            let model = analyse(Ast::new(
+                attrs,
+                Span::call_site(),
+                parse_quote!(#signature { todo!() }),
+            ))
+            .unwrap();
 
             assert_eq!(model.name, expected.name);
             assert_eq!(model.rpc_name, expected.rpc_name);
             assert_eq!(model.kind, expected.kind);
+            assert_eq!(model.extractors, expected.extractors);
             assert_eq!(model.param_names, expected.param_names);
+            assert_eq!(model.optional_params, expected.optional_params);
         }
 
         #[rstest]
@@ -241,17 +539,32 @@ mod test {
             parse_quote!(async fn my_handler(self)),
             |e| matches!(e, AnalyseError::Input(InputError::SelfParameter(_))),
         )]
-        #[case::destructured_param(
+        #[case::unnamed_param(
             Attributes::query(),
-            parse_quote!(async fn my_handler(SomeType { a, b }: SomeType)),
-            |e| matches!(e, AnalyseError::Input(InputError::Destructured(_))),
+            parse_quote!(async fn my_handler(ctx: Ctx, _: SomeType)),
+            |e| matches!(e, AnalyseError::Input(InputError::Unnamed(_))),
+        )]
+        #[case::async_blocking(
+            Attributes::query().with_blocking(),
+            parse_quote!(async fn my_handler(ctx: Ctx)),
+            |e| matches!(e, AnalyseError::AsyncBlocking(_)),
+        )]
+        #[case::primitive_ctx(
+            Attributes::query(),
+            parse_quote!(async fn my_handler(a: u32, b: u32)),
+            |e| matches!(e, AnalyseError::Input(InputError::PrimitiveCtx(_))),
         )]
         fn invalid(
             #[case] attrs: Attributes,
             #[case] signature: Signature,
             #[case] err_check: fn(AnalyseError) -> bool,
         ) {
-            let err = analyse(Ast::new(attrs, parse_quote!(#signature { todo!() }))).unwrap_err();
+            let err = analyse(Ast::new(
+                attrs,
+                Span::call_site(),
+                parse_quote!(#signature { todo!() }),
+            ))
+            .unwrap_err();
             assert!(err_check(err));
         }
     }
@@ -268,25 +581,178 @@ mod test {
             &[parse_quote!(n), parse_quote!(name), parse_quote!(thing)]
         )]
         #[case::type_path(&[parse_quote!(ctx: Ctx), parse_quote!(value: some_crate::path::Type)], &[parse_quote!(value)])]
+        #[case::single_extractor(&[parse_quote!(#[ctx] db: Db), parse_quote!(n: usize)], &[parse_quote!(n)])]
+        #[case::multiple_extractors(
+            &[parse_quote!(#[ctx] db: Db), parse_quote!(n: usize), parse_quote!(#[ctx] user: User)],
+            &[parse_quote!(n)]
+        )]
+        #[case::destructured_extractor(&[parse_quote!(#[ctx] Db(db): Db), parse_quote!(n: usize)], &[parse_quote!(n)])]
+        #[case::destructured_ctx(&[parse_quote!(Ctx { db }: Ctx), parse_quote!(n: usize)], &[parse_quote!(n)])]
+        #[case::destructured_struct(
+            &[parse_quote!(ctx: Ctx), parse_quote!(SomeType { a, b }: SomeType)],
+            &[parse_quote!(some_type)]
+        )]
+        #[case::destructured_tuple_struct(
+            &[parse_quote!(ctx: Ctx), parse_quote!(GeoPoint(lat, lng): geo::GeoPoint)],
+            &[parse_quote!(geo_point)]
+        )]
+        #[case::destructured_with_extractor(
+            &[parse_quote!(#[ctx] db: Db), parse_quote!(SomeType { a, b }: SomeType)],
+            &[parse_quote!(some_type)]
+        )]
+        #[case::named_tuple(
+            &[parse_quote!(ctx: Ctx), parse_quote!(#[name = "pair"] (a, b): (u32, u32))],
+            &[parse_quote!(pair)]
+        )]
+        #[case::named_wildcard(
+            &[parse_quote!(ctx: Ctx), parse_quote!(#[name = "unused"] _: u32)],
+            &[parse_quote!(unused)]
+        )]
+        #[case::renamed(
+            &[parse_quote!(ctx: Ctx), parse_quote!(#[name = "count"] n: usize)],
+            &[parse_quote!(count)]
+        )]
         fn valid<'a>(
             #[case] inputs: impl IntoIterator<Item = &'a FnArg>,
             #[case] expected: &[Ident],
         ) {
-            let inputs = process_inputs(inputs.into_iter()).unwrap();
-            assert_eq!(inputs, expected);
+            let (_, param_names, _) = process_inputs(inputs.into_iter(), false).unwrap();
+            assert_eq!(param_names, expected);
+        }
+
+        #[test]
+        fn extractors() {
+            let inputs: [FnArg; 3] = [
+                parse_quote!(#[ctx] db: Db),
+                parse_quote!(n: usize),
+                parse_quote!(#[ctx] user: User),
+            ];
+
+            let (extractors, _, _) = process_inputs(inputs.iter(), false).unwrap();
+            assert_eq!(
+                extractors,
+                [parse_quote!(db: Db), parse_quote!(user: User)] as [PatType; 2]
+            );
+        }
+
+        #[rstest]
+        #[case::none(&[parse_quote!(ctx: Ctx), parse_quote!(n: usize)], 0)]
+        #[case::option(&[parse_quote!(ctx: Ctx), parse_quote!(n: Option<usize>)], 1)]
+        #[case::option_path(&[parse_quote!(ctx: Ctx), parse_quote!(n: std::option::Option<usize>)], 1)]
+        #[case::default(&[parse_quote!(ctx: Ctx), parse_quote!(#[default] n: usize)], 1)]
+        #[case::trailing(
+            &[parse_quote!(ctx: Ctx), parse_quote!(n: usize), parse_quote!(a: Option<u32>), parse_quote!(#[default] b: u32)],
+            2
+        )]
+        #[case::not_trailing(
+            &[parse_quote!(ctx: Ctx), parse_quote!(a: Option<u32>), parse_quote!(n: usize)],
+            0
+        )]
+        #[case::optional_ctx(&[parse_quote!(ctx: Option<Ctx>), parse_quote!(n: usize)], 0)]
+        #[case::optional_extractor(&[parse_quote!(n: usize), parse_quote!(#[ctx] user: Option<User>)], 0)]
+        fn optional<'a>(
+            #[case] inputs: impl IntoIterator<Item = &'a FnArg>,
+            #[case] expected: usize,
+        ) {
+            let (_, _, optional_params) = process_inputs(inputs.into_iter(), false).unwrap();
+            assert_eq!(optional_params, expected);
         }
 
         #[rstest]
         #[case::reject_self(&[parse_quote!(self)], |e| matches!(e, InputError::SelfParameter(_)))]
         #[case::reject_self_after_input(&[parse_quote!(n: usize), parse_quote!(self)], |e| matches!(e, InputError::SelfParameter(_)))]
-        #[case::reject_wildcard(&[parse_quote!(_: usize)], |e| matches!(e, InputError::Destructured(_)))]
-        #[case::reject_destructuring(&[parse_quote!(SomeType { a, b }: SomeType)], |e| matches!(e, InputError::Destructured(_)))]
+        #[case::reject_wildcard(&[parse_quote!(ctx: Ctx), parse_quote!(_: usize)], |e| matches!(e, InputError::Unnamed(_)))]
+        #[case::reject_unnamed_tuple(
+            &[parse_quote!(ctx: Ctx), parse_quote!((a, b): (u32, u32))],
+            |e| matches!(e, InputError::Unnamed(_))
+        )]
+        #[case::reject_invalid_name(
+            &[parse_quote!(ctx: Ctx), parse_quote!(#[name = "not valid"] n: usize)],
+            |e| matches!(e, InputError::InvalidName(_))
+        )]
+        #[case::reject_name_list(
+            &[parse_quote!(ctx: Ctx), parse_quote!(#[name(count)] n: usize)],
+            |e| matches!(e, InputError::InvalidName(_))
+        )]
+        #[case::reject_duplicate_name(
+            &[parse_quote!(ctx: Ctx), parse_quote!(Point { x, y }: Point), parse_quote!(point: Point)],
+            |e| matches!(e, InputError::DuplicateName(_))
+        )]
+        #[case::reject_primitive_ctx(
+            &[parse_quote!(a: u32), parse_quote!(b: u32)],
+            |e| matches!(e, InputError::PrimitiveCtx(_))
+        )]
+        #[case::reject_primitive_reference_ctx(
+            &[parse_quote!(name: &str)],
+            |e| matches!(e, InputError::PrimitiveCtx(_))
+        )]
+        #[case::reject_default_not_trailing(
+            &[parse_quote!(ctx: Ctx), parse_quote!(#[default] a: u32), parse_quote!(n: usize)],
+            |e| matches!(e, InputError::DefaultNotTrailing(_))
+        )]
+        #[case::reject_default_before_trailing(
+            &[parse_quote!(ctx: Ctx), parse_quote!(#[default] a: u32), parse_quote!(n: usize), parse_quote!(b: Option<u32>)],
+            |e| matches!(e, InputError::DefaultNotTrailing(_))
+        )]
+        #[case::reject_named_ctx(
+            &[parse_quote!(#[name = "db"] Ctx { db }: Ctx), parse_quote!(n: usize)],
+            |e| matches!(e, InputError::CtxAttribute(_))
+        )]
+        #[case::reject_default_ctx(
+            &[parse_quote!(#[default] ctx: Ctx)],
+            |e| matches!(e, InputError::CtxAttribute(_))
+        )]
+        #[case::reject_named_extractor(
+            &[parse_quote!(n: usize), parse_quote!(#[ctx] #[name = "user"] user: User)],
+            |e| matches!(e, InputError::CtxAttribute(_))
+        )]
         fn fail<'a>(
             #[case] inputs: impl IntoIterator<Item = &'a FnArg>,
             #[case] err_check: fn(InputError) -> bool,
         ) {
-            let err = process_inputs(inputs.into_iter()).unwrap_err();
+            let err = process_inputs(inputs.into_iter(), false).unwrap_err();
             assert!(err_check(err));
         }
+
+        #[rstest]
+        #[case::empty(&[], &[])]
+        #[case::single(&[parse_quote!(n: usize)], &[parse_quote!(n)])]
+        #[case::multiple(
+            &[parse_quote!(a: u32), parse_quote!(b: u32)],
+            &[parse_quote!(a), parse_quote!(b)]
+        )]
+        #[case::destructured(
+            &[parse_quote!(SomeType { a, b }: SomeType), parse_quote!(n: usize)],
+            &[parse_quote!(some_type), parse_quote!(n)]
+        )]
+        fn no_ctx<'a>(
+            #[case] inputs: impl IntoIterator<Item = &'a FnArg>,
+            #[case] expected: &[Ident],
+        ) {
+            let (extractors, param_names, _) = process_inputs(inputs.into_iter(), true).unwrap();
+            assert!(extractors.is_empty());
+            assert_eq!(param_names, expected);
+        }
+
+        #[test]
+        fn reject_ctx_with_no_ctx() {
+            let inputs: [FnArg; 2] = [parse_quote!(#[ctx] db: Db), parse_quote!(n: usize)];
+
+            let err = process_inputs(inputs.iter(), true).unwrap_err();
+            assert!(matches!(err, InputError::CtxWithNoCtx(_)));
+        }
+
+        #[rstest]
+        #[case::single("Point", "point")]
+        #[case::camel("GeoPoint", "geo_point")]
+        #[case::acronym_prefix("HTTPRequest", "http_request")]
+        #[case::acronym_suffix("UserID", "user_id")]
+        #[case::acronym_only("URL", "url")]
+        #[case::acronym_middle("ParseJSONValue", "parse_json_value")]
+        #[case::digits("Vec3Point", "vec3_point")]
+        #[case::underscore("Geo_Point", "geo_point")]
+        fn snake_case(#[case] ident: &str, #[case] expected: &str) {
+            assert_eq!(to_snake_case(ident), expected);
+        }
     }
 }
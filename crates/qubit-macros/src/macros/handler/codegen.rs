@@ -1,5 +1,6 @@
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::{Visibility, parse_quote};
 
 use super::lower::Ir;
 
@@ -7,25 +8,73 @@ pub fn codegen(ir: Ir) -> TokenStream {
     let Ir {
         name,
         kind,
+        insert,
         rpc_name,
+        rate_limit,
+        timeout,
+        max_concurrency,
         param_names,
-        handler,
+        optional_params,
+        blocking,
+        ctx_ty,
+        mut handler,
     } = ir;
 
+    // The handler is replaced with a type of the same name, so the function itself is only
+    // visible to the registration.
+    let vis = std::mem::replace(&mut handler.vis, Visibility::Inherited);
+    let docs = handler
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .collect::<Vec<_>>();
+
+    // Handlers without a `ctx` accept the context of the router directly.
+    let ctx_ty = ctx_ty.unwrap_or_else(|| parse_quote!(__Ctx));
+
     quote! {
-        #handler
-
-        const _: () = {
-            #[::qubit::__private::linkme::distributed_slice(::qubit::__private::HANDLER_DEFINITIONS)]
-            #[linkme(crate = ::qubit::__private::linkme)]
-            static HANDLER_DEFINITION: fn() -> (::core::any::TypeId, ::qubit::__private::HandlerMeta) = || (
-                ::core::any::Any::type_id(&#name),
-                ::qubit::__private::HandlerMeta {
+        #(#docs)*
+        #[allow(non_camel_case_types)]
+        #[derive(::core::clone::Clone, ::core::marker::Copy, ::core::fmt::Debug)]
+        #vis struct #name;
+
+        impl ::qubit::__private::HandlerMetadata for #name {
+            fn meta() -> &'static ::qubit::__private::HandlerMeta {
+                static META: ::qubit::__private::HandlerMeta = ::qubit::__private::HandlerMeta {
                     kind: #kind,
                     name: #rpc_name,
-                    param_names: &[#(#param_names),*]
-                }
-            );
-        };
+                    param_names: &[#(#param_names),*],
+                    optional_params: #optional_params,
+                    rate_limit: #rate_limit,
+                    timeout: #timeout,
+                    max_concurrency: #max_concurrency,
+                    blocking: #blocking,
+                };
+
+                &META
+            }
+        }
+
+        impl<__Ctx> ::qubit::__private::RouterHandler<__Ctx> for #name
+        where
+            __Ctx: 'static
+                + ::core::clone::Clone
+                + ::core::marker::Send
+                + ::core::marker::Sync,
+            #ctx_ty: ::qubit::FromRequestExtensions<__Ctx>,
+        {
+            fn register(self, router: ::qubit::Router<__Ctx>) -> ::qubit::Router<__Ctx> {
+                #handler
+
+                // Bind the insert function separately, so that any unsatisfied bounds are reported
+                // against the handler kind, rather than the entire attribute.
+                let insert = #insert;
+                insert(
+                    router,
+                    #name,
+                    <Self as ::qubit::__private::HandlerMetadata>::meta(),
+                )
+            }
+        }
     }
 }
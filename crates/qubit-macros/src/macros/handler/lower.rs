@@ -1,10 +1,23 @@
+use std::time::Duration;
+
+use proc_macro2::Span;
 use quote::quote;
-use syn::{Expr, Ident, ItemFn, parse_quote};
+use syn::{
+    Error, Expr, FnArg, Ident, ItemFn, Pat, PatType, Stmt, Type, parse_quote, parse_quote_spanned,
+};
+
+use super::{
+    analyse::{Model, is_ctx, is_default, is_name},
+    parse::{HandlerKind, RateLimit},
+};
 
-use super::{analyse::Model, parse::HandlerKind};
+pub fn lower(model: Model) -> Result<Ir, Error> {
+    let handler = bind_params(
+        collapse_extractors(model.handler, model.extractors, model.no_ctx),
+        &model.param_names,
+    );
 
-pub fn lower(model: Model) -> Ir {
-    Ir {
+    Ok(Ir {
         name: model.name,
         kind: {
             let variant = match model.kind {
@@ -15,21 +28,151 @@ pub fn lower(model: Model) -> Ir {
 
             parse_quote!(::qubit::__private::HandlerKind::#variant)
         },
+        insert: insert_fn(model.kind, model.kind_span),
         rpc_name: model.rpc_name,
+        rate_limit: match model.rate_limit {
+            Some(RateLimit {
+                requests,
+                period_secs,
+            }) => parse_quote!(::core::option::Option::Some(
+                ::qubit::__private::RateLimit::new(
+                    #requests,
+                    ::core::time::Duration::from_secs(#period_secs),
+                )
+            )),
+            None => parse_quote!(::core::option::Option::None),
+        },
+        timeout: match model.timeout {
+            Some(timeout) => {
+                let millis = millis(timeout)?;
+                parse_quote!(::core::option::Option::Some(
+                    ::core::time::Duration::from_millis(#millis)
+                ))
+            }
+            None => parse_quote!(::core::option::Option::None),
+        },
+        max_concurrency: match (model.max_concurrency, model.max_wait) {
+            (Some(max), Some(max_wait)) => {
+                let millis = millis(max_wait)?;
+                parse_quote!(::core::option::Option::Some(
+                    ::qubit::__private::ConcurrencyLimit::new(#max)
+                        .queue(::core::time::Duration::from_millis(#millis))
+                ))
+            }
+            (Some(max), None) => parse_quote!(::core::option::Option::Some(
+                ::qubit::__private::ConcurrencyLimit::new(#max)
+            )),
+            (None, _) => parse_quote!(::core::option::Option::None),
+        },
         param_names: model
             .param_names
             .into_iter()
             .map(|param| param.to_string())
             .collect(),
-        handler: model.handler,
+        optional_params: model.optional_params,
+        blocking: model.blocking,
+        ctx_ty: handler.sig.inputs.first().map(|arg| match arg {
+            FnArg::Typed(arg) => (*arg.ty).clone(),
+            FnArg::Receiver(_) => unreachable!("receivers are rejected during analysis"),
+        }),
+        handler,
+    })
+}
+
+/// Number of milliseconds in `duration`, which is emitted into the generated code.
+fn millis(duration: Duration) -> Result<u64, Error> {
+    u64::try_from(duration.as_millis())
+        .map_err(|_| Error::new(Span::call_site(), "duration is too long"))
+}
+
+/// Function used to insert the handler into the router, which ensures that the handler's return
+/// type is suitable for its kind. Any error is reported against the kind in the attributes.
+fn insert_fn(kind: HandlerKind, span: Span) -> Expr {
+    match kind {
+        HandlerKind::Query | HandlerKind::Mutation => {
+            parse_quote_spanned!(span=> ::qubit::__private::insert_response_handler)
+        }
+        HandlerKind::Subscription => {
+            parse_quote_spanned!(span=> ::qubit::__private::insert_stream_handler)
+        }
+    }
+}
+
+/// Move any `#[ctx]` parameters to the start of the handler, collapsing multiple into a single
+/// `Extractors` parameter, so that the first parameter is always the `ctx`. Handlers without a
+/// `ctx` are given an empty `Extractors`, which can be built from any context.
+fn collapse_extractors(mut handler: ItemFn, extractors: Vec<PatType>, no_ctx: bool) -> ItemFn {
+    if extractors.is_empty() && !no_ctx {
+        return handler;
     }
+
+    let ctx: FnArg = match extractors.as_slice() {
+        [extractor] => FnArg::Typed(extractor.clone()),
+        extractors => {
+            let pats = extractors.iter().map(|extractor| &extractor.pat);
+            let tys = extractors.iter().map(|extractor| &extractor.ty);
+
+            parse_quote!(::qubit::Extractors((#(#pats,)*)): ::qubit::Extractors<(#(#tys,)*)>)
+        }
+    };
+
+    let params = handler
+        .sig
+        .inputs
+        .iter()
+        .filter(|arg| !matches!(arg, FnArg::Typed(arg) if is_ctx(arg)))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    handler.sig.inputs = std::iter::once(ctx).chain(params).collect();
+    handler
+}
+
+/// Replace any destructured parameters with their name, and instead destructure them at the start
+/// of the handler. Parameters marked with `#[default]` are accepted as an `Option`, and replaced
+/// with their default value when missing. Any `#[name]` and `#[default]` attributes are removed.
+fn bind_params(mut handler: ItemFn, param_names: &[Ident]) -> ItemFn {
+    let mut bindings = Vec::<Stmt>::new();
+
+    // The first parameter is always the `ctx`, once extractors have been collapsed.
+    for (arg, name) in handler.sig.inputs.iter_mut().skip(1).zip(param_names) {
+        let FnArg::Typed(arg) = arg else {
+            continue;
+        };
+
+        let default = arg.attrs.iter().any(is_default);
+        arg.attrs.retain(|attr| !is_name(attr) && !is_default(attr));
+
+        if default {
+            let ty = &arg.ty;
+            *arg.ty = parse_quote!(::core::option::Option<#ty>);
+            bindings.push(parse_quote!(let #name = #name.unwrap_or_default();));
+        }
+
+        if !matches!(*arg.pat, Pat::Ident(_)) {
+            let pat = std::mem::replace(&mut *arg.pat, parse_quote!(#name));
+            bindings.push(parse_quote!(let #pat = #name;));
+        }
+    }
+
+    handler.block.stmts.splice(0..0, bindings);
+    handler
 }
 
 pub struct Ir {
     pub name: Ident,
     pub kind: Expr,
+    /// Function which inserts the handler into the router.
+    pub insert: Expr,
     pub rpc_name: String,
+    pub rate_limit: Expr,
+    pub timeout: Expr,
+    pub max_concurrency: Expr,
     pub param_names: Vec<String>,
+    pub optional_params: usize,
+    pub blocking: bool,
+    /// Type of the `ctx` parameter, if the handler has one.
+    pub ctx_ty: Option<Type>,
     pub handler: ItemFn,
 }
 
@@ -130,14 +273,161 @@ mod test {
         let ir = lower(Model {
             rpc_name: model.rpc_name,
             kind: model.kind,
+            kind_span: Span::call_site(),
+            rate_limit: None,
+            timeout: None,
+            max_concurrency: None,
+            max_wait: None,
+            no_ctx: false,
+            blocking: false,
+            extractors: model.extractors,
             param_names: model.param_names,
+            optional_params: model.optional_params,
             handler: parse_quote!(fn #name() {}),
             name,
-        });
+        })
+        .unwrap();
 
         assert_eq!(ir.name, expected.name);
         assert_eq!(ir.kind, expected.kind);
         assert_eq!(ir.rpc_name, expected.rpc_name);
         assert_eq!(ir.param_names, expected.param_names);
     }
+
+    #[rstest]
+    #[case::query(
+        HandlerKind::Query,
+        parse_quote!(::qubit::__private::insert_response_handler),
+    )]
+    #[case::mutation(
+        HandlerKind::Mutation,
+        parse_quote!(::qubit::__private::insert_response_handler),
+    )]
+    #[case::subscription(
+        HandlerKind::Subscription,
+        parse_quote!(::qubit::__private::insert_stream_handler),
+    )]
+    fn insert(#[case] kind: HandlerKind, #[case] expected: Expr) {
+        assert_eq!(insert_fn(kind, Span::call_site()), expected);
+    }
+
+    #[rstest]
+    #[case::no_extractors(
+        parse_quote!(fn my_handler(ctx: Ctx, n: usize) {}),
+        vec![],
+        parse_quote!(fn my_handler(ctx: Ctx, n: usize) {}),
+    )]
+    #[case::single_extractor(
+        parse_quote!(fn my_handler(n: usize, #[ctx] db: Db) {}),
+        vec![parse_quote!(db: Db)],
+        parse_quote!(fn my_handler(db: Db, n: usize) {}),
+    )]
+    #[case::multiple_extractors(
+        parse_quote!(fn my_handler(#[ctx] db: Db, n: usize, #[ctx] user: User) {}),
+        vec![parse_quote!(db: Db), parse_quote!(user: User)],
+        parse_quote!(fn my_handler(::qubit::Extractors((db, user,)): ::qubit::Extractors<(Db, User,)>, n: usize) {}),
+    )]
+    fn collapse(
+        #[case] handler: ItemFn,
+        #[case] extractors: Vec<PatType>,
+        #[case] expected: ItemFn,
+    ) {
+        assert_eq!(collapse_extractors(handler, extractors, false), expected);
+    }
+
+    #[rstest]
+    #[case::no_params(
+        parse_quote!(fn my_handler() {}),
+        parse_quote!(fn my_handler(::qubit::Extractors(()): ::qubit::Extractors<()>) {}),
+    )]
+    #[case::params(
+        parse_quote!(fn my_handler(a: u32, b: u32) {}),
+        parse_quote!(fn my_handler(::qubit::Extractors(()): ::qubit::Extractors<()>, a: u32, b: u32) {}),
+    )]
+    fn no_ctx(#[case] handler: ItemFn, #[case] expected: ItemFn) {
+        assert_eq!(collapse_extractors(handler, Vec::new(), true), expected);
+    }
+
+    #[rstest]
+    #[case::no_params(parse_quote!(fn my_handler(ctx: Ctx) {}), &[], parse_quote!(fn my_handler(ctx: Ctx) {}))]
+    #[case::ident(
+        parse_quote!(fn my_handler(ctx: Ctx, n: usize) {}),
+        &[parse_quote!(n)],
+        parse_quote!(fn my_handler(ctx: Ctx, n: usize) {}),
+    )]
+    #[case::renamed(
+        parse_quote!(fn my_handler(ctx: Ctx, #[name = "count"] n: usize) {}),
+        &[parse_quote!(count)],
+        parse_quote!(fn my_handler(ctx: Ctx, n: usize) {}),
+    )]
+    #[case::destructured(
+        parse_quote!(fn my_handler(Ctx { db }: Ctx, Point { x, y }: Point, n: usize) { x + y }),
+        &[parse_quote!(point), parse_quote!(n)],
+        parse_quote!(fn my_handler(Ctx { db }: Ctx, point: Point, n: usize) {
+            let Point { x, y } = point;
+            x + y
+        }),
+    )]
+    #[case::default(
+        parse_quote!(fn my_handler(ctx: Ctx, #[default] limit: u32) {}),
+        &[parse_quote!(limit)],
+        parse_quote!(fn my_handler(ctx: Ctx, limit: ::core::option::Option<u32>) {
+            let limit = limit.unwrap_or_default();
+        }),
+    )]
+    #[case::default_destructured(
+        parse_quote!(fn my_handler(ctx: Ctx, #[default] Point { x, y }: Point) { x + y }),
+        &[parse_quote!(point)],
+        parse_quote!(fn my_handler(ctx: Ctx, point: ::core::option::Option<Point>) {
+            let point = point.unwrap_or_default();
+            let Point { x, y } = point;
+            x + y
+        }),
+    )]
+    fn bind(#[case] handler: ItemFn, #[case] param_names: &[Ident], #[case] expected: ItemFn) {
+        assert_eq!(bind_params(handler, param_names), expected);
+    }
+
+    #[rstest]
+    #[case::no_params(parse_quote!(fn my_handler() {}), vec![], None)]
+    #[case::ctx(
+        parse_quote!(fn my_handler(ctx: Ctx, n: usize) {}),
+        vec![],
+        Some(parse_quote!(Ctx)),
+    )]
+    #[case::single_extractor(
+        parse_quote!(fn my_handler(n: usize, #[ctx] db: Db) {}),
+        vec![parse_quote!(db: Db)],
+        Some(parse_quote!(Db)),
+    )]
+    #[case::multiple_extractors(
+        parse_quote!(fn my_handler(#[ctx] db: Db, n: usize, #[ctx] user: User) {}),
+        vec![parse_quote!(db: Db), parse_quote!(user: User)],
+        Some(parse_quote!(::qubit::Extractors<(Db, User,)>)),
+    )]
+    fn ctx_ty(
+        #[case] handler: ItemFn,
+        #[case] extractors: Vec<PatType>,
+        #[case] expected: Option<Type>,
+    ) {
+        let ir = lower(Model {
+            name: parse_quote!(my_handler),
+            rpc_name: "my_handler".to_string(),
+            kind: HandlerKind::Query,
+            kind_span: Span::call_site(),
+            rate_limit: None,
+            timeout: None,
+            max_concurrency: None,
+            max_wait: None,
+            no_ctx: false,
+            blocking: false,
+            extractors,
+            param_names: Vec::new(),
+            optional_params: 0,
+            handler,
+        })
+        .unwrap();
+
+        assert_eq!(ir.ctx_ty, expected);
+    }
 }
@@ -8,9 +8,11 @@ mod parse;
 
 use self::{analyse::analyse, codegen::codegen, lower::lower, parse::parse};
 
+pub use self::analyse::{is_default, is_name, param_name};
+
 pub fn handler(attrs: TokenStream, item: TokenStream) -> Result<TokenStream, Error> {
     let ast = parse(attrs, item)?;
     let model = analyse(ast)?;
-    let ir = lower(model);
+    let ir = lower(model)?;
     Ok(codegen(ir))
 }
@@ -1,15 +1,30 @@
-use proc_macro2::{Span, TokenStream};
-use syn::{Error, ItemFn, LitStr, meta::ParseNestedMeta, spanned::Spanned};
+use std::time::Duration;
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use syn::{Error, ItemFn, LitInt, LitStr, meta::ParseNestedMeta, spanned::Spanned};
 
 /// Parse the provided token streams into an AST.
 pub fn parse(tokens_attrs: TokenStream, tokens_item: TokenStream) -> Result<Ast, Error> {
     // Parse the attributes.
-    let attrs = Attributes::parse(tokens_attrs)?;
+    let attrs = Attributes::parse(tokens_attrs.clone())?;
+
+    // Locate the handler kind, so that errors relating to it can point to the attribute.
+    let kind_span = tokens_attrs
+        .into_iter()
+        .find_map(|token| match token {
+            TokenTree::Ident(ident)
+                if HandlerKind::try_from(ident.to_string().as_str()).is_ok() =>
+            {
+                Some(ident.span())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(Span::call_site);
 
     // Parse the handler.
     let handler = syn::parse2(tokens_item)?;
 
-    Ok(Ast::new(attrs, handler))
+    Ok(Ast::new(attrs, kind_span, handler))
 }
 
 /// Simple representation of a handler, suitable for further processing by a macro.
@@ -18,14 +33,21 @@ pub struct Ast {
     /// Provided attributes.
     pub attrs: Attributes,
 
+    /// Span of the handler kind within the attributes.
+    pub kind_span: Span,
+
     /// Handler implementation.
     pub handler: ItemFn,
 }
 
 impl Ast {
     /// Create a new AST instance.
-    pub fn new(attrs: Attributes, handler: ItemFn) -> Self {
-        Self { attrs, handler }
+    pub fn new(attrs: Attributes, kind_span: Span, handler: ItemFn) -> Self {
+        Self {
+            attrs,
+            kind_span,
+            handler,
+        }
     }
 }
 
@@ -38,6 +60,25 @@ pub struct Attributes {
 
     /// Kind of the handler.
     pub kind: HandlerKind,
+
+    /// Maximum rate at which each client may call the handler.
+    pub rate_limit: Option<RateLimit>,
+
+    /// Maximum duration that a call to the handler may run for.
+    pub timeout: Option<Duration>,
+
+    /// Maximum number of calls to the handler that may run at once.
+    pub max_concurrency: Option<usize>,
+
+    /// Maximum duration that a call may wait for a running call to complete, once the concurrency
+    /// limit has been reached.
+    pub max_wait: Option<Duration>,
+
+    /// Whether the handler doesn't take a `ctx`, so every parameter is an RPC parameter.
+    pub no_ctx: bool,
+
+    /// Whether the handler runs on the blocking thread pool.
+    pub blocking: bool,
 }
 
 impl Attributes {
@@ -66,6 +107,12 @@ impl Attributes {
         Self {
             kind: HandlerKind::Query,
             name: None,
+            rate_limit: None,
+            timeout: None,
+            max_concurrency: None,
+            max_wait: None,
+            no_ctx: false,
+            blocking: false,
         }
     }
 
@@ -73,6 +120,12 @@ impl Attributes {
         Self {
             kind: HandlerKind::Mutation,
             name: None,
+            rate_limit: None,
+            timeout: None,
+            max_concurrency: None,
+            max_wait: None,
+            no_ctx: false,
+            blocking: false,
         }
     }
 
@@ -80,6 +133,12 @@ impl Attributes {
         Self {
             kind: HandlerKind::Subscription,
             name: None,
+            rate_limit: None,
+            timeout: None,
+            max_concurrency: None,
+            max_wait: None,
+            no_ctx: false,
+            blocking: false,
         }
     }
 
@@ -87,19 +146,81 @@ impl Attributes {
         self.name = Some(name.as_ref().to_string());
         self
     }
+
+    pub(crate) fn with_rate_limit(mut self, requests: u32, period_secs: u64) -> Self {
+        self.rate_limit = Some(RateLimit {
+            requests,
+            period_secs,
+        });
+        self
+    }
+
+    pub(crate) fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub(crate) fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency);
+        self
+    }
+
+    pub(crate) fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
+    }
+
+    pub(crate) fn with_no_ctx(mut self) -> Self {
+        self.no_ctx = true;
+        self
+    }
+
+    pub(crate) fn with_blocking(mut self) -> Self {
+        self.blocking = true;
+        self
+    }
 }
 
 #[derive(Clone, Debug, Default)]
 struct AttributesBuilder {
     name: Option<String>,
     kind: Option<HandlerKind>,
+    rate_limit: Option<RateLimit>,
+    timeout: Option<Duration>,
+    max_concurrency: Option<usize>,
+    max_wait: Option<Duration>,
+    no_ctx: bool,
+    blocking: bool,
 }
 
 impl AttributesBuilder {
     fn build(self) -> Result<Attributes, AttributesBuilderError> {
+        let kind = self.kind.ok_or(AttributesBuilderError::KindRequired)?;
+
+        // Subscriptions are long-lived, so a timeout doesn't make sense.
+        if kind == HandlerKind::Subscription && self.timeout.is_some() {
+            return Err(AttributesBuilderError::SubscriptionTimeout);
+        }
+
+        // Subscriptions return a stream, so there's nothing to block on.
+        if kind == HandlerKind::Subscription && self.blocking {
+            return Err(AttributesBuilderError::SubscriptionBlocking);
+        }
+
+        // Calls can only wait if there's a limit to wait for.
+        if self.max_wait.is_some() && self.max_concurrency.is_none() {
+            return Err(AttributesBuilderError::MaxWaitWithoutConcurrency);
+        }
+
         Ok(Attributes {
             name: self.name,
-            kind: self.kind.ok_or(AttributesBuilderError::KindRequired)?,
+            kind,
+            rate_limit: self.rate_limit,
+            timeout: self.timeout,
+            max_concurrency: self.max_concurrency,
+            max_wait: self.max_wait,
+            no_ctx: self.no_ctx,
+            blocking: self.blocking,
         })
     }
 
@@ -135,6 +256,90 @@ impl AttributesBuilder {
             return Ok(());
         }
 
+        if meta.path.is_ident("rate_limit") {
+            let path_span = meta.path.span();
+
+            // Parse the limit from a string, such as `"10/min"`.
+            let rate_limit = RateLimit::parse(&meta.value()?.parse::<LitStr>()?)?;
+
+            // Prevent redefining the rate limit if it's already been passed.
+            if self.rate_limit.is_some() {
+                return Err(AttributesParseError::RateLimitProvided(path_span));
+            }
+
+            self.rate_limit = Some(rate_limit);
+            return Ok(());
+        }
+
+        if meta.path.is_ident("timeout") {
+            let path_span = meta.path.span();
+
+            // Parse the duration from a string, such as `"5s"`.
+            let timeout = parse_duration(&meta.value()?.parse::<LitStr>()?)?;
+
+            // Prevent redefining the timeout if it's already been passed.
+            if self.timeout.is_some() {
+                return Err(AttributesParseError::TimeoutProvided(path_span));
+            }
+
+            self.timeout = Some(timeout);
+            return Ok(());
+        }
+
+        if meta.path.is_ident("max_concurrency") {
+            let path_span = meta.path.span();
+
+            // Parse the limit as an integer, which must allow at least one call.
+            let lit = meta.value()?.parse::<LitInt>()?;
+            let max_concurrency = lit.base10_parse::<usize>()?;
+            if max_concurrency == 0 {
+                return Err(Error::new(lit.span(), "`max_concurrency` must be at least 1").into());
+            }
+
+            // Prevent redefining the limit if it's already been passed.
+            if self.max_concurrency.is_some() {
+                return Err(AttributesParseError::MaxConcurrencyProvided(path_span));
+            }
+
+            self.max_concurrency = Some(max_concurrency);
+            return Ok(());
+        }
+
+        if meta.path.is_ident("max_wait") {
+            let path_span = meta.path.span();
+
+            // Parse the duration from a string, such as `"500ms"`.
+            let max_wait = parse_duration(&meta.value()?.parse::<LitStr>()?)?;
+
+            // Prevent redefining the wait if it's already been passed.
+            if self.max_wait.is_some() {
+                return Err(AttributesParseError::MaxWaitProvided(path_span));
+            }
+
+            self.max_wait = Some(max_wait);
+            return Ok(());
+        }
+
+        if meta.path.is_ident("no_ctx") {
+            // Prevent providing the flag multiple times.
+            if self.no_ctx {
+                return Err(AttributesParseError::NoCtxProvided(meta.path.span()));
+            }
+
+            self.no_ctx = true;
+            return Ok(());
+        }
+
+        if meta.path.is_ident("blocking") {
+            // Prevent providing the flag multiple times.
+            if self.blocking {
+                return Err(AttributesParseError::BlockingProvided(meta.path.span()));
+            }
+
+            self.blocking = true;
+            return Ok(());
+        }
+
         Err(AttributesParseError::UnsupportedProperty(meta.path.span()))
     }
 }
@@ -143,6 +348,12 @@ impl AttributesBuilder {
 pub enum AttributesBuilderError {
     #[error("one of `query`/`mutation`/`subscription` is required")]
     KindRequired,
+    #[error("subscriptions do not support `timeout`")]
+    SubscriptionTimeout,
+    #[error("subscriptions do not support `blocking`")]
+    SubscriptionBlocking,
+    #[error("`max_wait` requires `max_concurrency`")]
+    MaxWaitWithoutConcurrency,
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -151,6 +362,18 @@ pub enum AttributesParseError {
     KindProvided(Span),
     #[error("handler name has already been provided")]
     NameProvided(Span),
+    #[error("rate limit has already been provided")]
+    RateLimitProvided(Span),
+    #[error("timeout has already been provided")]
+    TimeoutProvided(Span),
+    #[error("concurrency limit has already been provided")]
+    MaxConcurrencyProvided(Span),
+    #[error("maximum wait has already been provided")]
+    MaxWaitProvided(Span),
+    #[error("`no_ctx` has already been provided")]
+    NoCtxProvided(Span),
+    #[error("`blocking` has already been provided")]
+    BlockingProvided(Span),
     #[error("unknown attribute")]
     UnsupportedProperty(Span),
     #[error(transparent)]
@@ -163,6 +386,12 @@ impl From<AttributesParseError> for Error {
             match err {
                 AttributesParseError::KindProvided(span) => span,
                 AttributesParseError::NameProvided(span) => span,
+                AttributesParseError::RateLimitProvided(span) => span,
+                AttributesParseError::TimeoutProvided(span) => span,
+                AttributesParseError::MaxConcurrencyProvided(span) => span,
+                AttributesParseError::MaxWaitProvided(span) => span,
+                AttributesParseError::NoCtxProvided(span) => span,
+                AttributesParseError::BlockingProvided(span) => span,
                 AttributesParseError::UnsupportedProperty(span) => span,
                 AttributesParseError::ParseError(error) => return error,
             },
@@ -171,6 +400,90 @@ impl From<AttributesParseError> for Error {
     }
 }
 
+/// Maximum number of requests permitted within a period.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Number of requests allowed in each period.
+    pub requests: u32,
+
+    /// Length of the period, in seconds.
+    pub period_secs: u64,
+}
+
+impl RateLimit {
+    /// Parse a rate limit in the form `<requests>/<period>`, where the period is one of `s`, `min`,
+    /// `h` or `day` (such as `10/min`).
+    fn parse(lit: &LitStr) -> Result<Self, Error> {
+        let error = || {
+            Error::new(
+                lit.span(),
+                "rate limit must be in the form `<requests>/<period>`, where the period is one of `s`, `min`, `h` or `day` (such as `\"10/min\"`)",
+            )
+        };
+
+        let value = lit.value();
+        let (requests, period) = value.split_once('/').ok_or_else(error)?;
+
+        let requests = requests
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|requests| *requests > 0)
+            .ok_or_else(error)?;
+
+        let period_secs = match period.trim() {
+            "s" | "sec" | "second" => 1,
+            "min" | "minute" => 60,
+            "h" | "hour" => 60 * 60,
+            "day" => 24 * 60 * 60,
+            _ => return Err(error()),
+        };
+
+        Ok(Self {
+            requests,
+            period_secs,
+        })
+    }
+}
+
+/// Parse a duration in the form `<amount><unit>`, where the unit is one of `ms`, `s`, `min` or `h`
+/// (such as `5s`).
+fn parse_duration(lit: &LitStr) -> Result<Duration, Error> {
+    let error = || {
+        Error::new(
+            lit.span(),
+            "duration must be in the form `<amount><unit>`, where the unit is one of `ms`, `s`, `min` or `h` (such as `\"5s\"`)",
+        )
+    };
+
+    let value = lit.value();
+    let value = value.trim();
+    let (amount, unit) = value.split_at(
+        value
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(error)?,
+    );
+
+    let amount = amount
+        .parse::<u64>()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .ok_or_else(error)?;
+
+    let duration = match unit.trim() {
+        "ms" => Some(Duration::from_millis(amount)),
+        "s" => Some(Duration::from_secs(amount)),
+        "min" => amount.checked_mul(60).map(Duration::from_secs),
+        "h" => amount.checked_mul(60 * 60).map(Duration::from_secs),
+        _ => return Err(error()),
+    };
+
+    // The duration is emitted in milliseconds, so must fit within a `u64` of them.
+    duration
+        .filter(|duration| u64::try_from(duration.as_millis()).is_ok())
+        .ok_or_else(|| Error::new(lit.span(), "duration is too long"))
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum HandlerKind {
     Query,
@@ -204,6 +517,17 @@ mod test {
     #[case::subscription(quote!(subscription), Attributes::subscription())]
     #[case::kind_name(quote!(query, name = "other_name"), Attributes::query().with_name("other_name"))]
     #[case::name_kind(quote!(name = "other_name", mutation), Attributes::mutation().with_name("other_name"))]
+    #[case::rate_limit(quote!(mutation, rate_limit = "10/min"), Attributes::mutation().with_rate_limit(10, 60))]
+    #[case::rate_limit_seconds(quote!(query, rate_limit = "5 / s"), Attributes::query().with_rate_limit(5, 1))]
+    #[case::timeout(quote!(query, timeout = "5s"), Attributes::query().with_timeout(Duration::from_secs(5)))]
+    #[case::timeout_millis(quote!(mutation, timeout = "250ms"), Attributes::mutation().with_timeout(Duration::from_millis(250)))]
+    #[case::timeout_minutes(quote!(query, timeout = "2 min"), Attributes::query().with_timeout(Duration::from_secs(120)))]
+    #[case::max_concurrency(quote!(mutation, max_concurrency = 4), Attributes::mutation().with_max_concurrency(4))]
+    #[case::max_wait(quote!(mutation, max_concurrency = 4, max_wait = "500ms"), Attributes::mutation().with_max_concurrency(4).with_max_wait(Duration::from_millis(500)))]
+    #[case::max_concurrency_subscription(quote!(subscription, max_concurrency = 1), Attributes::subscription().with_max_concurrency(1))]
+    #[case::no_ctx(quote!(query, no_ctx), Attributes::query().with_no_ctx())]
+    #[case::blocking(quote!(query, blocking), Attributes::query().with_blocking())]
+    #[case::blocking_mutation(quote!(blocking, mutation, timeout = "1s"), Attributes::mutation().with_blocking().with_timeout(Duration::from_secs(1)))]
     fn parse_attributes(#[case] tokens: TokenStream, #[case] expected: Attributes) {
         let attrs = Attributes::parse(tokens).unwrap();
         assert_eq!(attrs, expected);
@@ -215,6 +539,26 @@ mod test {
     #[case::multiple_kind(quote!(query, mutation))]
     #[case::no_kind(quote!(name = "other_name"))]
     #[case::multiple_name(quote!(query, name = "name_1", name = "name_2"))]
+    #[case::multiple_rate_limit(quote!(query, rate_limit = "1/s", rate_limit = "2/s"))]
+    #[case::rate_limit_no_period(quote!(query, rate_limit = "10"))]
+    #[case::rate_limit_zero(quote!(query, rate_limit = "0/min"))]
+    #[case::rate_limit_unknown_period(quote!(query, rate_limit = "10/fortnight"))]
+    #[case::multiple_timeout(quote!(query, timeout = "1s", timeout = "2s"))]
+    #[case::timeout_no_unit(quote!(query, timeout = "5"))]
+    #[case::timeout_no_amount(quote!(query, timeout = "s"))]
+    #[case::timeout_unknown_unit(quote!(query, timeout = "5 weeks"))]
+    #[case::timeout_overflow_hours(quote!(query, timeout = "5124095576030432h"))]
+    #[case::timeout_overflow_millis(quote!(query, timeout = "18446744073709552s"))]
+    #[case::timeout_subscription(quote!(subscription, timeout = "5s"))]
+    #[case::multiple_max_concurrency(quote!(query, max_concurrency = 1, max_concurrency = 2))]
+    #[case::max_concurrency_zero(quote!(query, max_concurrency = 0))]
+    #[case::max_concurrency_string(quote!(query, max_concurrency = "4"))]
+    #[case::max_wait_without_concurrency(quote!(query, max_wait = "1s"))]
+    #[case::multiple_max_wait(quote!(query, max_concurrency = 1, max_wait = "1s", max_wait = "2s"))]
+    #[case::multiple_no_ctx(quote!(query, no_ctx, no_ctx))]
+    #[case::no_ctx_value(quote!(query, no_ctx = true))]
+    #[case::multiple_blocking(quote!(query, blocking, blocking))]
+    #[case::blocking_subscription(quote!(subscription, blocking))]
     fn parse_attributes_fail(#[case] tokens: TokenStream) {
         assert!(Attributes::parse(tokens).is_err());
     }
@@ -1,4 +1,5 @@
 mod handler;
+mod service;
 mod ts;
 
-pub use self::{handler::handler, ts::ts};
+pub use self::{handler::handler, service::service, ts::ts};
@@ -0,0 +1,382 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use syn::{
+    Attribute, Error, FnArg, ImplItem, ImplItemFn, ItemImpl, Meta, Receiver, Type, TypePath,
+    parse_quote, visit_mut::VisitMut,
+};
+
+use super::handler::{is_default, is_name, param_name};
+
+/// Attributes which mark a method as a handler, corresponding with the kinds of handlers.
+const HANDLER_KINDS: [&str; 3] = ["query", "mutation", "subscription"];
+
+/// Name of the generated function which produces the service's router.
+const ROUTER_FN: &str = "router";
+
+pub fn service(attrs: TokenStream, item: TokenStream) -> Result<TokenStream, Error> {
+    if !attrs.is_empty() {
+        return Err(Error::new_spanned(
+            attrs,
+            "services don't accept any arguments",
+        ));
+    }
+
+    let mut service = syn::parse2::<ItemImpl>(item)?;
+
+    if let Some((_, path, _)) = &service.trait_ {
+        return Err(Error::new_spanned(
+            path,
+            "services must be inherent impl blocks",
+        ));
+    }
+
+    if service.generics.lt_token.is_some() {
+        return Err(Error::new_spanned(
+            &service.generics,
+            "services cannot be generic",
+        ));
+    }
+
+    // Any item with the same name would conflict with the generated router.
+    if let Some(ident) = service.items.iter().find_map(|item| match item {
+        ImplItem::Fn(method) if method.sig.ident == ROUTER_FN => Some(&method.sig.ident),
+        ImplItem::Const(item) if item.ident == ROUTER_FN => Some(&item.ident),
+        _ => None,
+    }) {
+        return Err(Error::new_spanned(
+            ident,
+            "`router` is generated by `#[service]`, so it cannot be defined by the service",
+        ));
+    }
+
+    let self_ty = &*service.self_ty;
+
+    let mut handlers = Vec::new();
+    for item in &mut service.items {
+        if let ImplItem::Fn(method) = item
+            && let Some(handler) = Handler::take(method, self_ty)?
+        {
+            handlers.push(handler);
+        }
+    }
+
+    let names = handlers.iter().map(|handler| &handler.method.sig.ident);
+    let wrappers = handlers
+        .iter()
+        .map(Handler::wrapper)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The service (and any other extractors) must be available from the router's context.
+    let mut ctx_tys = Vec::<Type>::new();
+    for ty in handlers.iter().flat_map(|handler| &handler.ctx_tys) {
+        if !ctx_tys.contains(ty) {
+            ctx_tys.push(ty.clone());
+        }
+    }
+
+    Ok(quote! {
+        #service
+
+        impl #self_ty {
+            /// Router containing every handler of this service, for any context that the service
+            /// can be extracted from.
+            pub fn router<__Ctx>() -> ::qubit::Router<__Ctx>
+            where
+                __Ctx: 'static
+                    + ::core::clone::Clone
+                    + ::core::marker::Send
+                    + ::core::marker::Sync,
+                #(#ctx_tys: ::qubit::FromRequestExtensions<__Ctx>,)*
+            {
+                #(#wrappers)*
+
+                ::qubit::Router::new()
+                    #(.handler(#names))*
+            }
+        }
+    })
+}
+
+/// Method of the service which has been marked as a handler.
+struct Handler {
+    /// Attribute marking the method, which will be passed to the handler macro.
+    attr: Attribute,
+
+    /// Method, with any `Self` types replaced with the service's type.
+    method: ImplItemFn,
+
+    /// Type of the service once taken by value, followed by any other `#[ctx]` parameters.
+    ctx_tys: Vec<Type>,
+}
+
+impl Handler {
+    /// Take the handler attribute from a method (and any `#[ctx]` attributes from its
+    /// parameters), if it has been marked as a handler.
+    fn take(method: &mut ImplItemFn, self_ty: &Type) -> Result<Option<Self>, Error> {
+        let (attrs, other_attrs) = method.attrs.drain(..).partition::<Vec<_>, _>(|attr| {
+            HANDLER_KINDS.iter().any(|kind| attr.path().is_ident(kind))
+        });
+        method.attrs = other_attrs;
+
+        let attr = match attrs.as_slice() {
+            [] => return Ok(None),
+            [attr] => attr.clone(),
+            [_, attr, ..] => {
+                return Err(Error::new_spanned(
+                    attr,
+                    "methods may only be marked as a single kind of handler",
+                ));
+            }
+        };
+
+        // The handler attributes are passed through, but the service always provides a `ctx`.
+        if let Meta::List(list) = &attr.meta
+            && let Some(no_ctx) = list
+                .tokens
+                .clone()
+                .into_iter()
+                .find(|token| matches!(token, TokenTree::Ident(ident) if ident == "no_ctx"))
+        {
+            return Err(Error::new_spanned(
+                no_ctx,
+                "service handlers always take the service as their `ctx`, so cannot be `no_ctx`",
+            ));
+        }
+
+        let mut handler = method.clone();
+        ReplaceSelf(self_ty).visit_impl_item_fn_mut(&mut handler);
+
+        let Some(receiver) = handler.sig.receiver() else {
+            return Err(Error::new_spanned(
+                &method.sig,
+                "service handlers must take `self`",
+            ));
+        };
+
+        // The service is cloned for each call, so any mutation would be silently discarded.
+        if receiver.mutability.is_some()
+            || matches!(&*receiver.ty, Type::Reference(reference) if reference.mutability.is_some())
+        {
+            return Err(Error::new_spanned(
+                receiver,
+                "service handlers cannot take `self` mutably, as the service is cloned for each call",
+            ));
+        }
+
+        let ctx_tys = std::iter::once(service_ty(receiver))
+            .chain(handler.sig.inputs.iter().filter_map(|arg| match arg {
+                FnArg::Typed(arg) if is_ctx(&arg.attrs) => Some((*arg.ty).clone()),
+                _ => None,
+            }))
+            .collect();
+
+        // `#[ctx]`, `#[name]` and `#[default]` are only meaningful to the handler macro.
+        for arg in &mut method.sig.inputs {
+            if let FnArg::Typed(arg) = arg {
+                arg.attrs.retain(|attr| {
+                    !is_ctx(std::slice::from_ref(attr)) && !is_name(attr) && !is_default(attr)
+                });
+            }
+        }
+
+        Ok(Some(Self {
+            attr,
+            method: handler,
+            ctx_tys,
+        }))
+    }
+
+    /// Produce a handler function of the same name, which extracts the service as a `#[ctx]`
+    /// parameter and calls the method with every other parameter.
+    fn wrapper(&self) -> Result<TokenStream, Error> {
+        let kind = self.attr.path();
+        let handler_attrs = match &self.attr.meta {
+            Meta::Path(_) => quote!(#kind),
+            Meta::List(list) => {
+                let tokens = &list.tokens;
+                quote!(#kind, #tokens)
+            }
+            Meta::NameValue(meta) => {
+                return Err(Error::new_spanned(
+                    meta,
+                    "expected handler attributes in parentheses",
+                ));
+            }
+        };
+
+        let sig = &self.method.sig;
+        let receiver = sig.receiver().expect("receiver checked when taken");
+        let service_ty = service_ty(receiver);
+
+        let mut inputs = vec![quote!(#[ctx] __service: #service_ty)];
+        let mut params = Vec::new();
+        for arg in sig.inputs.iter().skip(1) {
+            let FnArg::Typed(arg) = arg else {
+                unreachable!("only the first parameter may be a receiver");
+            };
+
+            // Destructured parameters are bound to their name, and destructured by the method.
+            let ident = param_name(arg)?;
+
+            let attrs = &arg.attrs;
+            let ty = &arg.ty;
+            inputs.push(quote!(#(#attrs)* #ident: #ty));
+            params.push(ident);
+        }
+
+        let name = &sig.ident;
+        let asyncness = &sig.asyncness;
+        let output = &sig.output;
+        let await_call = asyncness.map(|_| quote!(.await));
+
+        Ok(quote! {
+            #[::qubit::handler(#handler_attrs)]
+            #asyncness fn #name(#(#inputs),*) #output {
+                __service.#name(#(#params),*) #await_call
+            }
+        })
+    }
+}
+
+/// Type of the service when it is taken by value, such as `Self` for `&self`, or `Arc<Self>` for
+/// `self: Arc<Self>`.
+fn service_ty(receiver: &Receiver) -> Type {
+    match &*receiver.ty {
+        Type::Reference(reference) => (*reference.elem).clone(),
+        ty => ty.clone(),
+    }
+}
+
+/// Whether the attributes include `#[ctx]`.
+fn is_ctx(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("ctx"))
+}
+
+/// Replace every `Self` type (including paths such as `Self::Output`) with the service's type, as
+/// the handlers are defined outside of the impl block.
+struct ReplaceSelf<'a>(&'a Type);
+
+impl VisitMut for ReplaceSelf<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(TypePath { qself: None, path }) = ty
+            && path.leading_colon.is_none()
+            && let Some(first) = path.segments.first()
+            && first.ident == "Self"
+        {
+            if path.segments.len() == 1 {
+                *ty = self.0.clone();
+                return;
+            }
+
+            let service_ty = self.0;
+            let rest = path.segments.iter().skip(1);
+            *ty = parse_quote!(<#service_ty>::#(#rest)::*);
+        }
+
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_block_mut(&mut self, _block: &mut syn::Block) {
+        // Only the signature is used by the handler.
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn take(mut method: ImplItemFn) -> Result<Option<Handler>, Error> {
+        Handler::take(&mut method, &parse_quote!(UserService))
+    }
+
+    #[rstest]
+    #[case::reference(parse_quote!(#[query] fn get(&self) {}), parse_quote!(UserService))]
+    #[case::value(parse_quote!(#[query] fn get(self) {}), parse_quote!(UserService))]
+    #[case::arc(
+        parse_quote!(#[query] fn get(self: Arc<Self>) {}),
+        parse_quote!(Arc<UserService>),
+    )]
+    fn receiver(#[case] method: ImplItemFn, #[case] expected: Type) {
+        let handler = take(method).unwrap().unwrap();
+        assert_eq!(handler.ctx_tys, [expected]);
+    }
+
+    #[test]
+    fn extractors() {
+        let mut method: ImplItemFn = parse_quote!(
+            #[mutation]
+            async fn update(&self, #[ctx] user: User, #[default] name: String) {}
+        );
+        let handler = Handler::take(&mut method, &parse_quote!(UserService))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            handler.ctx_tys,
+            [parse_quote!(UserService), parse_quote!(User)] as [Type; 2]
+        );
+
+        // The method itself no longer has any handler attributes.
+        assert_eq!(
+            method,
+            parse_quote!(
+                async fn update(&self, user: User, name: String) {}
+            )
+        );
+    }
+
+    #[test]
+    fn unmarked() {
+        assert!(
+            take(parse_quote!(
+                fn get(&self) {}
+            ))
+            .unwrap()
+            .is_none()
+        );
+    }
+
+    #[rstest]
+    #[case::no_receiver(parse_quote!(#[query] fn get() {}))]
+    #[case::multiple_kinds(parse_quote!(#[query] #[mutation] fn get(&self) {}))]
+    #[case::mutable_reference(parse_quote!(#[query] fn get(&mut self) {}))]
+    #[case::mutable_value(parse_quote!(#[query] fn get(mut self) {}))]
+    #[case::mutable_typed(parse_quote!(#[query] fn get(self: &mut Self) {}))]
+    #[case::no_ctx(parse_quote!(#[query(no_ctx)] fn get(&self) {}))]
+    #[case::no_ctx_with_others(parse_quote!(#[query(name = "get", no_ctx)] fn get(&self) {}))]
+    fn invalid(#[case] method: ImplItemFn) {
+        assert!(take(method).is_err());
+    }
+
+    #[test]
+    fn replace_self() {
+        let handler = take(parse_quote!(
+            #[query]
+            fn get(&self, other: Option<Self>, id: Self::Id) -> Vec<Self> {
+                Self::new()
+            }
+        ))
+        .unwrap()
+        .unwrap();
+
+        let sig = &handler.method.sig;
+        assert_eq!(
+            service_ty(sig.receiver().unwrap()),
+            parse_quote!(UserService)
+        );
+        assert_eq!(sig.inputs[1], parse_quote!(other: Option<UserService>));
+        assert_eq!(sig.inputs[2], parse_quote!(id: <UserService>::Id));
+        assert_eq!(sig.output, parse_quote!(-> Vec<UserService>));
+    }
+
+    #[rstest]
+    #[case::method(quote!(impl UserService { fn router(&self) {} }))]
+    #[case::handler(quote!(impl UserService { #[query] fn router(&self) {} }))]
+    #[case::constant(quote!(impl UserService { const router: u32 = 0; }))]
+    fn router_conflict(#[case] item: TokenStream) {
+        let err = service(TokenStream::new(), item).unwrap_err();
+        assert!(err.to_string().contains("`router` is generated"));
+    }
+}
@@ -0,0 +1,6 @@
+use qubit_macros::handler;
+
+#[handler(query, blocking)]
+async fn my_handler(ctx: ()) {}
+
+fn main() {}
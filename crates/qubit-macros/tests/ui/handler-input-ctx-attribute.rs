@@ -0,0 +1,10 @@
+use qubit_macros::handler;
+
+struct Ctx {
+    db: u32,
+}
+
+#[handler(query)]
+async fn my_handler(#[name = "db"] Ctx { db }: Ctx, n: u32) {}
+
+fn main() {}
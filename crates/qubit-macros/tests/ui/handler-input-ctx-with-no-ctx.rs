@@ -0,0 +1,6 @@
+use qubit_macros::handler;
+
+#[handler(query, no_ctx)]
+async fn my_handler(#[ctx] db: Db, n: u32) {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use qubit_macros::handler;
+
+#[handler(query)]
+async fn my_handler(ctx: (), #[default] limit: u32, query: String) {}
+
+fn main() {}
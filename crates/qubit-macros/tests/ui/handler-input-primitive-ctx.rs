@@ -1,6 +1,6 @@
 use qubit_macros::handler;
 
 #[handler(query)]
-async fn my_handler(MyStruct { a, b }: MyStruct) {}
+async fn my_handler(a: u32, b: u32) {}
 
 fn main() {}
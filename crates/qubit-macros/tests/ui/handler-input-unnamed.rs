@@ -0,0 +1,6 @@
+use qubit_macros::handler;
+
+#[handler(query)]
+async fn my_handler(ctx: (), (a, b): (u32, u32)) {}
+
+fn main() {}
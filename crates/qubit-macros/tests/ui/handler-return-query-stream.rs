@@ -0,0 +1,9 @@
+use futures::Stream;
+use qubit::handler;
+
+#[handler(query)]
+fn my_handler(_ctx: ()) -> impl Stream<Item = u32> {
+    futures::stream::iter([1, 2, 3])
+}
+
+fn main() {}
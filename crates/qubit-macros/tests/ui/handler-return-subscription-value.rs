@@ -0,0 +1,8 @@
+use qubit::handler;
+
+#[handler(subscription)]
+fn my_handler(_ctx: ()) -> u32 {
+    0
+}
+
+fn main() {}
@@ -13,4 +13,4 @@
 
 */
 import type { Query, Mutation, Subscription } from "@qubit-rs/client";
-export type QubitServer = { echo_cookie: Query<[], string>, secret_endpoint: Query<[], string>, };
+export type QubitServer = { echo_cookie: Query<[], string>, login: Mutation<[username: string, password: string], boolean>, secret_endpoint: Query<[], string>, };
@@ -1,4 +1,4 @@
-import { build_client, ws } from "@qubit-rs/client";
+import { build_client, http, ws } from "@qubit-rs/client";
 import type { QubitServer } from "./bindings";
 
 async function main() {
@@ -21,14 +21,8 @@ async function main() {
       console.error("Error whilst accessing secret:", e);
     });
   }
-  // Authenticate with the API
-  await fetch("/login", {
-    method: "POST",
-    body: new URLSearchParams({ username: "user", password: "password" }),
-    headers: {
-      "Content-Type": "application/x-www-form-urlencoded",
-    },
-  });
+  // Authenticate with the API. This must be made over HTTP, so that the response can set the cookie
+  await build_client<QubitServer>(http(`/rpc`)).login.mutate("user", "password");
 
   console.log("Successfully authenticated with the API");
 
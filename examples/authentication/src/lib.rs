@@ -0,0 +1,84 @@
+use axum::http::StatusCode;
+use qubit::{
+    auth::{Authenticated, Authenticator, Cookie, CookieName, Optional},
+    handler, ResponseParts, Router, RpcError,
+};
+
+const COOKIE_NAME: &str = "qubit-auth";
+
+/// Don't do this
+const USERNAME: &str = "user";
+const PASSWORD: &str = "password";
+
+/// Log in with a username and password, setting the auth cookie on the response. This must be
+/// called over HTTP, as a WebSocket can't modify the response.
+#[handler(mutation)]
+async fn login(#[ctx] mut response: ResponseParts, username: String, password: String) -> bool {
+    if username != USERNAME || password != PASSWORD {
+        response.set_status(StatusCode::UNAUTHORIZED);
+        return false;
+    }
+
+    response
+        .set_cookie(
+            cookie::Cookie::build((COOKIE_NAME, "abc-123"))
+                .path("/")
+                .same_site(cookie::SameSite::Lax)
+                .build()
+                .to_string(),
+        )
+        .unwrap();
+
+    !response.is_detached()
+}
+
+/// Name of the cookie used for authentication.
+struct AuthCookie;
+impl CookieName for AuthCookie {
+    const NAME: &'static str = COOKIE_NAME;
+}
+
+/// An authenticated user. Will act as a middleware, as a handler that relies on
+/// `Authenticated<User>` will only be run if the user can be successfully authenticated.
+struct User {
+    name: String,
+}
+
+impl Authenticator<()> for User {
+    /// Extract the auth cookie from the request.
+    type Credentials = Cookie<AuthCookie>;
+
+    async fn authenticate(_ctx: (), cookie: Cookie<AuthCookie>) -> Result<Self, RpcError> {
+        // Validate the session, and load the user that it belongs to.
+        Ok(User {
+            name: cookie.value().to_string(),
+        })
+    }
+}
+
+/// Handler takes in an optional cookie, so will run regardless of authentication status.
+#[handler(query)]
+async fn echo_cookie(cookie: Optional<Cookie<AuthCookie>>) -> String {
+    if let Some(cookie) = cookie.into_inner() {
+        format!("A cookie is set: {}", cookie.value())
+    } else {
+        "No cookie is set".to_string()
+    }
+}
+
+/// Handler takes in [`Authenticated`], so will only run if the user can be authenticated.
+#[handler(query)]
+async fn secret_endpoint(user: Authenticated<User>) -> String {
+    format!("Welcome {}. The secret is: `super_secret`", user.name)
+}
+
+/// Create the qubit router.
+pub fn router() -> Router<()> {
+    Router::<()>::new()
+        .handler(login)
+        .handler(echo_cookie)
+        .handler(secret_endpoint)
+}
+
+// Allow the `qubit` CLI to generate bindings for the router.
+qubit::export_router!(router());
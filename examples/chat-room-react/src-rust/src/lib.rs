@@ -0,0 +1,43 @@
+use futures::Stream;
+use manager::{ChatMessage, Client};
+use qubit::{handler, Router};
+
+pub mod manager;
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub name: char,
+}
+
+#[handler(query)]
+async fn get_name(ctx: Ctx) -> char {
+    ctx.name
+}
+
+#[handler(mutation)]
+async fn send_message(ctx: Ctx, message: String) {
+    ctx.client.send_message(ctx.name, message).await
+}
+
+#[handler(subscription)]
+async fn list_online(ctx: Ctx) -> impl Stream<Item = Vec<char>> {
+    ctx.client.stream_online().await
+}
+
+#[handler(subscription)]
+async fn list_messages(ctx: Ctx) -> impl Stream<Item = Vec<ChatMessage>> {
+    ctx.client.stream_messages().await
+}
+
+/// Construct the qubit router.
+pub fn router() -> Router<Ctx> {
+    Router::new()
+        .handler(get_name)
+        .handler(send_message)
+        .handler(list_online)
+        .handler(list_messages)
+}
+
+// Allow the `qubit` CLI to generate bindings for the router.
+qubit::export_router!(router());
@@ -1,54 +1,13 @@
 use std::net::SocketAddr;
 
-use futures::Stream;
-use manager::{ChatMessage, Client, Manager};
-use qubit::{handler, Router, TypeScript};
+use chat_room_react::{manager::Manager, Ctx};
 use rand::{thread_rng, Rng};
 use tokio::net::TcpListener;
 
-mod manager;
-
-#[derive(Clone)]
-struct Ctx {
-    client: Client,
-    name: char,
-}
-
-#[handler(query)]
-async fn get_name(ctx: Ctx) -> char {
-    ctx.name
-}
-
-#[handler(mutation)]
-async fn send_message(ctx: Ctx, message: String) {
-    ctx.client.send_message(ctx.name, message).await
-}
-
-#[handler(subscription)]
-async fn list_online(ctx: Ctx) -> impl Stream<Item = Vec<char>> {
-    ctx.client.stream_online().await
-}
-
-#[handler(subscription)]
-async fn list_messages(ctx: Ctx) -> impl Stream<Item = Vec<ChatMessage>> {
-    ctx.client.stream_messages().await
-}
-
 #[tokio::main]
 async fn main() {
     // Construct the qubit router
-    let router = Router::new()
-        .handler(get_name)
-        .handler(send_message)
-        .handler(list_online)
-        .handler(list_messages);
-
-    // Save the type
-    router
-        .as_codegen()
-        .write_type("../src/bindings.ts", TypeScript::new())
-        .unwrap();
-    println!("Successfully wrote server bindings to `./bindings.ts`");
+    let router = chat_room_react::router();
 
     // Create service and handle
     let client = Manager::start();
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use futures::{stream, Stream, StreamExt};
+use qubit::{handler, Router};
+
+pub use crate::ctx::Ctx;
+
+mod ctx;
+
+// Simple handler, with no parameters from the client and no return values.
+#[handler(mutation)]
+async fn increment(ctx: Ctx) {
+    ctx.increment();
+}
+
+// Another simple handler.
+#[handler(mutation)]
+async fn decrement(ctx: Ctx) {
+    ctx.decrement();
+}
+
+// Handler that takes a parameter from the client.
+#[handler(mutation)]
+async fn add(ctx: Ctx, n: i32) {
+    ctx.add(n);
+}
+
+// Handler that returns a value to the client.
+#[handler(query)]
+async fn get(ctx: Ctx) -> i32 {
+    ctx.get()
+}
+
+// Handler that sets up a subscription, to continually stream data to the client.
+#[handler(subscription)]
+async fn countdown(ctx: Ctx) -> impl Stream<Item = i32> {
+    stream::iter((0..=ctx.get()).rev()).then(|item| async move {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        item
+    })
+}
+
+/// Construct the qubit router.
+pub fn router() -> Router<Ctx> {
+    Router::new()
+        .handler(increment)
+        .handler(decrement)
+        .handler(add)
+        .handler(get)
+        .handler(countdown)
+}
+
+// Allow the `qubit` CLI to generate bindings for the router.
+qubit::export_router!(router());
@@ -0,0 +1,14 @@
+use qubit::{handler, Router};
+
+#[handler(query)]
+async fn hello_world() -> String {
+    "Hello, world!".to_string()
+}
+
+/// Construct the qubit router.
+pub fn router() -> Router<()> {
+    Router::new().handler(hello_world)
+}
+
+// Allow the `qubit` CLI to generate bindings for the router.
+qubit::export_router!(router());
@@ -1,24 +1,11 @@
 use std::net::SocketAddr;
 
-use qubit::{handler, Router, TypeScript};
 use tokio::net::TcpListener;
 
-#[handler(query)]
-async fn hello_world() -> String {
-    "Hello, world!".to_string()
-}
-
 #[tokio::main]
 async fn main() {
     // Construct the qubit router
-    let router = Router::new().handler(hello_world);
-
-    // Save the type
-    router
-        .as_codegen()
-        .write_type("./bindings.ts", TypeScript::new())
-        .unwrap();
-    println!("Successfully write server type to `./bindings`");
+    let router = hello_world::router();
 
     // Create service and handle
     let (qubit_service, qubit_handle) = router.as_rpc(()).into_service();
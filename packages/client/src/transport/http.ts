@@ -3,10 +3,20 @@ import { parse_response } from "../jsonrpc";
 
 export type HttpOptions = {
   fetch?: typeof fetch;
+  /**
+   * Additional headers to include with every mutation, such as a CSRF token. If a function is
+   * provided, it will be called before each mutation. Queries are sent without them, so that they
+   * remain simple `GET` requests which don't require a CORS preflight.
+   */
+  headers?: HeadersInit | (() => HeadersInit);
 };
 
 export function http(host: string, http_options?: HttpOptions) {
   const fetch_impl = http_options?.fetch || fetch;
+  const get_headers = () => {
+    const headers = http_options?.headers;
+    return new Headers(typeof headers === "function" ? headers() : headers);
+  };
 
   return {
     query: async (_id, payload) => {
@@ -29,10 +39,13 @@ export function http(host: string, http_options?: HttpOptions) {
       return parse_response(body);
     },
     mutate: async (_id, payload) => {
+      const headers = get_headers();
+      headers.set("Content-Type", "application/json");
+
       const res = await fetch_impl(host, {
         method: "POST",
         mode: "cors",
-        headers: { "Content-Type": "application/json" },
+        headers,
         body: JSON.stringify(payload),
       });
 
@@ -0,0 +1,354 @@
+//! Reusable building blocks for authenticating requests, implemented as
+//! [`FromRequestExtensions`] so that they can be used as the context of a handler.
+//!
+//! The headers of the request will be available through the [`RequestInfo`], so long as the service
+//! was created with [`RpcModule::into_service`]. For WebSockets, the headers of the upgrade request
+//! will be used for every call.
+//!
+//! ```
+//! use qubit::{RpcError, auth::{Authenticated, Authenticator, BearerToken}, handler};
+//!
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! impl Authenticator<()> for User {
+//!     type Credentials = BearerToken;
+//!
+//!     async fn authenticate(_ctx: (), token: BearerToken) -> Result<Self, RpcError> {
+//!         match token.as_str() {
+//!             "abc-123" => Ok(User { name: "user".to_string() }),
+//!             _ => Err(RpcError::unauthorised("invalid token")),
+//!         }
+//!     }
+//! }
+//!
+//! #[handler(query)]
+//! async fn whoami(user: Authenticated<User>) -> String {
+//!     user.name.clone()
+//! }
+//! ```
+//!
+//! [`RpcModule::into_service`]: crate::RpcModule::into_service
+
+use std::{marker::PhantomData, ops::Deref};
+
+use http::{Extensions, HeaderMap, header};
+
+use crate::{FromRequestExtensions, RequestInfo, RpcError};
+
+/// Token from a `Authorization: Bearer <token>` header. Requests without the header will be
+/// rejected with [`RpcError::unauthorised`], unless wrapped in [`Optional`].
+#[derive(Debug)]
+pub struct BearerToken(String);
+
+impl BearerToken {
+    /// The token, without the `Bearer` prefix.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consume this value, producing the token.
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+
+    /// Find the token in the headers of the request.
+    fn from_extensions(extensions: &Extensions) -> Option<Self> {
+        let (scheme, token) = headers(extensions)?
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .trim()
+            .split_once(' ')?;
+
+        (scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty())
+            .then(|| Self(token.trim().to_string()))
+    }
+}
+
+impl Deref for BearerToken {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<Ctx> FromRequestExtensions<Ctx> for BearerToken
+where
+    Ctx: Clone + Send,
+{
+    async fn from_request_extensions(_ctx: Ctx, extensions: Extensions) -> Result<Self, RpcError> {
+        Self::from_extensions(&extensions)
+            .ok_or_else(|| RpcError::unauthorised("missing bearer token"))
+    }
+}
+
+/// Name of a cookie to extract with [`Cookie`].
+///
+/// ```
+/// use qubit::auth::CookieName;
+///
+/// struct SessionCookie;
+/// impl CookieName for SessionCookie {
+///     const NAME: &'static str = "session";
+/// }
+/// ```
+pub trait CookieName: 'static + Send + Sync {
+    /// Name of the cookie.
+    const NAME: &'static str;
+}
+
+/// Value of the cookie named by `N`. Requests without the cookie will be rejected with
+/// [`RpcError::unauthorised`], unless wrapped in [`Optional`].
+pub struct Cookie<N> {
+    value: String,
+    name: PhantomData<N>,
+}
+
+impl<N: CookieName> Cookie<N> {
+    /// Value of the cookie.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Consume this value, producing the value of the cookie.
+    pub fn into_value(self) -> String {
+        self.value
+    }
+
+    /// Find the cookie in the headers of the request.
+    fn from_extensions(extensions: &Extensions) -> Option<Self> {
+        headers(extensions)?
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|cookies| cookies.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(name, _)| *name == N::NAME)
+            .map(|(_, value)| Self {
+                value: value.trim_matches('"').to_string(),
+                name: PhantomData,
+            })
+    }
+}
+
+impl<N: CookieName> std::fmt::Debug for Cookie<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cookie")
+            .field("name", &N::NAME)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<N: CookieName> Deref for Cookie<N> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.value()
+    }
+}
+
+impl<Ctx, N> FromRequestExtensions<Ctx> for Cookie<N>
+where
+    Ctx: Clone + Send,
+    N: CookieName,
+{
+    async fn from_request_extensions(_ctx: Ctx, extensions: Extensions) -> Result<Self, RpcError> {
+        Self::from_extensions(&extensions)
+            .ok_or_else(|| RpcError::unauthorised(format!("missing `{}` cookie", N::NAME)))
+    }
+}
+
+/// Authenticate a request, producing a value that represents the authenticated user.
+///
+/// Implementations should return [`RpcError::unauthorised`] if the credentials are invalid, and
+/// [`RpcError::forbidden`] if the credentials are valid but the user isn't permitted access.
+#[trait_variant::make(Send)]
+pub trait Authenticator<Ctx>: Sized {
+    /// Credentials to extract from the request, such as [`BearerToken`] or [`Cookie`].
+    type Credentials: FromRequestExtensions<Ctx>;
+
+    /// Using the provided context and credentials, authenticate the request.
+    async fn authenticate(ctx: Ctx, credentials: Self::Credentials) -> Result<Self, RpcError>;
+}
+
+/// A user that has been authenticated with an [`Authenticator`]. The handler will only be run if
+/// authentication succeeds.
+#[derive(Debug)]
+pub struct Authenticated<U>(pub U);
+
+impl<U> Authenticated<U> {
+    /// Consume this value, producing the authenticated user.
+    pub fn into_inner(self) -> U {
+        self.0
+    }
+}
+
+impl<U> Deref for Authenticated<U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Ctx, U> FromRequestExtensions<Ctx> for Authenticated<U>
+where
+    Ctx: Clone + Send,
+    U: Authenticator<Ctx> + Send,
+{
+    async fn from_request_extensions(ctx: Ctx, extensions: Extensions) -> Result<Self, RpcError> {
+        let credentials = U::Credentials::from_request_extensions(ctx.clone(), extensions).await?;
+
+        U::authenticate(ctx, credentials).await.map(Self)
+    }
+}
+
+/// Optionally extract `T`, producing [`None`] if the request is unauthorised (such as if the
+/// credentials are missing or invalid). Any other error will still reject the request.
+#[derive(Debug)]
+pub struct Optional<T>(pub Option<T>);
+
+impl<T> Optional<T> {
+    /// Consume this value, producing the inner value.
+    pub fn into_inner(self) -> Option<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for Optional<T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Ctx, T> FromRequestExtensions<Ctx> for Optional<T>
+where
+    Ctx: Clone + Send,
+    T: FromRequestExtensions<Ctx>,
+{
+    async fn from_request_extensions(ctx: Ctx, extensions: Extensions) -> Result<Self, RpcError> {
+        match T::from_request_extensions(ctx, extensions).await {
+            Ok(value) => Ok(Self(Some(value))),
+            Err(e) if e.code == RpcError::UNAUTHORISED => Ok(Self(None)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Headers of the request, from the [`RequestInfo`] inserted by [`RpcModule::into_service`].
+///
+/// [`RpcModule::into_service`]: crate::RpcModule::into_service
+fn headers(extensions: &Extensions) -> Option<&HeaderMap> {
+    extensions.get::<RequestInfo>().map(RequestInfo::headers)
+}
+
+#[cfg(test)]
+mod test {
+    use http::Request;
+    use rstest::rstest;
+
+    use crate::Transport;
+
+    use super::*;
+
+    fn extensions(headers: &[(header::HeaderName, &'static str)]) -> Extensions {
+        let req = headers
+            .iter()
+            .fold(Request::builder(), |req, (name, value)| {
+                req.header(name, *value)
+            })
+            .body(())
+            .unwrap();
+
+        let mut extensions = Extensions::new();
+        extensions.insert(RequestInfo::from_request(&req, Transport::Http));
+        extensions
+    }
+
+    #[rstest]
+    #[case::valid("Bearer abc-123", Some("abc-123"))]
+    #[case::lowercase("bearer abc-123", Some("abc-123"))]
+    #[case::basic("Basic abc-123", None)]
+    #[case::empty("Bearer ", None)]
+    #[tokio::test]
+    async fn bearer_token(#[case] authorization: &'static str, #[case] expected: Option<&str>) {
+        let token = BearerToken::from_request_extensions(
+            (),
+            extensions(&[(header::AUTHORIZATION, authorization)]),
+        )
+        .await;
+
+        assert_eq!(token.ok().as_ref().map(BearerToken::as_str), expected);
+    }
+
+    #[tokio::test]
+    async fn bearer_token_missing() {
+        let err = BearerToken::from_request_extensions((), Extensions::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, RpcError::UNAUTHORISED);
+
+        let token = Optional::<BearerToken>::from_request_extensions((), Extensions::new()).await;
+        assert!(token.unwrap().is_none());
+    }
+
+    struct Session;
+    impl CookieName for Session {
+        const NAME: &'static str = "session";
+    }
+
+    #[rstest]
+    #[case::single("session=abc", Some("abc"))]
+    #[case::multiple("theme=dark; session=abc", Some("abc"))]
+    #[case::quoted("session=\"abc\"", Some("abc"))]
+    #[case::missing("theme=dark", None)]
+    #[tokio::test]
+    async fn cookie(#[case] cookies: &'static str, #[case] expected: Option<&str>) {
+        let cookie = Optional::<Cookie<Session>>::from_request_extensions(
+            (),
+            extensions(&[(header::COOKIE, cookies)]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(cookie.as_ref().map(Cookie::value), expected);
+    }
+
+    struct Admin;
+    impl Authenticator<()> for Admin {
+        type Credentials = BearerToken;
+
+        async fn authenticate(_ctx: (), token: BearerToken) -> Result<Self, RpcError> {
+            match token.as_str() {
+                "admin" => Ok(Admin),
+                "user" => Err(RpcError::forbidden("not an admin")),
+                _ => Err(RpcError::unauthorised("invalid token")),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::admin(Some("Bearer admin"), None)]
+    #[case::user(Some("Bearer user"), Some(RpcError::FORBIDDEN))]
+    #[case::invalid(Some("Bearer nobody"), Some(RpcError::UNAUTHORISED))]
+    #[case::missing(None, Some(RpcError::UNAUTHORISED))]
+    #[tokio::test]
+    async fn authenticated(
+        #[case] authorization: Option<&'static str>,
+        #[case] expected_error: Option<crate::ErrorCode>,
+    ) {
+        let extensions = match authorization {
+            Some(authorization) => extensions(&[(header::AUTHORIZATION, authorization)]),
+            None => Extensions::new(),
+        };
+
+        let result = Authenticated::<Admin>::from_request_extensions((), extensions).await;
+        assert_eq!(result.err().map(|e| e.code), expected_error);
+    }
+}
@@ -0,0 +1,207 @@
+//! Binary payloads, which are far more compact than the JSON array that a `Vec<u8>` would
+//! otherwise produce.
+
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, SeqAccess, Visitor},
+};
+use ts_rs::TS;
+
+/// Binary data, for use as a handler parameter or return value.
+///
+/// In JSON, the data is a base64 string, which is typed as a branded `Bytes` string in TypeScript.
+/// Formats which support binary data (such as MessagePack or CBOR) will carry it as raw bytes, and
+/// clients using those encodings may provide parameters as raw bytes.
+///
+/// ```
+/// use qubit::{Bytes, handler};
+///
+/// #[handler(query)]
+/// async fn thumbnail(ctx: (), id: u32) -> Bytes {
+///     Bytes::from(vec![0x89, 0x50, 0x4e, 0x47])
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, TS)]
+pub struct Bytes(#[ts(type = "string & { readonly __brand: \"Bytes\" }")] Vec<u8>);
+
+impl Bytes {
+    /// Create an empty value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume this value, producing the underlying bytes.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for Bytes {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Bytes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Bytes {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl From<Bytes> for Vec<u8> {
+    fn from(bytes: Bytes) -> Self {
+        bytes.0
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(&self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(BytesVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+}
+
+/// Accepts a base64 string, raw bytes, or a sequence of bytes.
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a base64 string or bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        STANDARD
+            .decode(v)
+            .map(Bytes)
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Bytes(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+
+        Ok(Bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+    use serde_json::json;
+    use ts_rs::Config;
+
+    use super::*;
+
+    #[rstest]
+    #[case::empty(&[], "")]
+    #[case::text(b"qubit", "cXViaXQ=")]
+    #[case::binary(&[0x00, 0xff, 0x10], "AP8Q")]
+    fn json(#[case] bytes: &[u8], #[case] encoded: &str) {
+        let value = serde_json::to_value(Bytes::from(bytes)).unwrap();
+        assert_eq!(value, json!(encoded));
+
+        let decoded: Bytes = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.as_ref(), bytes);
+    }
+
+    #[test]
+    fn json_array() {
+        let decoded: Bytes = serde_json::from_value(json!([1, 2, 3])).unwrap();
+        assert_eq!(decoded.into_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn json_invalid() {
+        assert!(serde_json::from_value::<Bytes>(json!("not base64!")).is_err());
+        assert!(serde_json::from_value::<Bytes>(json!(5)).is_err());
+    }
+
+    #[test]
+    fn msgpack() {
+        let bytes = Bytes::from([0x00, 0xff, 0x10]);
+        let encoded = rmp_serde::to_vec(&bytes).unwrap();
+
+        // Binary format marker, followed by the length.
+        assert_eq!(encoded, [0xc4, 0x03, 0x00, 0xff, 0x10]);
+        assert_eq!(rmp_serde::from_slice::<Bytes>(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn cbor() {
+        let bytes = Bytes::from([0x00, 0xff, 0x10]);
+        let mut encoded = Vec::new();
+        ciborium::into_writer(&bytes, &mut encoded).unwrap();
+
+        // Byte string of the length.
+        assert_eq!(encoded, [0x43, 0x00, 0xff, 0x10]);
+        assert_eq!(
+            ciborium::from_reader::<Bytes, _>(encoded.as_slice()).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn ts() {
+        assert_eq!(
+            Bytes::decl(&Config::default()),
+            "type Bytes = string & { readonly __brand: \"Bytes\" };"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+//! Command line interface to generate bindings from a [`Router`], without needing to start the
+//! server.
+//!
+//! The `qubit` binary (from the `qubit-cli` crate) will build a small shim that calls the entry
+//! point created by [`export_router!`](crate::export_router), forwarding all arguments to
+//! [`Cli::run`]. Requires the `cli` feature.
+
+use std::{
+    ffi::OsString,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use crate::{ApiSnapshot, Router, TypeScript, router::codegen::CodegenModule};
+
+/// Name of the function created by [`export_router!`](crate::export_router), which the `qubit`
+/// binary will call.
+pub const ENTRY_POINT: &str = "__qubit_cli";
+
+const USAGE: &str = "\
+Usage: qubit <COMMAND> [OPTIONS]
+
+Commands:
+  generate  Generate TypeScript bindings for the router
+  check     Check that bindings are up to date, and that the API has no breaking changes
+  schema    Generate an OpenRPC or JSON Schema document for the router
+
+Options:
+  generate:
+    --out <PATH>          Write bindings to the path, instead of stdout
+    --router-name <NAME>  Name of the generated router type (default: QubitServer)
+    --no-preamble         Don't include imports and the header comment
+  check:
+    --bindings <PATH>     Ensure the bindings at the path match the router
+    --router-name <NAME>  Name of the router type in the bindings (default: QubitServer)
+    --no-preamble         Bindings were generated without the preamble
+    --snapshot <PATH>     Compare the router against a previously written API snapshot
+    --update              Accept all changes, and write a new snapshot to the snapshot path
+  schema:
+    --out <PATH>          Write the schema to the path, instead of stdout
+    --format <FORMAT>     Either `openrpc` (default) or `json-schema`
+";
+
+/// Creates the entry point used by the `qubit` binary to generate bindings from a library crate.
+/// The provided expression must evaluate to a [`Router`], and will only be evaluated when the
+/// binary is run.
+///
+/// ```ignore
+/// pub fn router() -> qubit::Router<AppCtx> {
+///     qubit::Router::new().handler(hello_world)
+/// }
+///
+/// qubit::export_router!(router());
+/// ```
+#[macro_export]
+macro_rules! export_router {
+    ($router:expr $(,)?) => {
+        #[doc(hidden)]
+        pub fn __qubit_cli() -> ::std::process::ExitCode {
+            $crate::cli::Cli::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")).run($router)
+        }
+    };
+}
+
+/// Command line interface for a single router.
+pub struct Cli {
+    /// Title of the API, included in generated schemas.
+    title: String,
+    /// Version of the API, included in generated schemas.
+    version: String,
+}
+
+impl Cli {
+    /// Create a new instance, with the title and version used in generated schemas.
+    pub fn new(title: impl ToString, version: impl ToString) -> Self {
+        Self {
+            title: title.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    /// Run the CLI against the provided router, using the arguments passed to the process.
+    pub fn run<Ctx>(&self, router: Router<Ctx>) -> ExitCode
+    where
+        Ctx: 'static + Clone + Send + Sync,
+    {
+        self.run_with_args(router, std::env::args_os().skip(1))
+    }
+
+    /// Run the CLI against the provided router, using the provided arguments (excluding the
+    /// binary name).
+    pub fn run_with_args<Ctx>(
+        &self,
+        router: Router<Ctx>,
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> ExitCode
+    where
+        Ctx: 'static + Clone + Send + Sync,
+    {
+        let command = match Command::parse(args) {
+            Ok(command) => command,
+            Err(e) => {
+                eprintln!("error: {e}\n\n{USAGE}");
+                return ExitCode::from(2);
+            }
+        };
+
+        match self.execute(command, &router.as_codegen()) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    /// Execute a command against the provided codegen module.
+    fn execute(&self, command: Command, codegen: &CodegenModule) -> Result<ExitCode, CliError> {
+        match command {
+            Command::Help => {
+                print!("{USAGE}");
+            }
+            Command::Generate { output, bindings } => {
+                let generated = codegen.generate_type(bindings.backend())?;
+                write_output(output.as_deref(), &generated)?;
+            }
+            Command::Check {
+                bindings,
+                snapshot,
+                update,
+            } => {
+                let mut code = ExitCode::SUCCESS;
+
+                if let Some((path, bindings)) = bindings {
+                    let generated = codegen.generate_type(bindings.backend())?;
+
+                    if fs::read_to_string(&path).ok().as_deref() == Some(generated.as_str()) {
+                        println!("bindings at `{}` are up to date", path.display());
+                    } else {
+                        eprintln!("bindings at `{}` are out of date", path.display());
+                        code = ExitCode::FAILURE;
+                    }
+                }
+
+                if let Some(path) = snapshot {
+                    let current = codegen.snapshot();
+
+                    if update {
+                        if let Ok(previous) = ApiSnapshot::read(&path) {
+                            print!("{}", previous.diff(&current));
+                        }
+
+                        current.write(&path)?;
+                        println!("snapshot written to `{}`", path.display());
+                    } else {
+                        let diff = ApiSnapshot::read(&path)?.diff(&current);
+                        print!("{diff}");
+
+                        if diff.is_breaking() {
+                            code = ExitCode::FAILURE;
+                        }
+                    }
+                }
+
+                return Ok(code);
+            }
+            Command::Schema { output, format } => {
+                let snapshot = codegen.snapshot();
+                let schema = match format {
+                    SchemaFormat::OpenRpc => snapshot.to_open_rpc(&self.title, &self.version),
+                    SchemaFormat::JsonSchema => snapshot.to_json_schema(),
+                };
+
+                write_output(
+                    output.as_deref(),
+                    &serde_json::to_string_pretty(&schema).expect("schema is valid JSON"),
+                )?;
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Write some output to a file if a path is provided, otherwise to stdout.
+fn write_output(path: Option<&Path>, output: &str) -> std::io::Result<()> {
+    match path {
+        Some(path) => fs::write(path, output),
+        None => std::io::stdout().write_all(output.as_bytes()),
+    }
+}
+
+/// Options for the generated bindings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct BindingsOptions {
+    router_name: Option<String>,
+    no_preamble: bool,
+}
+
+impl BindingsOptions {
+    /// Create a [`TypeScript`] backend with these options.
+    fn backend(&self) -> TypeScript {
+        let mut backend = TypeScript::new();
+
+        if let Some(router_name) = &self.router_name {
+            backend = backend.with_router_name(router_name);
+        }
+
+        if self.no_preamble {
+            backend = backend.without_preamble();
+        }
+
+        backend
+    }
+}
+
+/// Format of a generated schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SchemaFormat {
+    OpenRpc,
+    JsonSchema,
+}
+
+/// A parsed command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Command {
+    Help,
+    Generate {
+        output: Option<PathBuf>,
+        bindings: BindingsOptions,
+    },
+    Check {
+        bindings: Option<(PathBuf, BindingsOptions)>,
+        snapshot: Option<PathBuf>,
+        update: bool,
+    },
+    Schema {
+        output: Option<PathBuf>,
+        format: SchemaFormat,
+    },
+}
+
+impl Command {
+    /// Parse a command from the provided arguments.
+    fn parse(args: impl IntoIterator<Item = impl Into<OsString>>) -> Result<Self, CliError> {
+        let mut args = args
+            .into_iter()
+            .map(|arg| arg.into().to_string_lossy().to_string());
+
+        let command = args.next().ok_or(CliError::MissingCommand)?;
+
+        // Collect all flags, and any values that they may have.
+        let mut output = None;
+        let mut bindings_path = None;
+        let mut bindings = BindingsOptions::default();
+        let mut snapshot = None;
+        let mut update = false;
+        let mut format = SchemaFormat::OpenRpc;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| CliError::MissingValue(arg.clone()))
+            };
+
+            match (command.as_str(), arg.as_str()) {
+                ("generate" | "schema", "--out") => output = Some(PathBuf::from(value()?)),
+                ("generate" | "check", "--router-name") => bindings.router_name = Some(value()?),
+                ("generate" | "check", "--no-preamble") => bindings.no_preamble = true,
+                ("check", "--bindings") => bindings_path = Some(PathBuf::from(value()?)),
+                ("check", "--snapshot") => snapshot = Some(PathBuf::from(value()?)),
+                ("check", "--update") => update = true,
+                ("schema", "--format") => {
+                    format = match value()?.as_str() {
+                        "openrpc" => SchemaFormat::OpenRpc,
+                        "json-schema" => SchemaFormat::JsonSchema,
+                        format => return Err(CliError::UnknownFormat(format.to_string())),
+                    }
+                }
+                (_, "-h" | "--help") => return Ok(Self::Help),
+                _ => return Err(CliError::UnknownArgument(arg)),
+            }
+        }
+
+        Ok(match command.as_str() {
+            "generate" => Self::Generate { output, bindings },
+            "check" => {
+                if bindings_path.is_none() && snapshot.is_none() {
+                    return Err(CliError::NothingToCheck);
+                }
+
+                if bindings_path.is_none() && bindings != BindingsOptions::default() {
+                    return Err(CliError::BindingsOptionsWithoutBindings);
+                }
+
+                Self::Check {
+                    bindings: bindings_path.map(|path| (path, bindings)),
+                    snapshot,
+                    update,
+                }
+            }
+            "schema" => Self::Schema { output, format },
+            "help" | "-h" | "--help" => Self::Help,
+            _ => return Err(CliError::UnknownCommand(command)),
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error("a command is required")]
+    MissingCommand,
+    #[error("unknown command `{0}`")]
+    UnknownCommand(String),
+    #[error("unknown argument `{0}`")]
+    UnknownArgument(String),
+    #[error("a value is required for `{0}`")]
+    MissingValue(String),
+    #[error("unknown schema format `{0}`")]
+    UnknownFormat(String),
+    #[error("at least one of `--bindings` or `--snapshot` is required")]
+    NothingToCheck,
+    #[error("`--router-name` and `--no-preamble` can only be used with `--bindings`")]
+    BindingsOptionsWithoutBindings,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::help(&["help"], Command::Help)]
+    #[case::help_flag(&["generate", "--help"], Command::Help)]
+    #[case::generate(&["generate"], Command::Generate { output: None, bindings: BindingsOptions::default() })]
+    #[case::generate_everything(
+        &["generate", "--out", "bindings.ts", "--router-name", "Api", "--no-preamble"],
+        Command::Generate {
+            output: Some(PathBuf::from("bindings.ts")),
+            bindings: BindingsOptions { router_name: Some("Api".to_string()), no_preamble: true },
+        }
+    )]
+    #[case::check_bindings(
+        &["check", "--bindings", "bindings.ts"],
+        Command::Check {
+            bindings: Some((PathBuf::from("bindings.ts"), BindingsOptions::default())),
+            snapshot: None,
+            update: false,
+        }
+    )]
+    #[case::check_snapshot(
+        &["check", "--snapshot", "api.json", "--update"],
+        Command::Check { bindings: None, snapshot: Some(PathBuf::from("api.json")), update: true }
+    )]
+    #[case::schema(&["schema"], Command::Schema { output: None, format: SchemaFormat::OpenRpc })]
+    #[case::schema_json_schema(
+        &["schema", "--format", "json-schema", "--out", "schema.json"],
+        Command::Schema { output: Some(PathBuf::from("schema.json")), format: SchemaFormat::JsonSchema }
+    )]
+    fn parse_valid(#[case] args: &[&str], #[case] expected: Command) {
+        assert_eq!(Command::parse(args).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::empty(&[], |e| matches!(e, CliError::MissingCommand))]
+    #[case::unknown_command(&["build"], |e| matches!(e, CliError::UnknownCommand(_)))]
+    #[case::unknown_argument(&["generate", "--snapshot", "api.json"], |e| matches!(e, CliError::UnknownArgument(_)))]
+    #[case::missing_value(&["generate", "--out"], |e| matches!(e, CliError::MissingValue(_)))]
+    #[case::unknown_format(&["schema", "--format", "yaml"], |e| matches!(e, CliError::UnknownFormat(_)))]
+    #[case::nothing_to_check(&["check"], |e| matches!(e, CliError::NothingToCheck))]
+    #[case::router_name_without_bindings(
+        &["check", "--snapshot", "api.json", "--router-name", "Api"],
+        |e| matches!(e, CliError::BindingsOptionsWithoutBindings)
+    )]
+    #[case::no_preamble_without_bindings(
+        &["check", "--snapshot", "api.json", "--no-preamble"],
+        |e| matches!(e, CliError::BindingsOptionsWithoutBindings)
+    )]
+    fn parse_invalid(#[case] args: &[&str], #[case] err_check: fn(CliError) -> bool) {
+        assert!(err_check(Command::parse(args).unwrap_err()));
+    }
+}
@@ -99,7 +99,17 @@ impl<W: Write> HandlerBackend<W> for TypeScript {
         let params = handler
             .params
             .iter()
-            .map(|(name, ty)| format!("{name}: {ty}"))
+            .map(|param| {
+                let name = param.name;
+
+                if param.optional {
+                    // Omitted parameters are `None`, so there is no need to also accept `null`.
+                    let ty = param.inner_ty.as_ref().unwrap_or(&param.ty);
+                    format!("{name}?: {ty}")
+                } else {
+                    format!("{name}: {}", param.ty)
+                }
+            })
             .collect::<Vec<_>>()
             .join(", ");
 
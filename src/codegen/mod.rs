@@ -2,11 +2,13 @@
 
 mod backend;
 mod reflection;
+mod schema;
+mod snapshot;
 
 use std::io::Write;
 
-pub use self::backend::*;
 pub(crate) use self::reflection::*;
+pub use self::{backend::*, snapshot::*};
 
 use crate::{
     RegisterableHandler,
@@ -100,6 +102,11 @@ impl Codegen {
 
         Ok(())
     }
+
+    /// Capture a serialisable [`ApiSnapshot`] of all handlers and dependent types.
+    pub fn snapshot(&self) -> ApiSnapshot {
+        ApiSnapshot::from_codegen(self)
+    }
 }
 
 impl Default for Codegen {
@@ -113,8 +120,8 @@ impl Default for Codegen {
 pub struct HandlerCodegen {
     /// The kind of handler.
     kind: HandlerKind,
-    /// Parameter name and types that the handler accepts.
-    params: Vec<(&'static str, CodegenType)>,
+    /// Parameters that the handler accepts.
+    params: Vec<ParamCodegen>,
     /// Return type of the handler.
     return_ty: CodegenType,
 }
@@ -127,10 +134,37 @@ impl HandlerCodegen {
         MValue: marker::ResponseMarker,
         MReturn: marker::HandlerReturnMarker,
     {
+        let params = ParamVisitor::visit::<F::Params>(meta.param_names).unwrap();
+
+        // Only the trailing parameters may be omitted.
+        let required = params.len().saturating_sub(meta.optional_params);
+
         HandlerCodegen {
             kind: meta.kind,
-            params: ParamVisitor::visit::<F::Params>(meta.param_names).unwrap(),
+            params: params
+                .into_iter()
+                .enumerate()
+                .map(|(i, (name, ty, inner_ty))| ParamCodegen {
+                    name,
+                    ty,
+                    inner_ty,
+                    optional: i >= required,
+                })
+                .collect(),
             return_ty: CodegenType::from_type::<<F::Response as ResponseValue<MValue>>::Value>(),
         }
     }
 }
+
+/// Representation of a handler parameter, for the purpose of code generation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamCodegen {
+    /// Name of the parameter.
+    name: &'static str,
+    /// Type of the parameter.
+    ty: CodegenType,
+    /// Type within the parameter, if it is an [`Option`].
+    inner_ty: Option<CodegenType>,
+    /// Whether the parameter may be omitted.
+    optional: bool,
+}
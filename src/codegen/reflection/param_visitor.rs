@@ -2,13 +2,16 @@ use ts_rs::{TS, TypeVisitor};
 
 use crate::{handler::ts::TsTypeTuple, reflection::ty::CodegenType};
 
+/// Name and type of a parameter, along with the type within it if it is an [`Option`].
+pub type Param = (&'static str, CodegenType, Option<CodegenType>);
+
 /// Visits parameters in a [`TsTypeTuple`], and registers them with the associated parameter name
 /// to the handler.
 pub enum ParamVisitor {
     /// Visitor is in a good state.
     Ok {
         /// Handler to register parameters against.
-        params: Vec<(&'static str, CodegenType)>,
+        params: Vec<Param>,
         /// Remaining parameter names.
         param_names: &'static [&'static str],
     },
@@ -22,7 +25,7 @@ impl ParamVisitor {
     /// `param_names`, and push the parameter to the handler.
     pub fn visit<Params>(
         param_names: &'static [&'static str],
-    ) -> Result<Vec<(&'static str, CodegenType)>, ParamVisitorError>
+    ) -> Result<Vec<Param>, ParamVisitorError>
     where
         Params: TsTypeTuple,
     {
@@ -64,7 +67,11 @@ impl TypeVisitor for ParamVisitor {
                 let param_name = &param_names[0];
                 *param_names = &param_names[1..];
 
-                params.push((param_name, CodegenType::from_type::<T>()));
+                params.push((
+                    param_name,
+                    CodegenType::from_type::<T>(),
+                    CodegenType::from_option_inner::<T>(),
+                ));
             }
             ParamVisitor::MissingNames(missing) => {
                 *missing += 1;
@@ -94,7 +101,10 @@ pub mod test {
     #[test]
     fn single_param() {
         let params = ParamVisitor::visit::<(u32,)>(&["param_a"]).unwrap();
-        assert_eq!(params, &[("param_a", CodegenType::from_type::<u32>())]);
+        assert_eq!(
+            params,
+            &[("param_a", CodegenType::from_type::<u32>(), None)]
+        );
     }
 
     #[test]
@@ -105,9 +115,31 @@ pub mod test {
         assert_eq!(
             params,
             &[
-                ("param_a", CodegenType::from_type::<u32>()),
-                ("some_boolean", CodegenType::from_type::<bool>()),
-                ("cool_string", CodegenType::from_type::<String>()),
+                ("param_a", CodegenType::from_type::<u32>(), None),
+                ("some_boolean", CodegenType::from_type::<bool>(), None),
+                ("cool_string", CodegenType::from_type::<String>(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn option_param() {
+        let params =
+            ParamVisitor::visit::<(Option<u32>, Option<Vec<String>>)>(&["param_a", "param_b"])
+                .unwrap();
+        assert_eq!(
+            params,
+            &[
+                (
+                    "param_a",
+                    CodegenType::from_type::<Option<u32>>(),
+                    Some(CodegenType::from_type::<u32>())
+                ),
+                (
+                    "param_b",
+                    CodegenType::from_type::<Option<Vec<String>>>(),
+                    Some(CodegenType::from_type::<Vec<String>>())
+                ),
             ]
         );
     }
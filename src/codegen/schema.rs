@@ -0,0 +1,328 @@
+//! Conversion of an [`ApiSnapshot`] into [OpenRPC](https://spec.open-rpc.org) and
+//! [JSON Schema](https://json-schema.org) documents.
+//!
+//! Types are captured as TypeScript definitions, so only the subset of TypeScript that [`ts_rs`]
+//! produces is understood. Anything that can't be represented will be emitted as an unconstrained
+//! schema, with the original TypeScript included under `x-typescript`.
+
+use serde_json::{Map, Value, json};
+
+use crate::codegen::{
+    ApiSnapshot,
+    snapshot::{base_name, object_fields, split_top_level},
+};
+
+/// Version of the OpenRPC specification that is produced.
+const OPEN_RPC_VERSION: &str = "1.2.6";
+
+impl ApiSnapshot {
+    /// Produce an OpenRPC document describing every handler in this snapshot. Dependent types are
+    /// included under `components.schemas`.
+    ///
+    /// OpenRPC has no concept of subscriptions, so the `result` of a subscription describes each
+    /// item produced by the subscription. Each method includes the Qubit handler kind under
+    /// `x-qubit-kind`.
+    pub fn to_open_rpc(&self, title: &str, version: &str) -> Value {
+        let schema = SchemaBuilder::new(self, "#/components/schemas/");
+
+        let methods = self
+            .handlers
+            .iter()
+            .map(|(path, handler)| {
+                json!({
+                    "name": path,
+                    "x-qubit-kind": handler.kind,
+                    "paramStructure": "by-position",
+                    "params": handler
+                        .params
+                        .iter()
+                        .map(|param| json!({
+                            "name": param.name,
+                            "required": !param.optional,
+                            "schema": schema.ty(&param.ty),
+                        }))
+                        .collect::<Vec<_>>(),
+                    "result": {
+                        "name": "result",
+                        "schema": schema.ty(&handler.return_ty),
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "openrpc": OPEN_RPC_VERSION,
+            "info": {
+                "title": title,
+                "version": version,
+            },
+            "methods": methods,
+            "components": {
+                "schemas": schema.definitions(),
+            },
+        })
+    }
+
+    /// Produce a JSON Schema document containing a definition for every dependent type in this
+    /// snapshot under `$defs`.
+    pub fn to_json_schema(&self) -> Value {
+        let schema = SchemaBuilder::new(self, "#/$defs/");
+
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": schema.definitions(),
+        })
+    }
+}
+
+/// Utility to convert TypeScript types from a snapshot into JSON Schema.
+struct SchemaBuilder<'a> {
+    snapshot: &'a ApiSnapshot,
+    /// Prefix for any references to named types.
+    ref_prefix: &'static str,
+}
+
+impl<'a> SchemaBuilder<'a> {
+    fn new(snapshot: &'a ApiSnapshot, ref_prefix: &'static str) -> Self {
+        Self {
+            snapshot,
+            ref_prefix,
+        }
+    }
+
+    /// Schema for every dependent type, keyed by the type name (without generics).
+    fn definitions(&self) -> Map<String, Value> {
+        self.snapshot
+            .types
+            .iter()
+            .map(|(name, definition)| {
+                let mut schema = self.ty(definition);
+                if let Value::Object(schema) = &mut schema {
+                    schema.insert("x-typescript".to_string(), json!(definition));
+                }
+
+                (base_name(name).to_string(), schema)
+            })
+            .collect()
+    }
+
+    /// Convert a TypeScript type into a JSON Schema.
+    fn ty(&self, ty: &str) -> Value {
+        let ty = ty.trim();
+
+        // Unions
+        let variants = split_union(ty);
+        if variants.len() > 1 {
+            return json!({
+                "anyOf": variants.into_iter().map(|variant| self.ty(variant)).collect::<Vec<_>>(),
+            });
+        }
+
+        match ty {
+            "number" => return json!({ "type": "number" }),
+            "bigint" => return json!({ "type": "integer" }),
+            "string" => return json!({ "type": "string" }),
+            "boolean" => return json!({ "type": "boolean" }),
+            "null" => return json!({ "type": "null" }),
+            "true" | "false" => return json!({ "const": ty == "true" }),
+            _ => {}
+        }
+
+        // Literals
+        if let Ok(literal) = serde_json::from_str::<Value>(ty) {
+            return json!({ "const": literal });
+        }
+
+        // Parenthesised types
+        if let Some(inner) = ty.strip_prefix('(').and_then(|ty| ty.strip_suffix(')')) {
+            return self.ty(inner);
+        }
+
+        // Arrays
+        if let Some(inner) = ty
+            .strip_prefix("Array<")
+            .and_then(|ty| ty.strip_suffix('>'))
+        {
+            return json!({ "type": "array", "items": self.ty(inner) });
+        }
+
+        // Tuples
+        if let Some(inner) = ty.strip_prefix('[').and_then(|ty| ty.strip_suffix(']')) {
+            let items = split_list(inner)
+                .into_iter()
+                .map(|item| self.ty(item))
+                .collect::<Vec<_>>();
+
+            return json!({
+                "type": "array",
+                "prefixItems": items,
+                "minItems": items.len(),
+                "maxItems": items.len(),
+            });
+        }
+
+        // Maps (`{ [key in string]?: number }`)
+        if let Some(value) = ty
+            .strip_prefix("{ [key in ")
+            .and_then(|ty| ty.split_once("]"))
+            .and_then(|(_, ty)| ty.trim_start_matches('?').strip_prefix(':'))
+            .and_then(|ty| ty.trim().strip_suffix('}'))
+        {
+            return json!({
+                "type": "object",
+                "additionalProperties": self.ty(value.trim().trim_end_matches(',')),
+            });
+        }
+
+        // Objects
+        if let Some(fields) = object_fields(ty) {
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+
+            for (name, field) in fields {
+                if !field.optional {
+                    required.push(name.clone());
+                }
+
+                properties.insert(name, self.ty(&field.ty));
+            }
+
+            return json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            });
+        }
+
+        // References to dependent types
+        if self
+            .snapshot
+            .types
+            .keys()
+            .any(|name| base_name(name) == base_name(ty))
+        {
+            return json!({ "$ref": format!("{}{}", self.ref_prefix, base_name(ty)) });
+        }
+
+        json!({ "x-typescript": ty })
+    }
+}
+
+/// Split a union type into each of its variants.
+fn split_union(ty: &str) -> Vec<&str> {
+    split_top_level(ty, '|')
+        .into_iter()
+        .map(str::trim)
+        .filter(|variant| !variant.is_empty())
+        .collect()
+}
+
+/// Split a comma separated list of types.
+fn split_list(ty: &str) -> Vec<&str> {
+    split_top_level(ty, ',')
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use rstest::rstest;
+
+    use crate::{HandlerKind, HandlerSnapshot, ParamSnapshot};
+
+    use super::*;
+
+    fn snapshot() -> ApiSnapshot {
+        ApiSnapshot {
+            handlers: BTreeMap::from([(
+                "user.get".to_string(),
+                HandlerSnapshot {
+                    kind: HandlerKind::Query,
+                    params: vec![ParamSnapshot {
+                        name: "id".to_string(),
+                        ty: "number".to_string(),
+                        optional: false,
+                    }],
+                    return_ty: "User | null".to_string(),
+                },
+            )]),
+            types: BTreeMap::from([(
+                "User".to_string(),
+                "{ id: number, name: string, email?: string, }".to_string(),
+            )]),
+        }
+    }
+
+    #[rstest]
+    #[case::number("number", json!({ "type": "number" }))]
+    #[case::bigint("bigint", json!({ "type": "integer" }))]
+    #[case::string("string", json!({ "type": "string" }))]
+    #[case::null("null", json!({ "type": "null" }))]
+    #[case::string_literal(r#""Active""#, json!({ "const": "Active" }))]
+    #[case::number_literal("123", json!({ "const": 123 }))]
+    #[case::option("number | null", json!({ "anyOf": [{ "type": "number" }, { "type": "null" }] }))]
+    #[case::array("Array<string>", json!({ "type": "array", "items": { "type": "string" } }))]
+    #[case::tuple(
+        "[number, string]",
+        json!({
+            "type": "array",
+            "prefixItems": [{ "type": "number" }, { "type": "string" }],
+            "minItems": 2,
+            "maxItems": 2,
+        })
+    )]
+    #[case::map(
+        "{ [key in string]?: number }",
+        json!({ "type": "object", "additionalProperties": { "type": "number" } })
+    )]
+    #[case::object(
+        "{ a: number, b?: string, }",
+        json!({
+            "type": "object",
+            "properties": { "a": { "type": "number" }, "b": { "type": "string" } },
+            "required": ["a"],
+        })
+    )]
+    #[case::reference("User", json!({ "$ref": "#/$defs/User" }))]
+    #[case::unknown("Date", json!({ "x-typescript": "Date" }))]
+    fn convert_ty(#[case] ty: &str, #[case] expected: Value) {
+        let snapshot = snapshot();
+        assert_eq!(SchemaBuilder::new(&snapshot, "#/$defs/").ty(ty), expected);
+    }
+
+    #[test]
+    fn open_rpc() {
+        let document = snapshot().to_open_rpc("My API", "1.0.0");
+
+        assert_eq!(document["openrpc"], OPEN_RPC_VERSION);
+        assert_eq!(document["info"]["title"], "My API");
+
+        let method = &document["methods"][0];
+        assert_eq!(method["name"], "user.get");
+        assert_eq!(method["x-qubit-kind"], "query");
+        assert_eq!(method["params"][0]["name"], "id");
+        assert_eq!(
+            method["result"]["schema"],
+            json!({ "anyOf": [{ "$ref": "#/components/schemas/User" }, { "type": "null" }] })
+        );
+
+        assert_eq!(
+            document["components"]["schemas"]["User"]["required"],
+            json!(["id", "name"])
+        );
+    }
+
+    #[test]
+    fn json_schema() {
+        let document = snapshot().to_json_schema();
+
+        assert_eq!(
+            document["$defs"]["User"]["properties"]["email"],
+            json!({ "type": "string" })
+        );
+    }
+}
@@ -0,0 +1,1114 @@
+//! Machine-readable snapshots of a router's API surface, which can be compared against each other
+//! to detect changes that would break already-deployed clients.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter},
+    path::Path,
+    process::ExitCode,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    codegen::{Codegen, HandlerCodegen},
+    reflection::handler::HandlerKind,
+    util::Node,
+};
+
+/// Serialisable description of every handler and dependent type exposed by a router.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiSnapshot {
+    /// All handlers, keyed by their dotted method path.
+    pub handlers: BTreeMap<String, HandlerSnapshot>,
+    /// TypeScript definitions of all user types that handlers depend on, keyed by type name.
+    pub types: BTreeMap<String, String>,
+}
+
+/// Snapshot of a single handler.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandlerSnapshot {
+    /// Kind of the handler.
+    pub kind: HandlerKind,
+    /// Parameters of the handler, in the order they must be provided.
+    pub params: Vec<ParamSnapshot>,
+    /// Return type of the handler.
+    pub return_ty: String,
+}
+
+/// Snapshot of a single handler parameter.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamSnapshot {
+    /// Name of the parameter.
+    pub name: String,
+    /// Type of the parameter.
+    pub ty: String,
+    /// Whether the parameter may be omitted.
+    #[serde(default)]
+    pub optional: bool,
+}
+
+impl ApiSnapshot {
+    /// Capture a snapshot of the provided [`Codegen`] instance.
+    pub(crate) fn from_codegen(codegen: &Codegen) -> Self {
+        fn visit_node(
+            node: &Node<HandlerCodegen>,
+            prefix: &[&str],
+            handlers: &mut BTreeMap<String, HandlerSnapshot>,
+        ) {
+            for (key, handler) in &node.items {
+                let path = prefix
+                    .iter()
+                    .copied()
+                    .chain([key.as_str()])
+                    .collect::<Vec<_>>();
+
+                handlers.insert(path.join("."), HandlerSnapshot::from(handler));
+            }
+
+            for (key, node) in &node.children {
+                let prefix = prefix
+                    .iter()
+                    .copied()
+                    .chain([key.as_str()])
+                    .collect::<Vec<_>>();
+
+                visit_node(node, &prefix, handlers);
+            }
+        }
+
+        let mut handlers = BTreeMap::new();
+        visit_node(&codegen.tree, &[], &mut handlers);
+
+        Self {
+            handlers,
+            types: codegen
+                .dependent_types
+                .definitions
+                .values()
+                .map(|(name, definition)| (name.to_string(), definition.clone()))
+                .collect(),
+        }
+    }
+
+    /// Read a previously written snapshot from the provided path.
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Write this snapshot to the provided path.
+    ///
+    /// If a file at the path doesn't exist it will be created. If it does exist, it will be
+    /// overwritten.
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Compare this (previously released) snapshot against the `current` snapshot, and classify
+    /// every change that was made.
+    pub fn diff(&self, current: &ApiSnapshot) -> ApiDiff {
+        let mut changes = Vec::new();
+
+        // Types used by either version, as a change to a type may affect clients of both.
+        let usage = self.usage().union(current.usage());
+
+        for (path, previous) in &self.handlers {
+            let Some(current) = current.handlers.get(path) else {
+                changes.push(ApiChange::HandlerRemoved { path: path.clone() });
+                continue;
+            };
+
+            diff_handler(path, previous, current, &mut changes);
+        }
+
+        for path in current.handlers.keys() {
+            if !self.handlers.contains_key(path) {
+                changes.push(ApiChange::HandlerAdded { path: path.clone() });
+            }
+        }
+
+        for (name, previous) in &self.types {
+            let Some(current) = current.types.get(name) else {
+                changes.push(ApiChange::TypeRemoved { name: name.clone() });
+                continue;
+            };
+
+            diff_type(name, previous, current, usage.of(name), &mut changes);
+        }
+
+        for name in current.types.keys() {
+            if !self.types.contains_key(name) {
+                changes.push(ApiChange::TypeAdded { name: name.clone() });
+            }
+        }
+
+        ApiDiff { changes }
+    }
+}
+
+impl ApiSnapshot {
+    /// Determine which types are (directly or indirectly) used by parameters and return types.
+    fn usage(&self) -> TypeUsages {
+        let mut usage = TypeUsages::default();
+
+        for handler in self.handlers.values() {
+            for param in &handler.params {
+                self.visit_types(&param.ty, &mut usage.input);
+            }
+
+            self.visit_types(&handler.return_ty, &mut usage.output);
+        }
+
+        usage
+    }
+
+    /// Add every type referenced by `ty` (and the types that they reference) to `visited`.
+    fn visit_types(&self, ty: &str, visited: &mut BTreeSet<String>) {
+        let idents = ty
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .filter(|ident| !ident.is_empty());
+
+        for ident in idents {
+            let Some((name, definition)) =
+                self.types.iter().find(|(name, _)| base_name(name) == ident)
+            else {
+                continue;
+            };
+
+            if visited.insert(base_name(name).to_string()) {
+                self.visit_types(definition, visited);
+            }
+        }
+    }
+}
+
+/// Types which are used as inputs (within parameters) and outputs (within return types).
+#[derive(Default)]
+struct TypeUsages {
+    input: BTreeSet<String>,
+    output: BTreeSet<String>,
+}
+
+impl TypeUsages {
+    fn union(mut self, other: Self) -> Self {
+        self.input.extend(other.input);
+        self.output.extend(other.output);
+        self
+    }
+
+    fn of(&self, name: &str) -> TypeUsage {
+        let name = base_name(name);
+
+        TypeUsage {
+            input: self.input.contains(name),
+            output: self.output.contains(name),
+        }
+    }
+}
+
+/// How a single type is used by handlers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct TypeUsage {
+    /// Type is sent by clients.
+    input: bool,
+    /// Type is received by clients.
+    output: bool,
+}
+
+/// Name of a type, without any generics.
+pub(crate) fn base_name(ty: &str) -> &str {
+    ty.split_once('<')
+        .map(|(name, _)| name)
+        .unwrap_or(ty)
+        .trim()
+}
+
+impl From<&HandlerCodegen> for HandlerSnapshot {
+    fn from(handler: &HandlerCodegen) -> Self {
+        Self {
+            kind: handler.kind,
+            params: handler
+                .params
+                .iter()
+                .map(|param| ParamSnapshot {
+                    name: param.name.to_string(),
+                    ty: param.ty.to_string(),
+                    optional: param.optional,
+                })
+                .collect(),
+            return_ty: handler.return_ty.to_string(),
+        }
+    }
+}
+
+/// Compare two versions of the same handler.
+fn diff_handler(
+    path: &str,
+    previous: &HandlerSnapshot,
+    current: &HandlerSnapshot,
+    changes: &mut Vec<ApiChange>,
+) {
+    if previous.kind != current.kind {
+        changes.push(ApiChange::KindChanged {
+            path: path.to_string(),
+            previous: previous.kind,
+            current: current.kind,
+        });
+    }
+
+    // Parameters are positional on the wire, so compare them by index.
+    for (index, previous_param) in previous.params.iter().enumerate() {
+        let Some(current_param) = current.params.get(index) else {
+            changes.push(ApiChange::ParamRemoved {
+                path: path.to_string(),
+                name: previous_param.name.clone(),
+            });
+            continue;
+        };
+
+        if previous_param.ty != current_param.ty {
+            changes.push(ApiChange::ParamTypeChanged {
+                path: path.to_string(),
+                name: current_param.name.clone(),
+                previous: previous_param.ty.clone(),
+                current: current_param.ty.clone(),
+            });
+        } else if previous_param.optional && !current_param.optional {
+            changes.push(ApiChange::ParamRequired {
+                path: path.to_string(),
+                name: current_param.name.clone(),
+            });
+        } else if previous_param.name != current_param.name {
+            changes.push(ApiChange::ParamRenamed {
+                path: path.to_string(),
+                previous: previous_param.name.clone(),
+                current: current_param.name.clone(),
+            });
+        }
+    }
+
+    for param in current.params.iter().skip(previous.params.len()) {
+        changes.push(ApiChange::ParamAdded {
+            path: path.to_string(),
+            name: param.name.clone(),
+            ty: param.ty.clone(),
+            optional: param.optional,
+        });
+    }
+
+    if previous.return_ty != current.return_ty {
+        changes.push(ApiChange::ReturnTypeChanged {
+            path: path.to_string(),
+            previous: previous.return_ty.clone(),
+            current: current.return_ty.clone(),
+        });
+    }
+}
+
+/// Compare two versions of the same type definition.
+fn diff_type(
+    name: &str,
+    previous: &str,
+    current: &str,
+    usage: TypeUsage,
+    changes: &mut Vec<ApiChange>,
+) {
+    if previous == current {
+        return;
+    }
+
+    // Only object definitions can be compared field by field, anything else is compared as a
+    // whole.
+    let (Some(previous_fields), Some(current_fields)) =
+        (object_fields(previous), object_fields(current))
+    else {
+        changes.push(ApiChange::TypeChanged {
+            name: name.to_string(),
+            previous: previous.to_string(),
+            current: current.to_string(),
+        });
+        return;
+    };
+
+    for (field, previous_field) in &previous_fields {
+        let Some(current_field) = current_fields.get(field) else {
+            changes.push(ApiChange::FieldRemoved {
+                ty: name.to_string(),
+                field: field.clone(),
+            });
+            continue;
+        };
+
+        if current_field.ty != previous_field.ty {
+            changes.push(ApiChange::FieldTypeChanged {
+                ty: name.to_string(),
+                field: field.clone(),
+                previous: previous_field.ty.clone(),
+                current: current_field.ty.clone(),
+            });
+        }
+
+        if current_field.optional != previous_field.optional {
+            changes.push(ApiChange::FieldOptionalityChanged {
+                ty: name.to_string(),
+                field: field.clone(),
+                optional: current_field.optional,
+                input: usage.input,
+                output: usage.output,
+            });
+        }
+    }
+
+    for (field, current_field) in &current_fields {
+        if !previous_fields.contains_key(field) {
+            changes.push(ApiChange::FieldAdded {
+                ty: name.to_string(),
+                field: field.clone(),
+                field_ty: current_field.ty.clone(),
+                optional: current_field.optional,
+                input: usage.input,
+            });
+        }
+    }
+}
+
+/// Field of a TypeScript object definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Field {
+    /// Type of the field.
+    pub ty: String,
+    /// Whether the field may be omitted (`?`).
+    pub optional: bool,
+}
+
+/// Extract the fields of a TypeScript object definition (such as `{ a: number, b?: string, }`).
+/// If the definition isn't a plain object, `None` will be returned.
+pub(crate) fn object_fields(definition: &str) -> Option<BTreeMap<String, Field>> {
+    let definition = strip_comments(definition);
+    let inner = definition
+        .trim()
+        .strip_prefix('{')?
+        .strip_suffix('}')?
+        .trim();
+
+    // Ensure that the braces that were removed actually match each other (eg `{ a: A } & { b: B }`).
+    if split_top_level(inner, '}').len() > 1 {
+        return None;
+    }
+
+    split_top_level(inner, ',')
+        .into_iter()
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let [name, ty] = split_top_level(field, ':')[..] else {
+                return None;
+            };
+
+            let name = name.trim();
+            let (name, optional) = match name.strip_suffix('?') {
+                Some(name) => (name, true),
+                None => (name, false),
+            };
+            let name = name.trim_matches(|c| c == '"' || c == '\'');
+
+            Some((
+                name.to_string(),
+                Field {
+                    ty: ty.trim().to_string(),
+                    optional,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Remove any `/* ... */` comments (including doc comments) from a definition.
+fn strip_comments(definition: &str) -> String {
+    let mut output = String::with_capacity(definition.len());
+    let mut rest = definition;
+
+    while let Some((before, after)) = rest.split_once("/*") {
+        output.push_str(before);
+        rest = after.split_once("*/").map(|(_, after)| after).unwrap_or("");
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Split a string on the provided separator, ignoring any separators nested within brackets or
+/// string literals.
+pub(crate) fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    let mut previous = None;
+
+    for (i, c) in s.char_indices() {
+        // The `>` of an arrow (`=>`) doesn't close a bracket.
+        let arrow = previous == Some('=') && c == '>';
+        previous = Some(c);
+
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, c) if c == separator && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            (None, '{' | '[' | '(' | '<') => depth += 1,
+            (None, '}' | ']' | ')' | '>') if !arrow => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    parts.push(&s[start..]);
+    parts
+}
+
+/// A single change between two [`ApiSnapshot`]s.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ApiChange {
+    /// A new handler was added.
+    HandlerAdded { path: String },
+    /// A handler was removed.
+    HandlerRemoved { path: String },
+    /// The kind of a handler changed (eg from a query to a mutation).
+    KindChanged {
+        path: String,
+        previous: HandlerKind,
+        current: HandlerKind,
+    },
+    /// A parameter was added to a handler.
+    ParamAdded {
+        path: String,
+        name: String,
+        ty: String,
+        optional: bool,
+    },
+    /// A parameter was removed from a handler.
+    ParamRemoved { path: String, name: String },
+    /// The type of a parameter changed.
+    ParamTypeChanged {
+        path: String,
+        name: String,
+        previous: String,
+        current: String,
+    },
+    /// A parameter which could previously be omitted is now required.
+    ParamRequired { path: String, name: String },
+    /// A parameter was renamed, without changing its type or position.
+    ParamRenamed {
+        path: String,
+        previous: String,
+        current: String,
+    },
+    /// The return type of a handler changed.
+    ReturnTypeChanged {
+        path: String,
+        previous: String,
+        current: String,
+    },
+    /// A new type was added.
+    TypeAdded { name: String },
+    /// A type is no longer used by any handler.
+    TypeRemoved { name: String },
+    /// The definition of a (non-object) type changed.
+    TypeChanged {
+        name: String,
+        previous: String,
+        current: String,
+    },
+    /// A field was added to an object type.
+    FieldAdded {
+        ty: String,
+        field: String,
+        field_ty: String,
+        optional: bool,
+        /// Whether the type is sent by clients.
+        input: bool,
+    },
+    /// A field was removed from an object type.
+    FieldRemoved { ty: String, field: String },
+    /// The type of a field within an object type changed.
+    FieldTypeChanged {
+        ty: String,
+        field: String,
+        previous: String,
+        current: String,
+    },
+    /// A field within an object type became optional, or became required.
+    FieldOptionalityChanged {
+        ty: String,
+        field: String,
+        /// Whether the field is now optional.
+        optional: bool,
+        /// Whether the type is sent by clients.
+        input: bool,
+        /// Whether the type is received by clients.
+        output: bool,
+    },
+}
+
+impl ApiChange {
+    /// Whether this change would break clients generated from the previous snapshot.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Self::HandlerRemoved { .. }
+            | Self::KindChanged { .. }
+            | Self::ParamAdded {
+                optional: false, ..
+            }
+            | Self::ParamRemoved { .. }
+            | Self::ParamRequired { .. }
+            | Self::ParamTypeChanged { .. }
+            | Self::ReturnTypeChanged { .. }
+            | Self::TypeChanged { .. }
+            | Self::FieldRemoved { .. }
+            | Self::FieldTypeChanged { .. } => true,
+            Self::HandlerAdded { .. }
+            | Self::ParamAdded { optional: true, .. }
+            | Self::ParamRenamed { .. }
+            | Self::TypeAdded { .. }
+            | Self::TypeRemoved { .. } => false,
+            // Clients that send the type won't provide the new field.
+            Self::FieldAdded {
+                optional, input, ..
+            } => *input && !optional,
+            // Clients that receive the type may not handle the field being missing, and clients
+            // that send the type may not provide it.
+            Self::FieldOptionalityChanged {
+                optional,
+                input,
+                output,
+                ..
+            } => {
+                if *optional {
+                    *output
+                } else {
+                    *input
+                }
+            }
+        }
+    }
+}
+
+impl Display for ApiChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HandlerAdded { path } => write!(f, "handler `{path}` was added"),
+            Self::HandlerRemoved { path } => write!(f, "handler `{path}` was removed"),
+            Self::KindChanged {
+                path,
+                previous,
+                current,
+            } => write!(
+                f,
+                "handler `{path}` changed from a {previous:?} to a {current:?}"
+            ),
+            Self::ParamAdded {
+                path,
+                name,
+                ty,
+                optional,
+            } => {
+                let optional = if *optional { "?" } else { "" };
+                write!(
+                    f,
+                    "handler `{path}` has new parameter `{name}{optional}: {ty}`"
+                )
+            }
+            Self::ParamRemoved { path, name } => {
+                write!(f, "handler `{path}` no longer has parameter `{name}`")
+            }
+            Self::ParamRequired { path, name } => {
+                write!(f, "parameter `{name}` of handler `{path}` is now required")
+            }
+            Self::ParamTypeChanged {
+                path,
+                name,
+                previous,
+                current,
+            } => write!(
+                f,
+                "parameter `{name}` of handler `{path}` changed from `{previous}` to `{current}`"
+            ),
+            Self::ParamRenamed {
+                path,
+                previous,
+                current,
+            } => write!(
+                f,
+                "parameter `{previous}` of handler `{path}` was renamed to `{current}`"
+            ),
+            Self::ReturnTypeChanged {
+                path,
+                previous,
+                current,
+            } => write!(
+                f,
+                "return type of handler `{path}` changed from `{previous}` to `{current}`"
+            ),
+            Self::TypeAdded { name } => write!(f, "type `{name}` was added"),
+            Self::TypeRemoved { name } => write!(f, "type `{name}` is no longer used"),
+            Self::TypeChanged {
+                name,
+                previous,
+                current,
+            } => write!(f, "type `{name}` changed from `{previous}` to `{current}`"),
+            Self::FieldAdded {
+                ty,
+                field,
+                field_ty,
+                optional,
+                ..
+            } => {
+                let optional = if *optional { "?" } else { "" };
+                write!(
+                    f,
+                    "type `{ty}` has new field `{field}{optional}: {field_ty}`"
+                )
+            }
+            Self::FieldRemoved { ty, field } => {
+                write!(f, "type `{ty}` no longer has field `{field}`")
+            }
+            Self::FieldTypeChanged {
+                ty,
+                field,
+                previous,
+                current,
+            } => write!(
+                f,
+                "field `{field}` of type `{ty}` changed from `{previous}` to `{current}`"
+            ),
+            Self::FieldOptionalityChanged {
+                ty,
+                field,
+                optional,
+                ..
+            } => {
+                let optional = if *optional { "optional" } else { "required" };
+                write!(f, "field `{field}` of type `{ty}` is now {optional}")
+            }
+        }
+    }
+}
+
+/// Report of all changes between two [`ApiSnapshot`]s.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiDiff {
+    /// Every change that was detected.
+    pub changes: Vec<ApiChange>,
+}
+
+impl ApiDiff {
+    /// Whether any of the changes would break clients generated from the previous snapshot.
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(ApiChange::is_breaking)
+    }
+
+    /// All changes that would break clients generated from the previous snapshot.
+    pub fn breaking(&self) -> impl Iterator<Item = &ApiChange> {
+        self.changes.iter().filter(|change| change.is_breaking())
+    }
+
+    /// Exit code suitable for a CI check, which will be non-zero if there are breaking changes.
+    pub fn exit_code(&self) -> ExitCode {
+        if self.is_breaking() {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Human readable report of all changes, with breaking changes listed first.
+impl Display for ApiDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "no API changes");
+        }
+
+        let (breaking, compatible) = self
+            .changes
+            .iter()
+            .partition::<Vec<_>, _>(|change| change.is_breaking());
+
+        if !breaking.is_empty() {
+            writeln!(f, "breaking changes:")?;
+            for change in breaking {
+                writeln!(f, "  - {change}")?;
+            }
+        }
+
+        if !compatible.is_empty() {
+            writeln!(f, "compatible changes:")?;
+            for change in compatible {
+                writeln!(f, "  - {change}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    /// Create a parameter, which is optional if the name ends with `?`.
+    fn param(name: &str, ty: &str) -> ParamSnapshot {
+        let (name, optional) = match name.strip_suffix('?') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+
+        ParamSnapshot {
+            name: name.to_string(),
+            ty: ty.to_string(),
+            optional,
+        }
+    }
+
+    fn handler(kind: HandlerKind, params: &[(&str, &str)], return_ty: &str) -> HandlerSnapshot {
+        HandlerSnapshot {
+            kind,
+            params: params.iter().map(|(name, ty)| param(name, ty)).collect(),
+            return_ty: return_ty.to_string(),
+        }
+    }
+
+    fn snapshot(
+        handlers: impl IntoIterator<Item = (&'static str, HandlerSnapshot)>,
+        types: impl IntoIterator<Item = (&'static str, &'static str)>,
+    ) -> ApiSnapshot {
+        ApiSnapshot {
+            handlers: handlers
+                .into_iter()
+                .map(|(path, handler)| (path.to_string(), handler))
+                .collect(),
+            types: types
+                .into_iter()
+                .map(|(name, definition)| (name.to_string(), definition.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn no_changes() {
+        let snapshot = snapshot(
+            [(
+                "user.get",
+                handler(HandlerKind::Query, &[("id", "number")], "User"),
+            )],
+            [("User", "{ id: number, name: string, }")],
+        );
+
+        let diff = snapshot.diff(&snapshot.clone());
+        assert!(diff.changes.is_empty());
+        assert!(!diff.is_breaking());
+    }
+
+    #[rstest]
+    #[case::handler_removed(
+        snapshot([("a", handler(HandlerKind::Query, &[], "null"))], []),
+        snapshot([], []),
+        ApiChange::HandlerRemoved { path: "a".to_string() },
+        true,
+    )]
+    #[case::handler_added(
+        snapshot([], []),
+        snapshot([("a", handler(HandlerKind::Query, &[], "null"))], []),
+        ApiChange::HandlerAdded { path: "a".to_string() },
+        false,
+    )]
+    #[case::kind_changed(
+        snapshot([("a", handler(HandlerKind::Query, &[], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Mutation, &[], "null"))], []),
+        ApiChange::KindChanged {
+            path: "a".to_string(),
+            previous: HandlerKind::Query,
+            current: HandlerKind::Mutation,
+        },
+        true,
+    )]
+    #[case::param_added(
+        snapshot([("a", handler(HandlerKind::Query, &[], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Query, &[("n", "number")], "null"))], []),
+        ApiChange::ParamAdded {
+            path: "a".to_string(),
+            name: "n".to_string(),
+            ty: "number".to_string(),
+            optional: false,
+        },
+        true,
+    )]
+    #[case::optional_param_added(
+        snapshot([("a", handler(HandlerKind::Query, &[], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Query, &[("n?", "number | null")], "null"))], []),
+        ApiChange::ParamAdded {
+            path: "a".to_string(),
+            name: "n".to_string(),
+            ty: "number | null".to_string(),
+            optional: true,
+        },
+        false,
+    )]
+    #[case::param_required(
+        snapshot([("a", handler(HandlerKind::Query, &[("n?", "number")], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Query, &[("n", "number")], "null"))], []),
+        ApiChange::ParamRequired { path: "a".to_string(), name: "n".to_string() },
+        true,
+    )]
+    #[case::param_removed(
+        snapshot([("a", handler(HandlerKind::Query, &[("n", "number")], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Query, &[], "null"))], []),
+        ApiChange::ParamRemoved { path: "a".to_string(), name: "n".to_string() },
+        true,
+    )]
+    #[case::param_type_changed(
+        snapshot([("a", handler(HandlerKind::Query, &[("n", "number")], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Query, &[("n", "string")], "null"))], []),
+        ApiChange::ParamTypeChanged {
+            path: "a".to_string(),
+            name: "n".to_string(),
+            previous: "number".to_string(),
+            current: "string".to_string(),
+        },
+        true,
+    )]
+    #[case::param_renamed(
+        snapshot([("a", handler(HandlerKind::Query, &[("n", "number")], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Query, &[("m", "number")], "null"))], []),
+        ApiChange::ParamRenamed {
+            path: "a".to_string(),
+            previous: "n".to_string(),
+            current: "m".to_string(),
+        },
+        false,
+    )]
+    #[case::return_type_changed(
+        snapshot([("a", handler(HandlerKind::Query, &[], "null"))], []),
+        snapshot([("a", handler(HandlerKind::Query, &[], "number"))], []),
+        ApiChange::ReturnTypeChanged {
+            path: "a".to_string(),
+            previous: "null".to_string(),
+            current: "number".to_string(),
+        },
+        true,
+    )]
+    #[case::field_removed(
+        snapshot([], [("User", "{ id: number, name: string, }")]),
+        snapshot([], [("User", "{ id: number, }")]),
+        ApiChange::FieldRemoved { ty: "User".to_string(), field: "name".to_string() },
+        true,
+    )]
+    #[case::field_added(
+        snapshot([], [("User", "{ id: number, }")]),
+        snapshot([], [("User", "{ id: number, name: string, }")]),
+        ApiChange::FieldAdded {
+            ty: "User".to_string(),
+            field: "name".to_string(),
+            field_ty: "string".to_string(),
+            optional: false,
+            input: false,
+        },
+        false,
+    )]
+    #[case::field_added_to_output(
+        snapshot([("a", handler(HandlerKind::Query, &[], "User"))], [("User", "{ id: number, }")]),
+        snapshot(
+            [("a", handler(HandlerKind::Query, &[], "User"))],
+            [("User", "{ id: number, name: string, }")],
+        ),
+        ApiChange::FieldAdded {
+            ty: "User".to_string(),
+            field: "name".to_string(),
+            field_ty: "string".to_string(),
+            optional: false,
+            input: false,
+        },
+        false,
+    )]
+    #[case::field_added_to_input(
+        snapshot([("a", handler(HandlerKind::Query, &[("u", "User")], "null"))], [("User", "{ id: number, }")]),
+        snapshot(
+            [("a", handler(HandlerKind::Query, &[("u", "User")], "null"))],
+            [("User", "{ id: number, name: string, }")],
+        ),
+        ApiChange::FieldAdded {
+            ty: "User".to_string(),
+            field: "name".to_string(),
+            field_ty: "string".to_string(),
+            optional: false,
+            input: true,
+        },
+        true,
+    )]
+    #[case::optional_field_added_to_input(
+        snapshot([("a", handler(HandlerKind::Query, &[("u", "User")], "null"))], [("User", "{ id: number, }")]),
+        snapshot(
+            [("a", handler(HandlerKind::Query, &[("u", "User")], "null"))],
+            [("User", "{ id: number, name?: string, }")],
+        ),
+        ApiChange::FieldAdded {
+            ty: "User".to_string(),
+            field: "name".to_string(),
+            field_ty: "string".to_string(),
+            optional: true,
+            input: true,
+        },
+        false,
+    )]
+    #[case::field_added_to_nested_input(
+        snapshot(
+            [("a", handler(HandlerKind::Query, &[("u", "Array<User>")], "null"))],
+            [("User", "{ id: Id, }"), ("Id", "{ value: number, }")],
+        ),
+        snapshot(
+            [("a", handler(HandlerKind::Query, &[("u", "Array<User>")], "null"))],
+            [("User", "{ id: Id, }"), ("Id", "{ value: number, kind: string, }")],
+        ),
+        ApiChange::FieldAdded {
+            ty: "Id".to_string(),
+            field: "kind".to_string(),
+            field_ty: "string".to_string(),
+            optional: false,
+            input: true,
+        },
+        true,
+    )]
+    #[case::field_made_optional_in_input(
+        snapshot([("a", handler(HandlerKind::Query, &[("u", "User")], "null"))], [("User", "{ id: number, }")]),
+        snapshot([("a", handler(HandlerKind::Query, &[("u", "User")], "null"))], [("User", "{ id?: number, }")]),
+        ApiChange::FieldOptionalityChanged {
+            ty: "User".to_string(),
+            field: "id".to_string(),
+            optional: true,
+            input: true,
+            output: false,
+        },
+        false,
+    )]
+    #[case::field_made_optional_in_output(
+        snapshot([("a", handler(HandlerKind::Query, &[], "User"))], [("User", "{ id: number, }")]),
+        snapshot([("a", handler(HandlerKind::Query, &[], "User"))], [("User", "{ id?: number, }")]),
+        ApiChange::FieldOptionalityChanged {
+            ty: "User".to_string(),
+            field: "id".to_string(),
+            optional: true,
+            input: false,
+            output: true,
+        },
+        true,
+    )]
+    #[case::field_made_required_in_input(
+        snapshot([("a", handler(HandlerKind::Query, &[("u", "User")], "null"))], [("User", "{ id?: number, }")]),
+        snapshot([("a", handler(HandlerKind::Query, &[("u", "User")], "null"))], [("User", "{ id: number, }")]),
+        ApiChange::FieldOptionalityChanged {
+            ty: "User".to_string(),
+            field: "id".to_string(),
+            optional: false,
+            input: true,
+            output: false,
+        },
+        true,
+    )]
+    #[case::field_made_required_in_output(
+        snapshot([("a", handler(HandlerKind::Query, &[], "User"))], [("User", "{ id?: number, }")]),
+        snapshot([("a", handler(HandlerKind::Query, &[], "User"))], [("User", "{ id: number, }")]),
+        ApiChange::FieldOptionalityChanged {
+            ty: "User".to_string(),
+            field: "id".to_string(),
+            optional: false,
+            input: false,
+            output: true,
+        },
+        false,
+    )]
+    #[case::type_changed(
+        snapshot([], [("Status", r#""Active" | "Inactive""#)]),
+        snapshot([], [("Status", r#""Active""#)]),
+        ApiChange::TypeChanged {
+            name: "Status".to_string(),
+            previous: r#""Active" | "Inactive""#.to_string(),
+            current: r#""Active""#.to_string(),
+        },
+        true,
+    )]
+    fn single_change(
+        #[case] previous: ApiSnapshot,
+        #[case] current: ApiSnapshot,
+        #[case] expected: ApiChange,
+        #[case] breaking: bool,
+    ) {
+        let diff = previous.diff(&current);
+        assert_eq!(diff.changes, [expected]);
+        assert_eq!(diff.is_breaking(), breaking);
+    }
+
+    #[rstest]
+    #[case::empty("{ }", &[])]
+    #[case::simple("{ a: number, b: string, }", &[("a", "number"), ("b", "string")])]
+    #[case::optional("{ a?: number, }", &[("a", "?number")])]
+    #[case::quoted(r#"{ "a-b": number, }"#, &[("a-b", "number")])]
+    #[case::nested(
+        "{ a: { b: number, c: string, }, d: Array<[number, string]>, }",
+        &[("a", "{ b: number, c: string, }"), ("d", "Array<[number, string]>")],
+    )]
+    #[case::string_literal(r#"{ a: "x, y: z", }"#, &[("a", r#""x, y: z""#)])]
+    #[case::doc_comment("{ /** Some: docs, */ a: number, }", &[("a", "number")])]
+    #[case::arrow("{ a: (x: number) => Array<number>, b: string, }", &[("a", "(x: number) => Array<number>"), ("b", "string")])]
+    fn object_fields_valid(#[case] definition: &str, #[case] expected: &[(&str, &str)]) {
+        assert_eq!(
+            object_fields(definition).unwrap(),
+            expected
+                .iter()
+                .map(|(name, ty)| {
+                    let (ty, optional) = match ty.strip_prefix('?') {
+                        Some(ty) => (ty, true),
+                        None => (*ty, false),
+                    };
+
+                    (
+                        name.to_string(),
+                        Field {
+                            ty: ty.to_string(),
+                            optional,
+                        },
+                    )
+                })
+                .collect::<BTreeMap<_, _>>()
+        );
+    }
+
+    #[rstest]
+    #[case::arrow("(a: A, b: B) => C", ',', &["(a: A, b: B) => C"])]
+    #[case::arrow_generic("A<(x: X) => Y>, B", ',', &["A<(x: X) => Y>", " B"])]
+    fn split_top_level_arrow(#[case] s: &str, #[case] separator: char, #[case] expected: &[&str]) {
+        assert_eq!(split_top_level(s, separator), expected);
+    }
+
+    #[rstest]
+    #[case::union(r#""A" | "B""#)]
+    #[case::intersection("{ a: number, } & { b: string, }")]
+    #[case::primitive("number")]
+    fn object_fields_invalid(#[case] definition: &str) {
+        assert!(object_fields(definition).is_none());
+    }
+}
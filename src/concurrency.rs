@@ -0,0 +1,162 @@
+//! Limits on the number of calls to a handler that may run at once, declared with the
+//! `max_concurrency` attribute of the [`handler`](crate::handler) macro.
+//!
+//! Once the limit is reached, further calls will be rejected with [`RpcError::server_busy`]. If
+//! `max_wait` is also provided, calls will instead wait for a running call to complete, and will
+//! only be rejected if they wait for longer than `max_wait`. Subscriptions count against the
+//! limit for as long as they are open.
+//!
+//! ```
+//! use qubit::{Router, handler};
+//!
+//! #[handler(mutation, max_concurrency = 4, max_wait = "2s")]
+//! async fn charge_card(ctx: (), amount: u32) {}
+//!
+//! let rpc = Router::new().handler(charge_card).as_rpc(());
+//! ```
+//!
+//! The limit of any handler can be overridden when building the module, with
+//! [`RpcModule::with_concurrency_limit`].
+//!
+//! [`RpcModule::with_concurrency_limit`]: crate::RpcModule::with_concurrency_limit
+
+use std::{sync::Arc, time::Duration};
+
+use http::{Extensions, StatusCode};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{ResponseParts, RpcError};
+
+/// Maximum number of calls to a handler that may run at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConcurrencyLimit {
+    /// Number of calls that may run at once.
+    pub max: usize,
+    /// Maximum duration that a call may wait once the limit has been reached. If not provided,
+    /// calls will be rejected immediately.
+    pub max_wait: Option<Duration>,
+}
+
+impl ConcurrencyLimit {
+    /// Create a new limit of `max` calls at once, rejecting any further calls.
+    ///
+    /// # Panics
+    ///
+    /// If `max` is zero, or greater than [`Semaphore::MAX_PERMITS`].
+    pub const fn new(max: usize) -> Self {
+        let limit = Self {
+            max,
+            max_wait: None,
+        };
+        limit.assert_valid();
+        limit
+    }
+
+    /// Panic if the limit would reject every call, or can't be enforced.
+    pub(crate) const fn assert_valid(&self) {
+        assert!(self.max > 0, "concurrency limit must be greater than zero");
+        assert!(
+            self.max <= Semaphore::MAX_PERMITS,
+            "concurrency limit exceeds the maximum number of permits"
+        );
+    }
+
+    /// Wait for up to `max_wait` for a running call to complete, rather than rejecting calls
+    /// immediately.
+    pub const fn queue(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
+    }
+}
+
+/// Enforces a [`ConcurrencyLimit`] for a single handler.
+#[derive(Clone, Debug)]
+pub(crate) struct ConcurrencyLimiter {
+    semaphore: Arc<Semaphore>,
+    max_wait: Option<Duration>,
+}
+
+impl ConcurrencyLimiter {
+    /// Create a limiter for the provided limit.
+    pub(crate) fn new(limit: ConcurrencyLimit) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit.max)),
+            max_wait: limit.max_wait,
+        }
+    }
+
+    /// Acquire a permit to run a call, which must be held until the call completes. If a permit
+    /// isn't available in time, the call will be rejected. If the call was made over HTTP, the
+    /// response will also be given a `503 Service Unavailable` status.
+    pub(crate) async fn acquire(
+        &self,
+        extensions: &Extensions,
+    ) -> Result<OwnedSemaphorePermit, RpcError> {
+        let semaphore = Arc::clone(&self.semaphore);
+
+        let permit = match self.max_wait {
+            Some(max_wait) => tokio::time::timeout(max_wait, semaphore.acquire_owned())
+                .await
+                .ok()
+                .map(|permit| permit.expect("semaphore is never closed")),
+            None => semaphore.try_acquire_owned().ok(),
+        };
+
+        permit.ok_or_else(|| {
+            ResponseParts::from_extensions(extensions).set_status(StatusCode::SERVICE_UNAVAILABLE);
+            RpcError::server_busy()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn reject() {
+        let limiter = ConcurrencyLimiter::new(ConcurrencyLimit::new(2));
+        let extensions = Extensions::new();
+
+        let first = limiter.acquire(&extensions).await.unwrap();
+        let _second = limiter.acquire(&extensions).await.unwrap();
+
+        let err = limiter.acquire(&extensions).await.unwrap_err();
+        assert_eq!(err.code, RpcError::SERVER_BUSY);
+
+        // Completing a call allows another to run.
+        drop(first);
+        assert!(limiter.acquire(&extensions).await.is_ok());
+    }
+
+    #[rstest]
+    #[case::zero(0)]
+    #[case::too_large(Semaphore::MAX_PERMITS + 1)]
+    #[should_panic]
+    fn invalid(#[case] max: usize) {
+        ConcurrencyLimit::new(max);
+    }
+
+    #[tokio::test]
+    async fn queue() {
+        let limiter =
+            ConcurrencyLimiter::new(ConcurrencyLimit::new(1).queue(Duration::from_millis(50)));
+        let extensions = Extensions::new();
+
+        let first = limiter.acquire(&extensions).await.unwrap();
+
+        // Waiting calls are rejected once the maximum wait elapses.
+        let err = limiter.acquire(&extensions).await.unwrap_err();
+        assert_eq!(err.code, RpcError::SERVER_BUSY);
+
+        // Waiting calls run once a permit is available.
+        let waiting = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire(&Extensions::new()).await.is_ok() })
+        };
+        drop(first);
+        assert!(waiting.await.unwrap());
+    }
+}
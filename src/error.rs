@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use jsonrpsee::{IntoResponse, types::ErrorObjectOwned};
 use serde::Serialize;
 use serde_json::Value;
@@ -18,6 +20,83 @@ pub struct RpcError {
     pub data: Option<Value>,
 }
 
+impl RpcError {
+    /// Error code used when a request is missing valid credentials.
+    pub const UNAUTHORISED: ErrorCode = ErrorCode::ServerError(-32001);
+
+    /// Error code used when a request has valid credentials, but isn't permitted to perform the
+    /// action.
+    pub const FORBIDDEN: ErrorCode = ErrorCode::ServerError(-32003);
+
+    /// Error code used when a handler didn't complete within its timeout.
+    pub const TIMED_OUT: ErrorCode = ErrorCode::ServerError(-32008);
+
+    /// Error code used when a client has exceeded the rate limit of a handler.
+    pub const RATE_LIMITED: ErrorCode = ErrorCode::ServerError(-32029);
+
+    /// Error code used when a handler is already running as many calls as it allows.
+    pub const SERVER_BUSY: ErrorCode = ErrorCode::ServerError(-32053);
+
+    /// Error code used when a call was cancelled before completing, such as when the client
+    /// disconnects.
+    pub const CANCELLED: ErrorCode = ErrorCode::ServerError(-32099);
+
+    /// Create an error indicating that the request is missing valid credentials.
+    pub fn unauthorised(message: impl ToString) -> Self {
+        Self {
+            code: Self::UNAUTHORISED,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    /// Create an error indicating that the request isn't permitted to perform the action.
+    pub fn forbidden(message: impl ToString) -> Self {
+        Self {
+            code: Self::FORBIDDEN,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    /// Create an error indicating that the client has exceeded a rate limit, and may retry after
+    /// the provided number of seconds.
+    pub fn rate_limited(retry_after: u64) -> Self {
+        Self {
+            code: Self::RATE_LIMITED,
+            message: "rate limit exceeded".to_string(),
+            data: Some(serde_json::json!({ "retry_after": retry_after })),
+        }
+    }
+
+    /// Create an error indicating that a handler didn't complete within the provided timeout.
+    pub fn timed_out(timeout: Duration) -> Self {
+        Self {
+            code: Self::TIMED_OUT,
+            message: "handler timed out".to_string(),
+            data: Some(serde_json::json!({ "timeout_ms": timeout.as_millis() })),
+        }
+    }
+
+    /// Create an error indicating that the handler is already running as many calls as it allows.
+    pub fn server_busy() -> Self {
+        Self {
+            code: Self::SERVER_BUSY,
+            message: "server busy".to_string(),
+            data: None,
+        }
+    }
+
+    /// Create an error indicating that a call was cancelled before completing.
+    pub fn cancelled() -> Self {
+        Self {
+            code: Self::CANCELLED,
+            message: "call cancelled".to_string(),
+            data: None,
+        }
+    }
+}
+
 /// Convert into [`jsonrpsee::types::ErrorObjectOwned`].
 impl From<RpcError> for ErrorObjectOwned {
     fn from(rpc_error: RpcError) -> Self {
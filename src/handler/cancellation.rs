@@ -0,0 +1,198 @@
+//! Cancellation of handlers once their result is no longer needed, either because they exceeded
+//! their timeout, or because the client went away.
+
+use std::{ops::Deref, time::Duration};
+
+use jsonrpsee::Extensions;
+use tokio_util::sync::CancellationToken;
+
+use crate::{FromRequestExtensions, RpcError};
+
+/// Token for the connection that a call was made over, which will be cancelled once the
+/// connection closes.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectionToken(pub(crate) CancellationToken);
+
+/// Token for a single call, made available to handlers through [`Cancellation`].
+#[derive(Clone, Debug)]
+struct CallToken(CancellationToken);
+
+/// Signal that a call to the handler has been cancelled, such as when it exceeds its timeout or
+/// the client disconnects. Handlers are dropped when cancelled, so this is only required when a
+/// handler spawns its own tasks which should also be stopped.
+///
+/// The token will not be cancelled once the handler completes successfully, so spawned tasks may
+/// outlive the call.
+///
+/// ```
+/// use qubit::{Cancellation, handler};
+///
+/// #[handler(mutation, timeout = "30s")]
+/// async fn generate_report(cancellation: Cancellation) {
+///     let token = cancellation.into_inner();
+///
+///     tokio::spawn(async move {
+///         token
+///             .run_until_cancelled(async {
+///                 // Some expensive work.
+///             })
+///             .await;
+///     });
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Cancellation(CancellationToken);
+
+impl Cancellation {
+    /// Consume this value, producing the underlying token.
+    pub fn into_inner(self) -> CancellationToken {
+        self.0
+    }
+}
+
+impl Deref for Cancellation {
+    type Target = CancellationToken;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Ctx> FromRequestExtensions<Ctx> for Cancellation
+where
+    Ctx: Clone + Send,
+{
+    async fn from_request_extensions(_ctx: Ctx, extensions: Extensions) -> Result<Self, RpcError> {
+        // Calls made outside of a module won't have a token, so will never be cancelled.
+        Ok(Self(
+            extensions
+                .get::<CallToken>()
+                .map(|token| token.0.clone())
+                .unwrap_or_default(),
+        ))
+    }
+}
+
+/// Create a token for a new call, and attach it to the extensions. The token will be cancelled
+/// along with the connection that the call was made over.
+pub(crate) fn attach(extensions: &mut Extensions) -> CancellationToken {
+    let token = extensions
+        .get::<ConnectionToken>()
+        .map(|connection| connection.0.child_token())
+        .unwrap_or_default();
+
+    extensions.insert(CallToken(token.clone()));
+    token
+}
+
+/// Run a call to a handler, producing a [`RpcError::timed_out`] error if it doesn't complete
+/// within the timeout. The call's token will be cancelled if it times out, if the connection
+/// closes, or if the returned future is dropped before completing (such as when a HTTP client
+/// disconnects).
+pub(crate) async fn run<T, F>(
+    timeout: Option<Duration>,
+    mut extensions: Extensions,
+    f: impl FnOnce(Extensions) -> F,
+) -> Result<T, RpcError>
+where
+    F: Future<Output = Result<T, RpcError>>,
+{
+    let token = attach(&mut extensions);
+    let guard = token.drop_guard_ref();
+
+    let call = token.run_until_cancelled(f(extensions));
+    let output = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, call)
+            .await
+            .map_err(|_| RpcError::timed_out(timeout))?,
+        None => call.await,
+    }
+    .ok_or_else(RpcError::cancelled)?;
+
+    // The call completed, so any tasks that it spawned may continue.
+    guard.disarm();
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use futures::FutureExt;
+
+    use super::*;
+
+    async fn extract(extensions: Extensions) -> CancellationToken {
+        Cancellation::from_request_extensions((), extensions)
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn completed() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        let result = run(None, Extensions::new(), |extensions| async move {
+            tx.send(extract(extensions).await).unwrap();
+            Ok(1)
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 1);
+        assert!(!rx.await.unwrap().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn timed_out() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        let err = run(
+            Some(Duration::from_millis(10)),
+            Extensions::new(),
+            |extensions| async move {
+                tx.send(extract(extensions).await).unwrap();
+                std::future::pending::<Result<(), RpcError>>().await
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.code, RpcError::TIMED_OUT);
+        assert!(rx.await.unwrap().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn dropped() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        let mut call = run(None, Extensions::new(), |extensions| async move {
+            tx.send(extract(extensions).await).unwrap();
+            std::future::pending::<Result<(), RpcError>>().await
+        })
+        .boxed();
+
+        assert!((&mut call).now_or_never().is_none());
+        drop(call);
+
+        assert!(rx.await.unwrap().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn connection_closed() {
+        let connection = CancellationToken::new();
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectionToken(connection.clone()));
+
+        let call = run(None, extensions, |_| {
+            std::future::pending::<Result<(), RpcError>>()
+        });
+        connection.cancel();
+
+        assert_eq!(call.await.unwrap_err().code, RpcError::CANCELLED);
+    }
+
+    #[tokio::test]
+    async fn detached() {
+        assert!(!extract(Extensions::new()).await.is_cancelled());
+    }
+}
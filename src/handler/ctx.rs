@@ -20,3 +20,42 @@ where
         Ok(ctx)
     }
 }
+
+/// Multiple [`FromRequestExtensions`] values, each built from the same context and extensions.
+///
+/// The [`handler`](crate::handler) macro will produce this from any parameters marked with
+/// `#[ctx]`, allowing a handler to use multiple extractors.
+#[derive(Debug)]
+pub struct Extractors<T>(pub T);
+
+macro_rules! impl_extractors {
+    (impl [$($extractors:ident,)*]) => {
+        impl<Ctx, $($extractors),*> FromRequestExtensions<Ctx> for Extractors<($($extractors,)*)>
+        where
+            Ctx: Clone + Send,
+            $($extractors: FromRequestExtensions<Ctx> + Send,)*
+        {
+            #[allow(unused_variables)]
+            async fn from_request_extensions(
+                ctx: Ctx,
+                extensions: Extensions,
+            ) -> Result<Self, RpcError> {
+                Ok(Self(($(
+                    $extractors::from_request_extensions(ctx.clone(), extensions.clone()).await?,
+                )*)))
+            }
+        }
+    };
+
+    (recurse []) => {};
+    (recurse [$extractor:ident, $($extractors:ident,)*]) => {
+        impl_extractors!($($extractors),*);
+    };
+
+    ($($extractors:ident),* $(,)?) => {
+        impl_extractors!(impl [$($extractors,)*]);
+        impl_extractors!(recurse [$($extractors,)*]);
+    };
+}
+
+impl_extractors!(E0, E1, E2, E3, E4, E5, E6, E7);
@@ -0,0 +1,350 @@
+//! Instrumentation of handler invocations. Any [`Metrics`] configured for the module will be
+//! recorded, and with the `tracing` feature enabled a [`tracing`] span will be created for each call
+//! (or for the lifetime of each subscription).
+
+use std::{sync::Arc, time::Instant};
+
+use jsonrpsee::Extensions;
+
+use crate::{ErrorCode, RpcError, metrics::Metrics, reflection::handler::HandlerMeta};
+
+#[cfg(feature = "tracing")]
+use crate::RequestInfo;
+
+/// Instrumentation for a single invocation of a handler. If the invocation is dropped before it
+/// completes (such as when a HTTP client disconnects), it is recorded as cancelled.
+pub(crate) struct Invocation {
+    /// Method that the handler is registered at.
+    method_name: &'static str,
+    /// Time that the invocation began.
+    start: Instant,
+    /// Metrics to record the invocation against, if configured.
+    metrics: Option<Arc<dyn Metrics>>,
+    /// Number of items sent, once the invocation has been accepted as a subscription.
+    items: Option<usize>,
+    /// Whether the outcome of the invocation has been recorded.
+    completed: bool,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Invocation {
+    /// Begin a new invocation of the handler registered at `method_name`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn start(
+        method_name: &'static str,
+        meta: &HandlerMeta,
+        extensions: &Extensions,
+        metrics: Option<Arc<dyn Metrics>>,
+    ) -> Self {
+        Self {
+            method_name,
+            start: Instant::now(),
+            metrics,
+            items: None,
+            completed: false,
+            #[cfg(feature = "tracing")]
+            span: {
+                let info = extensions.get::<RequestInfo>();
+
+                // Span names must be static, so the method is included as `otel.name` which most
+                // subscribers will use in place of the name.
+                tracing::info_span!(
+                    "qubit.handler",
+                    otel.name = method_name,
+                    method = method_name,
+                    kind = ?meta.kind,
+                    id = info.map(|info| info.id().to_string()),
+                    transport = info.map(|info| tracing::field::debug(info.transport())),
+                    duration_ms = tracing::field::Empty,
+                    outcome = tracing::field::Empty,
+                    error_code = tracing::field::Empty,
+                    count = tracing::field::Empty,
+                )
+            },
+        }
+    }
+
+    /// Run the future produced by `f` within this invocation.
+    pub(crate) fn run<F: Future>(
+        self,
+        f: impl FnOnce(Self) -> F,
+    ) -> impl Future<Output = F::Output> {
+        #[cfg(feature = "tracing")]
+        {
+            let span = self.span.clone();
+            tracing::Instrument::instrument(f(self), span)
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        f(self)
+    }
+
+    /// Complete the invocation, recording whether it succeeded.
+    pub(crate) fn finish(mut self, result: Result<(), ErrorCode>) {
+        self.complete(result);
+    }
+
+    /// A subscription was accepted, and will begin emitting items.
+    pub(crate) fn subscribed(&mut self) {
+        self.items = Some(0);
+
+        if let Some(metrics) = &self.metrics {
+            metrics.subscription_started(self.method_name);
+        }
+    }
+
+    /// An item was sent to the subscriber.
+    pub(crate) fn sent(&mut self) {
+        if let Some(items) = &mut self.items {
+            *items += 1;
+        }
+    }
+
+    /// An item couldn't be sent to the subscriber.
+    pub(crate) fn send_failed(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.send_failed(self.method_name);
+        }
+    }
+
+    /// Complete the invocation of a subscription, producing the number of items that were sent.
+    pub(crate) fn unsubscribed(mut self) -> usize {
+        self.complete(Ok(()));
+        self.items.unwrap_or_default()
+    }
+
+    /// Record the outcome of the invocation. Subscriptions are recorded as a call once they end,
+    /// so that the duration covers their entire lifetime.
+    fn complete(&mut self, result: Result<(), ErrorCode>) {
+        self.completed = true;
+        let duration = self.start.elapsed();
+
+        if let Some(metrics) = &self.metrics {
+            if let Some(items) = self.items {
+                metrics.subscription_completed(self.method_name, items, duration);
+            }
+
+            metrics.call_completed(self.method_name, duration, result);
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            self.span
+                .record("duration_ms", duration.as_secs_f64() * 1000.0);
+
+            if let Some(items) = self.items {
+                self.span.record("count", items);
+            }
+
+            match result {
+                Ok(()) => {
+                    self.span.record("outcome", "success");
+                }
+                Err(code) => {
+                    self.span.record(
+                        "outcome",
+                        if code == RpcError::CANCELLED {
+                            "cancelled"
+                        } else {
+                            "error"
+                        },
+                    );
+                    self.span.record("error_code", code.code());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Invocation {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.complete(Err(RpcError::CANCELLED));
+        }
+    }
+}
+
+/// Wrap `f` so that it runs within the current span, for use on another thread such as the blocking
+/// thread pool.
+pub(crate) fn in_current_span<T>(f: impl FnOnce() -> T) -> impl FnOnce() -> T {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        move || span.in_scope(f)
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    f
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use std::{
+        cell::Cell,
+        collections::HashMap,
+        sync::{Arc, Mutex, OnceLock},
+    };
+
+    use tracing::{
+        Event, Metadata, Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        subscriber::Interest,
+    };
+
+    use tracing_core::span::Current;
+
+    use crate::{HandlerKind, RpcError};
+
+    use super::*;
+
+    /// Fields recorded against every span.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<HashMap<String, String>>>);
+
+    /// Metadata of the span, so that it can be reported as the current span once entered.
+    static SPAN_METADATA: OnceLock<&'static Metadata<'static>> = OnceLock::new();
+
+    thread_local! {
+        /// Whether the span has been entered on this thread.
+        static ENTERED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    impl Visit for Recorder {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+            Interest::always()
+        }
+
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            SPAN_METADATA.get_or_init(|| span.metadata());
+            span.record(&mut self.clone());
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &Id, values: &Record<'_>) {
+            values.record(&mut self.clone());
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        fn event(&self, _event: &Event<'_>) {}
+        fn enter(&self, _span: &Id) {
+            ENTERED.set(true);
+
+            // Track every thread that the span is entered on.
+            self.0.lock().unwrap().insert(
+                format!("entered.{:?}", std::thread::current().id()),
+                String::new(),
+            );
+        }
+
+        fn exit(&self, _span: &Id) {
+            ENTERED.set(false);
+        }
+
+        fn current_span(&self) -> Current {
+            match SPAN_METADATA.get() {
+                Some(metadata) if ENTERED.get() => Current::new(Id::from_u64(1), metadata),
+                _ => Current::none(),
+            }
+        }
+    }
+
+    const META: HandlerMeta = HandlerMeta::new(HandlerKind::Mutation, "handler", &[]);
+
+    #[tokio::test]
+    async fn records_span() {
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        Invocation::start("nested.handler", &META, &Extensions::new(), None)
+            .run(|invocation| async move { invocation.finish(Err(RpcError::UNAUTHORISED)) })
+            .await;
+
+        let fields = recorder.0.lock().unwrap().clone();
+        assert_eq!(fields["method"], "nested.handler");
+        assert_eq!(fields["otel.name"], "nested.handler");
+        assert_eq!(fields["kind"], "Mutation");
+        assert_eq!(fields["outcome"], "error");
+        assert_eq!(fields["error_code"], "-32001");
+        assert!(fields.contains_key("duration_ms"));
+    }
+
+    #[test]
+    fn dropped_span() {
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        drop(Invocation::start(
+            "handler",
+            &META,
+            &Extensions::new(),
+            None,
+        ));
+
+        let fields = recorder.0.lock().unwrap().clone();
+        assert_eq!(fields["outcome"], "cancelled");
+        assert_eq!(fields["error_code"], RpcError::CANCELLED.code().to_string());
+    }
+
+    #[test]
+    fn subscription_span() {
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        let mut invocation = Invocation::start("handler", &META, &Extensions::new(), None);
+        invocation.subscribed();
+        invocation.sent();
+        invocation.sent();
+        assert_eq!(invocation.unsubscribed(), 2);
+
+        let fields = recorder.0.lock().unwrap().clone();
+        assert_eq!(fields["outcome"], "success");
+        assert_eq!(fields["count"], "2");
+    }
+
+    #[tokio::test]
+    async fn blocking_span() {
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        Invocation::start("handler", &META, &Extensions::new(), None)
+            .run(|invocation| async move {
+                tokio::task::spawn_blocking(in_current_span(|| {}))
+                    .await
+                    .unwrap();
+                invocation.finish(Ok(()));
+            })
+            .await;
+
+        // The span was entered on both the runtime and the blocking thread.
+        let fields = recorder.0.lock().unwrap().clone();
+        let current = format!("entered.{:?}", std::thread::current().id());
+        assert!(fields.contains_key(&current));
+        assert!(
+            fields
+                .keys()
+                .any(|key| key.starts_with("entered.") && *key != current)
+        );
+    }
+}
@@ -31,3 +31,25 @@ impl<MReturn> HandlerReturnMarker for MFuture<MReturn> where MReturn: HandlerRet
 /// Marker for a [`Stream`](futures::stream::Stream), consisting of [`ResponseMarker`].
 pub struct MStream<MValue: ResponseMarker>(PhantomData<MValue>);
 impl<MValue> HandlerReturnMarker for MStream<MValue> where MValue: ResponseMarker {}
+
+/// Marker for any [`HandlerReturnMarker`] which produces a single response, so can be registered
+/// as a query or mutation.
+#[diagnostic::on_unimplemented(
+    message = "queries and mutations must return a single value, not a stream",
+    label = "handler returns a stream",
+    note = "use `subscription` for handlers which return a stream"
+)]
+pub trait ResponseReturnMarker: HandlerReturnMarker {}
+impl<MValue> ResponseReturnMarker for MResponse<MValue> where MValue: ResponseMarker {}
+impl<MValue> ResponseReturnMarker for MFuture<MResponse<MValue>> where MValue: ResponseMarker {}
+
+/// Marker for any [`HandlerReturnMarker`] which produces a stream, so can be registered as a
+/// subscription.
+#[diagnostic::on_unimplemented(
+    message = "subscriptions must return a stream",
+    label = "handler doesn't return a stream",
+    note = "use `query` or `mutation` for handlers which return a single value"
+)]
+pub trait StreamReturnMarker: HandlerReturnMarker {}
+impl<MValue> StreamReturnMarker for MStream<MValue> where MValue: ResponseMarker {}
+impl<MValue> StreamReturnMarker for MFuture<MStream<MValue>> where MValue: ResponseMarker {}
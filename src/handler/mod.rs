@@ -1,20 +1,37 @@
+pub mod cancellation;
 pub mod ctx;
+mod instrument;
 pub mod marker;
 pub mod response;
 pub mod ts;
 
 use futures::{Stream, StreamExt};
 use jsonrpsee::{
-    DisconnectError, RpcModule, SubscriptionCloseResponse, SubscriptionMessage,
-    types::{Params, ResponsePayload},
+    DisconnectError, Extensions, RpcModule, SubscriptionCloseResponse, SubscriptionMessage,
+    types::{
+        Params, ResponsePayload,
+        error::{ErrorCode, INVALID_PARAMS_MSG},
+    },
 };
-use serde::Deserialize;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::OwnedSemaphorePermit;
 use ts_rs::TS;
 
-use std::pin::pin;
+use std::{collections::HashMap, pin::pin, sync::Arc, time::Duration};
 
-use self::{ctx::FromRequestExtensions, response::ResponseValue, ts::TsTypeTuple};
+use crate::{
+    RpcError,
+    concurrency::{ConcurrencyLimit, ConcurrencyLimiter},
+    metrics::Metrics,
+    rate_limit::RateLimiter,
+    reflection::handler::HandlerMeta,
+    router::binary::{BinaryResponse, ResultValue},
+};
+
+use self::{
+    ctx::FromRequestExtensions, instrument::Invocation, response::ResponseValue, ts::TsTypeTuple,
+};
 
 /// A handler suitable for use with Qubit.
 ///
@@ -24,13 +41,16 @@ pub trait QubitHandler<Ctx, MSig>: 'static + Send + Sync + Clone {
     /// Context type this handler expects.
     type Ctx: 'static + Send + Sync + FromRequestExtensions<Ctx>;
     /// Parameters that the handler will accept (excluding [`Ctx`](QubitHandler::Ctx)).
-    type Params: TsTypeTuple;
+    type Params: 'static + Send + TsTypeTuple;
     /// Return type of the handler.
     type Return;
 
-    /// Call the handler with the provided `Ctx` and [`Params`]. The handler implementation
-    /// must deserialise the parameters as required.
-    fn call(&self, ctx: Self::Ctx, params: Params) -> Self::Return;
+    /// Deserialise the positional [`Params`] of a call into [`Self::Params`], using `meta` to
+    /// determine which trailing parameters may be omitted.
+    fn parse_params(params: &Params, meta: &HandlerMeta) -> Result<Self::Params, RpcError>;
+
+    /// Call the handler with the provided `Ctx` and parsed parameters.
+    fn call(&self, ctx: Self::Ctx, params: Self::Params) -> Self::Return;
 }
 
 macro_rules! impl_handlers {
@@ -53,20 +73,20 @@ macro_rules! impl_handlers {
             type Params = ($($($params,)*)?);
             type Return = R;
 
+            fn parse_params(
+                params: &Params,
+                #[allow(unused)] meta: &HandlerMeta,
+            ) -> Result<Self::Params, RpcError> {
+                impl_handlers!(parse_impl params meta -> [$($($params,)*)?])
+            }
+
             fn call(
                 &self,
                 #[allow(unused)] ctx: Self::Ctx,
-                #[allow(unused)] params: Params
+                #[allow(unused)] params: Self::Params,
             ) -> Self::Return {
                 #[allow(non_snake_case)]
-                let ($($($params,)*)?) = match impl_handlers!(parse_impl params -> [$($($params,)*)?]) {
-                    Ok(params) => params,
-                    Err(e) => {
-                        // TODO: Something
-                        dbg!(e);
-                        panic!("fukc");
-                    }
-                };
+                let ($($($params,)*)?) = params;
 
                 // Call the handler, optionally with the context and any parameters.
                 self($(ctx, $($params,)*)?)
@@ -92,12 +112,17 @@ macro_rules! impl_handlers {
     // Otherwise if the parameter does require parameters, they will be parsed as normal.
     //
     // This can be reverted once the following PR lands: https://github.com/serde-rs/json/pull/869
-    (parse_impl $params:ident -> []) => {
-        $params.parse::<[(); 0]>()
+    (parse_impl $params:ident $meta:ident -> []) => {
+        parse_params::<[(); 0]>($params, 0, 0, &[])
             .map(|_| ())
     };
-    (parse_impl $params:ident -> [$($param_tys:ident,)*]) => {
-        $params.parse::<Self::Params>()
+    (parse_impl $params:ident $meta:ident -> [$($param_tys:ident,)*]) => {
+        parse_params::<Self::Params>(
+            $params,
+            impl_handlers!(count [$($param_tys,)*]),
+            $meta.optional_params,
+            $meta.param_names,
+        )
     };
 
     (count []) => { 0 };
@@ -120,6 +145,322 @@ impl_handlers!(
     P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14, P15
 );
 
+/// Parse positional parameters into `P`, a tuple of `count` parameters. The last `optional`
+/// parameters may be omitted, in which case they are treated as `null`. Omitting any other
+/// parameter is rejected, naming the first missing parameter when its name is known.
+fn parse_params<P>(
+    params: &Params,
+    count: usize,
+    optional: usize,
+    param_names: &[&str],
+) -> Result<P, RpcError>
+where
+    P: for<'a> Deserialize<'a>,
+{
+    let mut values = serde_json::from_str::<Option<Vec<Value>>>(params.as_str().unwrap_or("null"))
+        .map_err(invalid_params)?
+        .unwrap_or_default();
+
+    let required = count.saturating_sub(optional);
+    if values.len() < required {
+        let missing = values.len();
+        return Err(invalid_params(match param_names.get(missing) {
+            Some(name) => format!("missing required parameter `{name}`"),
+            None => format!("missing required parameter at position {missing}"),
+        }));
+    }
+
+    if values.len() < count {
+        values.resize(count, Value::Null);
+    }
+
+    serde_json::from_value(Value::Array(values)).map_err(invalid_params)
+}
+
+/// Produce an invalid parameters error, with the reason as its data.
+fn invalid_params(reason: impl ToString) -> RpcError {
+    RpcError {
+        code: ErrorCode::InvalidParams,
+        message: INVALID_PARAMS_MSG.to_string(),
+        data: Some(Value::String(reason.to_string())),
+    }
+}
+
+/// Options that apply to every handler registered to a module, configured through
+/// [`RpcModule`](crate::RpcModule).
+pub struct RegisterOptions<Ctx> {
+    /// Metrics to record for each call.
+    pub(crate) metrics: Option<Arc<dyn Metrics>>,
+    /// Rate limiter to enforce the rate limit of each handler.
+    pub(crate) rate_limiter: Option<RateLimiter<Ctx>>,
+    /// Timeout for queries and mutations that don't declare their own.
+    pub(crate) timeout: Option<Duration>,
+    /// Concurrency limits that override those declared by handlers, keyed by method name.
+    pub(crate) concurrency_limits: Arc<HashMap<String, ConcurrencyLimit>>,
+    /// Whether every synchronous query and mutation runs on the blocking thread pool.
+    pub(crate) blocking: bool,
+}
+
+impl<Ctx> Clone for RegisterOptions<Ctx> {
+    fn clone(&self) -> Self {
+        Self {
+            metrics: self.metrics.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            timeout: self.timeout,
+            concurrency_limits: Arc::clone(&self.concurrency_limits),
+            blocking: self.blocking,
+        }
+    }
+}
+
+impl<Ctx> Default for RegisterOptions<Ctx> {
+    fn default() -> Self {
+        Self {
+            metrics: None,
+            rate_limiter: None,
+            timeout: None,
+            concurrency_limits: Arc::default(),
+            blocking: false,
+        }
+    }
+}
+
+impl<Ctx> RegisterOptions<Ctx> {
+    /// Create a limiter for the concurrency limit of the handler at `method_name`, if it has one.
+    fn concurrency_limiter(
+        &self,
+        method_name: &str,
+        meta: &HandlerMeta,
+    ) -> Option<ConcurrencyLimiter> {
+        self.concurrency_limits
+            .get(method_name)
+            .copied()
+            .or(meta.max_concurrency)
+            .map(ConcurrencyLimiter::new)
+    }
+}
+
+/// A handler being registered at a method, which wraps every call to the handler with the
+/// instrumentation, limits and cancellation that apply to it.
+struct Registration<Ctx> {
+    /// Method that the handler is registered at.
+    method_name: &'static str,
+    meta: Arc<HandlerMeta>,
+    options: RegisterOptions<Ctx>,
+    /// Limiter shared by every call, if the handler has a concurrency limit.
+    concurrency: Option<ConcurrencyLimiter>,
+}
+
+impl<Ctx> Clone for Registration<Ctx> {
+    fn clone(&self) -> Self {
+        Self {
+            method_name: self.method_name,
+            meta: Arc::clone(&self.meta),
+            options: self.options.clone(),
+            concurrency: self.concurrency.clone(),
+        }
+    }
+}
+
+impl<Ctx> Registration<Ctx>
+where
+    Ctx: 'static + Clone + Send + Sync,
+{
+    fn new(method_name: String, meta: Arc<HandlerMeta>, options: &RegisterOptions<Ctx>) -> Self {
+        let method_name: &'static str = Box::leak(method_name.into_boxed_str());
+
+        Self {
+            method_name,
+            concurrency: options.concurrency_limiter(method_name, &meta),
+            meta,
+            options: options.clone(),
+        }
+    }
+
+    /// Register a query or mutation using [`RpcModule::register_async_method`]. Once the parameters
+    /// of a call have been parsed with `parse` and its context prepared, `call` runs the handler.
+    /// Any concurrency permit is passed to `call`, and must be held until the handler completes.
+    fn register_method<C, P, V, F, Fut>(
+        self,
+        module: &mut RpcModule<Ctx>,
+        parse: fn(&Params, &HandlerMeta) -> Result<P, RpcError>,
+        call: F,
+    ) where
+        C: 'static + Send + FromRequestExtensions<Ctx>,
+        P: 'static + Send,
+        V: 'static + Clone + Serialize,
+        F: 'static + Clone + Send + Sync + Fn(C, P, Option<OwnedSemaphorePermit>) -> Fut,
+        Fut: Send + Future<Output = Result<V, RpcError>>,
+    {
+        let method_name = self.method_name;
+
+        module
+            .register_async_method(method_name, move |params, ctx, extensions| {
+                let this = self.clone();
+                let call = call.clone();
+
+                Invocation::start(
+                    method_name,
+                    &this.meta,
+                    &extensions,
+                    this.options.metrics.clone(),
+                )
+                .run(move |invocation| async move {
+                    let this = &this;
+                    let binary = BinaryResponse::is_requested(&extensions);
+                    let timeout = this.meta.timeout.or(this.options.timeout);
+                    let result = cancellation::run(timeout, extensions, |extensions| async move {
+                        let params = parse(&params, &this.meta)?;
+                        let (ctx, permit) = this.prepare((*ctx).clone(), extensions).await?;
+
+                        call(ctx, params, permit).await
+                    })
+                    .await;
+
+                    match result {
+                        Ok(result) => {
+                            invocation.finish(Ok(()));
+                            ResponsePayload::success(ResultValue::new(result, binary))
+                        }
+                        Err(e) => {
+                            invocation.finish(Err(e.code));
+                            ResponsePayload::error(e)
+                        }
+                    }
+                })
+            })
+            .unwrap();
+    }
+
+    /// Register a subscription using [`RpcModule::register_subscription`]. Once the parameters of a
+    /// call have been parsed with `parse` and its context prepared, the subscription is accepted
+    /// and `subscribe` produces the stream of items to send.
+    fn register_subscription<C, P, S, F, Fut>(
+        self,
+        module: &mut RpcModule<Ctx>,
+        parse: fn(&Params, &HandlerMeta) -> Result<P, RpcError>,
+        subscribe: F,
+    ) where
+        C: 'static + Send + FromRequestExtensions<Ctx>,
+        P: 'static + Send,
+        S: Send + Stream,
+        S::Item: Serialize,
+        F: 'static + Clone + Send + Sync + Fn(C, P) -> Fut,
+        Fut: Send + Future<Output = S>,
+    {
+        let method_name = self.method_name;
+        let notif_method_name = format!("{method_name}_notif");
+        let unsub_method_name = format!("{method_name}_unsub");
+
+        module
+            .register_subscription(
+                method_name,
+                Box::leak(notif_method_name.into_boxed_str()),
+                Box::leak(unsub_method_name.into_boxed_str()),
+                move |params, pending, ctx, extensions| {
+                    let this = self.clone();
+                    let subscribe = subscribe.clone();
+
+                    // The invocation will span the entire lifetime of the subscription.
+                    Invocation::start(
+                        method_name,
+                        &this.meta,
+                        &extensions,
+                        this.options.metrics.clone(),
+                    )
+                    .run(move |mut invocation| async move {
+                        // Cancel the call if the subscription is closed before the stream
+                        // completes.
+                        let mut extensions = extensions;
+                        let guard = cancellation::attach(&mut extensions).drop_guard();
+                        let binary = BinaryResponse::is_requested(&extensions);
+
+                        let prepared = async {
+                            let params = parse(&params, &this.meta)?;
+                            let (ctx, permit) = this.prepare((*ctx).clone(), extensions).await?;
+
+                            Ok::<_, RpcError>((ctx, params, permit))
+                        };
+
+                        let (ctx, params, _permit) = match prepared.await {
+                            Ok(prepared) => prepared,
+                            Err(e) => {
+                                invocation.finish(Err(e.code));
+                                pending.reject(e).await;
+                                return SubscriptionCloseResponse::None;
+                            }
+                        };
+
+                        let sink = pending.accept().await.unwrap();
+                        invocation.subscribed();
+
+                        let subscription_id = sink.subscription_id();
+
+                        let stream = pin!(subscribe(ctx, params).await.take_until(sink.closed()));
+
+                        // Items are serialised as they are produced, as they may not be `Send`.
+                        let mut items = stream.map(|item| {
+                            serde_json::value::to_raw_value(&ResultValue::new(item, binary))
+                                .unwrap()
+                        });
+
+                        while let Some(item) = items.next().await {
+                            if let Some(DisconnectError(..)) = sink.send(item).await.err() {
+                                invocation.send_failed();
+                                break;
+                            };
+
+                            invocation.sent();
+                        }
+
+                        // The stream completed, so any tasks that it spawned may continue.
+                        if !sink.is_closed() {
+                            guard.disarm();
+                        }
+
+                        let count = invocation.unsubscribed();
+
+                        // Notify that stream is closing
+                        SubscriptionCloseResponse::Notif(SubscriptionMessage::from(
+                            serde_json::value::to_raw_value(&ResultValue::new(
+                                json!({ "close_stream": subscription_id, "count": count }),
+                                binary,
+                            ))
+                            .unwrap(),
+                        ))
+                    })
+                },
+            )
+            .unwrap();
+    }
+
+    /// Prepare to call the handler by enforcing any limits, and then extracting its context. If the
+    /// handler has a concurrency limit, the returned permit must be held until the call completes.
+    async fn prepare<C>(
+        &self,
+        ctx: Ctx,
+        extensions: Extensions,
+    ) -> Result<(C, Option<OwnedSemaphorePermit>), RpcError>
+    where
+        C: FromRequestExtensions<Ctx>,
+    {
+        if let Some(limit) = self.meta.rate_limit
+            && let Some(rate_limiter) = &self.options.rate_limiter
+        {
+            rate_limiter
+                .check(self.method_name, limit, ctx.clone(), &extensions)
+                .await?;
+        }
+
+        let permit = match &self.concurrency {
+            Some(concurrency) => Some(concurrency.acquire(&extensions).await?),
+            None => None,
+        };
+
+        Ok((C::from_request_extensions(ctx, extensions).await?, permit))
+    }
+}
+
 /// Registration implementation differs depending on the return type of the handler. This
 /// is to account for handlers which may return futures, streams, or values directly.
 pub trait RegisterableHandler<
@@ -134,8 +475,14 @@ pub trait RegisterableHandler<
     /// derived from a handler return value.
     type Response: ResponseValue<MValue>;
 
-    /// Register this handler against the provided RPC module.
-    fn register(self, module: &mut RpcModule<Ctx>, method_name: String);
+    /// Register this handler against the provided RPC module, using the associated metadata.
+    fn register(
+        self,
+        module: &mut RpcModule<Ctx>,
+        method_name: String,
+        meta: Arc<HandlerMeta>,
+        options: &RegisterOptions<Ctx>,
+    );
 }
 
 /// Register any handler that directly returns a [`ResponseValue`]. This will generally be the
@@ -145,37 +492,53 @@ where
     Ctx: 'static + Clone + Send + Sync,
     MValue: marker::ResponseMarker,
     T: QubitHandler<Ctx, MSig>,
-    T::Return: ResponseValue<MValue>,
+    T::Return: 'static + Send + ResponseValue<MValue>,
 {
     /// The response is whatever is returned from the handler (plus any additional processing from
     /// [`ResponseValue::transform`]).
     type Response = T::Return;
 
-    /// These handlers will be registered using [`RpcModule::register_blocking_method`], so that
-    /// the handler can be run on a new thread without blocking the server.
-    fn register(self, module: &mut RpcModule<Ctx>, method_name: String) {
-        module
-            .register_async_method(
-                Box::leak(method_name.into_boxed_str()),
-                move |params, ctx, extensions| {
-                    let handler = self.clone();
-
-                    async move {
-                        let ctx =
-                            match Self::Ctx::from_request_extensions((*ctx).clone(), extensions)
-                                .await
-                            {
-                                Ok(ctx) => ctx,
-                                Err(e) => {
-                                    return ResponsePayload::error(e);
-                                }
-                            };
-                        let result = handler.call(ctx, params);
-                        ResponsePayload::success(result.transform())
+    /// These handlers will be registered using [`RpcModule::register_async_method`]. The handler
+    /// is called directly on the runtime, unless it is `blocking`, in which case it is run on the
+    /// blocking thread pool once the context has been prepared.
+    fn register(
+        self,
+        module: &mut RpcModule<Ctx>,
+        method_name: String,
+        meta: Arc<HandlerMeta>,
+        options: &RegisterOptions<Ctx>,
+    ) {
+        let blocking = meta.blocking || options.blocking;
+
+        Registration::new(method_name, meta, options).register_method(
+            module,
+            Self::parse_params,
+            move |ctx, params, permit| {
+                let handler = self.clone();
+
+                async move {
+                    if !blocking {
+                        let _permit = permit;
+                        return Ok(handler.call(ctx, params).transform());
                     }
-                },
-            )
-            .unwrap();
+
+                    // The permit is held by the blocking task, as it will continue running even if
+                    // the call times out or is cancelled.
+                    let call = instrument::in_current_span(move || {
+                        let _permit = permit;
+                        handler.call(ctx, params)
+                    });
+
+                    match tokio::task::spawn_blocking(call).await {
+                        Ok(result) => Ok(result.transform()),
+                        // Propagate any panic, as if the handler had been called directly.
+                        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                        // The task can only be cancelled if the runtime is shutting down.
+                        Err(_) => Err(RpcError::cancelled()),
+                    }
+                }
+            },
+        );
     }
 }
 
@@ -194,29 +557,25 @@ where
     type Response = <T::Return as Future>::Output;
 
     /// These handlers will be registered using [`RpcModule::register_async_method`].
-    fn register(self, module: &mut RpcModule<Ctx>, method_name: String) {
-        module
-            .register_async_method(
-                Box::leak(method_name.into_boxed_str()),
-                move |params, ctx, extensions| {
-                    let f = self.clone();
-
-                    async move {
-                        let ctx =
-                            match Self::Ctx::from_request_extensions((*ctx).clone(), extensions)
-                                .await
-                            {
-                                Ok(ctx) => ctx,
-                                Err(e) => {
-                                    return ResponsePayload::error(e);
-                                }
-                            };
-                        let result = f.call(ctx, params).await;
-                        ResponsePayload::success(result.transform())
-                    }
-                },
-            )
-            .unwrap();
+    fn register(
+        self,
+        module: &mut RpcModule<Ctx>,
+        method_name: String,
+        meta: Arc<HandlerMeta>,
+        options: &RegisterOptions<Ctx>,
+    ) {
+        Registration::new(method_name, meta, options).register_method(
+            module,
+            Self::parse_params,
+            move |ctx, params, permit| {
+                let f = self.clone();
+
+                async move {
+                    let _permit = permit;
+                    Ok(f.call(ctx, params).await.transform())
+                }
+            },
+        );
     }
 }
 
@@ -236,63 +595,24 @@ where
     type Response = <T::Return as Stream>::Item;
 
     /// These handlers will be registered usig [`RpcModule::register_subscription`].
-    fn register(self, module: &mut RpcModule<Ctx>, method_name: String) {
-        let notif_method_name = format!("{method_name}_notif");
-        let unsub_method_name = format!("{method_name}_unsub");
-
-        module
-            .register_subscription(
-                Box::leak(method_name.into_boxed_str()),
-                Box::leak(notif_method_name.into_boxed_str()),
-                Box::leak(unsub_method_name.into_boxed_str()),
-                move |params, pending, ctx, extensions| {
-                    let f = self.clone();
-
-                    async move {
-                        let ctx =
-                            match Self::Ctx::from_request_extensions((*ctx).clone(), extensions)
-                                .await
-                            {
-                                Ok(ctx) => ctx,
-                                Err(e) => {
-                                    pending.reject(e).await;
-                                    return SubscriptionCloseResponse::None;
-                                }
-                            };
-
-                        let sink = pending.accept().await.unwrap();
-
-                        // Track the number of items emitted through the subscription
-                        let mut count = 0;
-                        let subscription_id = sink.subscription_id();
-
-                        let mut stream = pin!(f.call(ctx, params));
-
-                        while let Some(item) = stream.next().await {
-                            let item = serde_json::value::to_raw_value(&item.transform()).unwrap();
-                            if let Some(DisconnectError(..)) = sink.send(item).await.err() {
-                                break;
-                            };
-
-                            count += 1;
-                        }
-
-                        // Notify that stream is closing
-                        SubscriptionCloseResponse::Notif(SubscriptionMessage::from(
-                            serde_json::value::to_raw_value(
-                                &json!({ "close_stream": subscription_id, "count": count }),
-                            )
-                            .unwrap(),
-                        ))
-                    }
-                },
-            )
-            .unwrap();
+    fn register(
+        self,
+        module: &mut RpcModule<Ctx>,
+        method_name: String,
+        meta: Arc<HandlerMeta>,
+        options: &RegisterOptions<Ctx>,
+    ) {
+        Registration::new(method_name, meta, options).register_subscription(
+            module,
+            Self::parse_params,
+            move |ctx, params| {
+                let stream = self.call(ctx, params).map(|item| item.transform());
+                std::future::ready(stream)
+            },
+        );
     }
 }
 
-// TODO: Combine the duplicated `register_subscription` logic between sync and async streams.
-
 /// Register any handler that returns a [`Future`] that outputs a [`Stream`] containing items
 /// implementing [`ResponseValue`]. This implementation only supports async handlers.
 impl<Ctx, T, MValue, MSig>
@@ -307,57 +627,21 @@ where
 {
     type Response = <<T::Return as Future>::Output as Stream>::Item;
 
-    fn register(self, module: &mut RpcModule<Ctx>, method_name: String) {
-        let notif_method_name = format!("{method_name}_notif");
-        let unsub_method_name = format!("{method_name}_unsub");
-
-        module
-            .register_subscription(
-                Box::leak(method_name.into_boxed_str()),
-                Box::leak(notif_method_name.into_boxed_str()),
-                Box::leak(unsub_method_name.into_boxed_str()),
-                move |params, pending, ctx, extensions| {
-                    let f = self.clone();
-
-                    async move {
-                        let ctx =
-                            match Self::Ctx::from_request_extensions((*ctx).clone(), extensions)
-                                .await
-                            {
-                                Ok(ctx) => ctx,
-                                Err(e) => {
-                                    pending.reject(e).await;
-                                    return SubscriptionCloseResponse::None;
-                                }
-                            };
-
-                        let sink = pending.accept().await.unwrap();
-
-                        // Track the number of items emitted through the subscription
-                        let mut count = 0;
-                        let subscription_id = sink.subscription_id();
-
-                        let mut stream = pin!(f.call(ctx, params).await);
-
-                        while let Some(item) = stream.next().await {
-                            let item = serde_json::value::to_raw_value(&item.transform()).unwrap();
-                            if let Some(DisconnectError(..)) = sink.send(item).await.err() {
-                                break;
-                            };
-                            count += 1;
-                        }
-
-                        // Notify that stream is closing
-                        SubscriptionCloseResponse::Notif(SubscriptionMessage::from(
-                            serde_json::value::to_raw_value(
-                                &json!({ "close_stream": subscription_id, "count": count }),
-                            )
-                            .unwrap(),
-                        ))
-                    }
-                },
-            )
-            .unwrap();
+    fn register(
+        self,
+        module: &mut RpcModule<Ctx>,
+        method_name: String,
+        meta: Arc<HandlerMeta>,
+        options: &RegisterOptions<Ctx>,
+    ) {
+        Registration::new(method_name, meta, options).register_subscription(
+            module,
+            Self::parse_params,
+            move |ctx, params| {
+                let f = self.clone();
+                async move { f.call(ctx, params).await.map(|item| item.transform()) }
+            },
+        );
     }
 }
 
@@ -373,6 +657,9 @@ mod test {
 
     use std::{fmt::Debug, iter};
 
+    /// Metadata used when registering handlers in tests.
+    const TEST_META: HandlerMeta = HandlerMeta::new(crate::HandlerKind::Query, "handler", &[]);
+
     mod register {
         //! Test registering different kinds of handlers to a [`RpcModule`], and call them to
         //! ensure they produce the correct response.
@@ -401,7 +688,13 @@ mod test {
             F: RegisterableHandler<(), MSig, MValue, MReturn, Ctx = ()>,
         {
             let mut module = RpcModule::new(());
-            F::register(handler, &mut module, "handler".to_string());
+            F::register(
+                handler,
+                &mut module,
+                "handler".to_string(),
+                Arc::new(TEST_META),
+                &RegisterOptions::default(),
+            );
             module
         }
 
@@ -483,26 +776,56 @@ mod test {
     >(
         #[case] handler: impl RegisterableHandler<(), MSig, MValue, MReturn, Ctx = ()>,
     ) {
-        handler.register(&mut RpcModule::new(()), "handler".to_string());
+        handler.register(
+            &mut RpcModule::new(()),
+            "handler".to_string(),
+            Arc::new(TEST_META),
+            &RegisterOptions::default(),
+        );
     }
 
-    /// Call some handlers, and assert the output.
+    /// Call some handlers with their trailing `optional` parameters, and assert the output.
     #[rstest]
-    #[case(|| {}, json!([]), ())]
-    #[case(|_ctx: ()| {}, json!([]), ())]
-    #[case(|_ctx: (), param: u32| param, json!([123]), 123)]
-    #[case(|_ctx: (), param_1: u32, param_2: String| -> (u32, String) { (param_1, param_2) }, json!([123, "hello"]), (123, "hello".to_string()))]
-    fn call_handler<H, MSig>(#[case] handler: H, #[case] params: Value, #[case] expected: H::Return)
-    where
+    #[case(|| {}, json!([]), 0, ())]
+    #[case(|_ctx: ()| {}, json!([]), 0, ())]
+    #[case(|_ctx: (), param: u32| param, json!([123]), 0, 123)]
+    #[case(|_ctx: (), param_1: u32, param_2: String| -> (u32, String) { (param_1, param_2) }, json!([123, "hello"]), 0, (123, "hello".to_string()))]
+    #[case::omitted_optional(|_ctx: (), param: Option<u32>| param, json!([]), 1, None)]
+    #[case::provided_optional(|_ctx: (), param: Option<u32>| param, json!([123]), 1, Some(123))]
+    #[case::trailing_optional(|_ctx: (), param_1: u32, param_2: Option<String>| -> (u32, Option<String>) { (param_1, param_2) }, json!([123]), 1, (123, None))]
+    fn call_handler<H, MSig>(
+        #[case] handler: H,
+        #[case] params: Value,
+        #[case] optional: usize,
+        #[case] expected: H::Return,
+    ) where
         H: QubitHandler<(), MSig, Ctx = ()>,
         H::Return: Debug + PartialEq,
     {
-        let output = handler.call(
-            (),
-            Params::new(Some(&serde_json::to_string(&params).unwrap())).into_owned(),
-        );
+        let meta = HandlerMeta {
+            optional_params: optional,
+            ..TEST_META
+        };
+        let params = H::parse_params(
+            &Params::new(Some(&serde_json::to_string(&params).unwrap())),
+            &meta,
+        )
+        .unwrap();
+
+        assert_eq!(handler.call((), params), expected);
+    }
 
-        assert_eq!(output, expected);
+    #[rstest]
+    #[case::missing_required(json!([]), "missing required parameter `a`")]
+    #[case::missing_unnamed(json!([1]), "missing required parameter at position 1")]
+    #[case::too_many(json!([1, 2, 3, 4]), "invalid length 4, expected fewer elements in array")]
+    #[case::wrong_type(json!(["hello", 1]), "invalid type: string \"hello\", expected u32")]
+    #[case::not_array(json!({ "a": 1 }), "invalid type: map, expected a sequence at line 1 column 0")]
+    fn parse_invalid_params(#[case] params: Value, #[case] reason: &str) {
+        let params = Params::new(Some(&serde_json::to_string(&params).unwrap())).into_owned();
+        let err = parse_params::<(u32, u32, Option<u32>)>(&params, 3, 1, &["a"]).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidParams);
+        assert_eq!(err.data, Some(json!(reason)));
     }
 
     /// Sample CTX.
@@ -525,7 +848,12 @@ mod test {
     #[test]
     fn derived_ctx() {
         fn handler(_ctx: DerivedCtx) {}
-        handler.register(&mut RpcModule::new(SampleCtx), "handler".to_string());
+        handler.register(
+            &mut RpcModule::new(SampleCtx),
+            "handler".to_string(),
+            Arc::new(TEST_META),
+            &RegisterOptions::default(),
+        );
     }
 
     /// Assert that a handler implements [`RegisterableHandler`], and the reflected TS types are correct.
@@ -1,6 +1,13 @@
+pub mod auth;
+mod bytes;
+#[cfg(feature = "cli")]
+pub mod cli;
 mod codegen;
+pub mod concurrency;
 mod error;
 mod handler;
+pub mod metrics;
+pub mod rate_limit;
 mod reflection;
 mod router;
 mod util;
@@ -8,10 +15,19 @@ mod util;
 pub use qubit_macros::*;
 
 pub use self::{
+    bytes::Bytes,
     codegen::*,
     error::*,
-    handler::{QubitHandler, RegisterableHandler, ctx::FromRequestExtensions},
-    router::Router,
+    handler::{
+        QubitHandler, RegisterableHandler,
+        cancellation::Cancellation,
+        ctx::{Extractors, FromRequestExtensions},
+    },
+    reflection::handler::HandlerKind,
+    router::{
+        Csrf, RequestInfo, ResponseParts, Router, RouterHandler, RpcModule, TestClient,
+        TestSubscription, Transport,
+    },
 };
 
 pub use jsonrpsee::Extensions;
@@ -0,0 +1,476 @@
+//! Collection of per-method metrics, such as call counts, errors and latency.
+//!
+//! Any implementation of [`Metrics`] can be provided to [`RpcModule::with_metrics`], and will be
+//! called as handlers are run. [`InMemoryMetrics`] is a simple implementation, which can be
+//! exported in the Prometheus text format.
+//!
+//! ```
+//! # use qubit::{Router, metrics::InMemoryMetrics};
+//! let metrics = InMemoryMetrics::new();
+//! let (qubit_service, handle) = Router::<()>::new()
+//!     .as_rpc(())
+//!     .with_metrics(metrics.clone())
+//!     .into_service();
+//!
+//! let app = axum::Router::<()>::new()
+//!     .nest_service("/rpc", qubit_service)
+//!     .route_service("/metrics", metrics.service());
+//! ```
+//!
+//! [`RpcModule::with_metrics`]: crate::RpcModule::with_metrics
+
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    response::{IntoResponse, Response},
+};
+use futures::future::{Ready, ready};
+use http::{Request, header};
+use tower::{Service, service_fn};
+
+use crate::ErrorCode;
+
+/// Receiver of metrics produced whilst running handlers. Every method has a default (empty)
+/// implementation, so only the metrics of interest need to be implemented.
+pub trait Metrics: 'static + Send + Sync {
+    /// A call to the method completed, either successfully or with an error. Calls which are
+    /// dropped before completing (such as when a HTTP client disconnects) complete with
+    /// [`RpcError::CANCELLED`]. For subscriptions, this is called once the subscription is rejected
+    /// or ends, with its entire lifetime as the duration.
+    ///
+    /// [`RpcError::CANCELLED`]: crate::RpcError::CANCELLED
+    #[allow(unused_variables)]
+    fn call_completed(&self, method: &str, duration: Duration, result: Result<(), ErrorCode>) {}
+
+    /// A subscription to the method was accepted.
+    #[allow(unused_variables)]
+    fn subscription_started(&self, method: &str) {}
+
+    /// A subscription to the method ended, after emitting `items` items over `duration`.
+    #[allow(unused_variables)]
+    fn subscription_completed(&self, method: &str, items: usize, duration: Duration) {}
+
+    /// An item produced by a subscription to the method couldn't be sent, as the subscriber had
+    /// disconnected.
+    #[allow(unused_variables)]
+    fn send_failed(&self, method: &str) {}
+}
+
+/// Upper bounds (in seconds) of the buckets used for call latency.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Upper bounds of the buckets used for the number of items emitted by a subscription.
+const ITEM_BUCKETS: &[f64] = &[0.0, 1.0, 10.0, 100.0, 1000.0, 10000.0];
+
+/// Metrics collected in memory, keyed by method. This is cheap to clone, with all clones sharing
+/// the same metrics.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryMetrics {
+    methods: Arc<Mutex<BTreeMap<String, MethodMetrics>>>,
+}
+
+/// Metrics collected for a single method.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodMetrics {
+    /// Number of calls that have completed.
+    pub calls: u64,
+    /// Number of calls that completed with an error, keyed by the error code.
+    pub errors: BTreeMap<i32, u64>,
+    /// Duration of each call, in seconds.
+    pub latency: Histogram,
+    /// Number of subscriptions that are currently active.
+    pub active_subscriptions: u64,
+    /// Number of items emitted by each subscription once it completed.
+    pub subscription_items: Histogram,
+    /// Number of items that couldn't be sent to a subscriber.
+    pub failed_sends: u64,
+}
+
+impl Default for MethodMetrics {
+    fn default() -> Self {
+        Self {
+            calls: 0,
+            errors: BTreeMap::new(),
+            latency: Histogram::new(LATENCY_BUCKETS),
+            active_subscriptions: 0,
+            subscription_items: Histogram::new(ITEM_BUCKETS),
+            failed_sends: 0,
+        }
+    }
+}
+
+/// Distribution of observed values across a fixed set of buckets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    /// Upper bound of each bucket (inclusive).
+    bounds: &'static [f64],
+    /// Number of observations that fell into each bucket (not cumulative). Observations above the
+    /// largest bound are only included in `count`.
+    buckets: Vec<u64>,
+    /// Sum of all observations.
+    sum: f64,
+    /// Total number of observations.
+    count: u64,
+}
+
+impl Histogram {
+    /// Create an empty histogram with the provided bucket bounds, which must be sorted.
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Record an observation.
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Upper bound of each bucket, paired with the cumulative number of observations less than or
+    /// equal to it.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.bounds
+            .iter()
+            .zip(self.buckets.iter().scan(0, |total, count| {
+                *total += count;
+                Some(*total)
+            }))
+            .map(|(bound, count)| (*bound, count))
+    }
+
+    /// Sum of all observations.
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// Total number of observations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl InMemoryMetrics {
+    /// Create a new, empty, collection of metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current metrics of every method that has been called.
+    pub fn snapshot(&self) -> BTreeMap<String, MethodMetrics> {
+        self.methods.lock().expect("metrics not poisoned").clone()
+    }
+
+    /// Render the current metrics in the Prometheus text format.
+    pub fn render_prometheus(&self) -> String {
+        let methods = self.snapshot();
+        let mut out = String::new();
+
+        let mut family = |name: &str, kind: &str, help: &str, samples: &dyn Fn(&mut String)| {
+            writeln!(out, "# HELP {name} {help}").unwrap();
+            writeln!(out, "# TYPE {name} {kind}").unwrap();
+            samples(&mut out);
+        };
+
+        family(
+            "qubit_calls_total",
+            "counter",
+            "Number of calls that have completed.",
+            &|out| {
+                for (method, metrics) in &methods {
+                    sample(
+                        out,
+                        "qubit_calls_total",
+                        &[("method", method)],
+                        metrics.calls,
+                    );
+                }
+            },
+        );
+
+        family(
+            "qubit_errors_total",
+            "counter",
+            "Number of calls that completed with an error.",
+            &|out| {
+                for (method, metrics) in &methods {
+                    for (code, count) in &metrics.errors {
+                        let code = code.to_string();
+                        sample(
+                            out,
+                            "qubit_errors_total",
+                            &[("method", method), ("code", &code)],
+                            count,
+                        );
+                    }
+                }
+            },
+        );
+
+        family(
+            "qubit_call_duration_seconds",
+            "histogram",
+            "Duration of each call.",
+            &|out| {
+                for (method, metrics) in &methods {
+                    histogram(out, "qubit_call_duration_seconds", method, &metrics.latency);
+                }
+            },
+        );
+
+        family(
+            "qubit_active_subscriptions",
+            "gauge",
+            "Number of subscriptions that are currently active.",
+            &|out| {
+                for (method, metrics) in &methods {
+                    sample(
+                        out,
+                        "qubit_active_subscriptions",
+                        &[("method", method)],
+                        metrics.active_subscriptions,
+                    );
+                }
+            },
+        );
+
+        family(
+            "qubit_subscription_items",
+            "histogram",
+            "Number of items emitted by each subscription.",
+            &|out| {
+                for (method, metrics) in &methods {
+                    histogram(
+                        out,
+                        "qubit_subscription_items",
+                        method,
+                        &metrics.subscription_items,
+                    );
+                }
+            },
+        );
+
+        family(
+            "qubit_failed_sends_total",
+            "counter",
+            "Number of subscription items that couldn't be sent.",
+            &|out| {
+                for (method, metrics) in &methods {
+                    sample(
+                        out,
+                        "qubit_failed_sends_total",
+                        &[("method", method)],
+                        metrics.failed_sends,
+                    );
+                }
+            },
+        );
+
+        out
+    }
+
+    /// Produce a [`Service`] which responds to every request with the metrics in the Prometheus
+    /// text format, suitable for mounting next to the RPC service.
+    pub fn service(
+        &self,
+    ) -> impl Service<
+        Request<Body>,
+        Response = Response,
+        Error = Infallible,
+        Future = Ready<Result<Response, Infallible>>,
+    > + Clone
+    + use<> {
+        let metrics = self.clone();
+
+        service_fn(move |_req: Request<Body>| {
+            ready(Ok((
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                metrics.render_prometheus(),
+            )
+                .into_response()))
+        })
+    }
+
+    /// Update the metrics of a method.
+    fn update(&self, method: &str, f: impl FnOnce(&mut MethodMetrics)) {
+        let mut methods = self.methods.lock().expect("metrics not poisoned");
+
+        match methods.get_mut(method) {
+            Some(metrics) => f(metrics),
+            None => f(methods.entry(method.to_string()).or_default()),
+        }
+    }
+}
+
+impl Metrics for InMemoryMetrics {
+    fn call_completed(&self, method: &str, duration: Duration, result: Result<(), ErrorCode>) {
+        self.update(method, |metrics| {
+            metrics.calls += 1;
+            metrics.latency.observe(duration.as_secs_f64());
+
+            if let Err(code) = result {
+                *metrics.errors.entry(code.code()).or_default() += 1;
+            }
+        });
+    }
+
+    fn subscription_started(&self, method: &str) {
+        self.update(method, |metrics| metrics.active_subscriptions += 1);
+    }
+
+    fn subscription_completed(&self, method: &str, items: usize, _duration: Duration) {
+        self.update(method, |metrics| {
+            metrics.active_subscriptions = metrics.active_subscriptions.saturating_sub(1);
+            metrics.subscription_items.observe(items as f64);
+        });
+    }
+
+    fn send_failed(&self, method: &str) {
+        self.update(method, |metrics| metrics.failed_sends += 1);
+    }
+}
+
+/// Write a single Prometheus sample.
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+    let labels = labels
+        .iter()
+        .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    writeln!(out, "{name}{{{labels}}} {value}").unwrap();
+}
+
+/// Write the samples for a histogram of a method.
+fn histogram(out: &mut String, name: &str, method: &str, histogram: &Histogram) {
+    for (bound, count) in histogram.buckets() {
+        let bound = bound.to_string();
+        sample(
+            out,
+            &format!("{name}_bucket"),
+            &[("method", method), ("le", &bound)],
+            count,
+        );
+    }
+
+    sample(
+        out,
+        &format!("{name}_bucket"),
+        &[("method", method), ("le", "+Inf")],
+        histogram.count(),
+    );
+    sample(
+        out,
+        &format!("{name}_sum"),
+        &[("method", method)],
+        histogram.sum(),
+    );
+    sample(
+        out,
+        &format!("{name}_count"),
+        &[("method", method)],
+        histogram.count(),
+    );
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn histogram() {
+        let mut histogram = Histogram::new(&[1.0, 5.0, 10.0]);
+        histogram.observe(0.5);
+        histogram.observe(5.0);
+        histogram.observe(20.0);
+
+        assert_eq!(
+            histogram.buckets().collect::<Vec<_>>(),
+            [(1.0, 1), (5.0, 2), (10.0, 2)]
+        );
+        assert_eq!(histogram.count(), 3);
+        assert_eq!(histogram.sum(), 25.5);
+    }
+
+    #[test]
+    fn in_memory() {
+        let metrics = InMemoryMetrics::new();
+        metrics.call_completed("a", Duration::from_millis(2), Ok(()));
+        metrics.call_completed("a", Duration::from_millis(2), Err(ErrorCode::InvalidParams));
+        metrics.call_completed("b", Duration::from_millis(2), Ok(()));
+        metrics.subscription_started("b");
+        metrics.send_failed("b");
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot["a"].calls, 2);
+        assert_eq!(snapshot["a"].errors, BTreeMap::from([(-32602, 1)]));
+        assert_eq!(snapshot["a"].latency.count(), 2);
+        assert_eq!(snapshot["b"].active_subscriptions, 1);
+        assert_eq!(snapshot["b"].failed_sends, 1);
+
+        metrics.subscription_completed("b", 3, Duration::from_secs(1));
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot["b"].active_subscriptions, 0);
+        assert_eq!(snapshot["b"].subscription_items.count(), 1);
+    }
+
+    #[test]
+    fn prometheus() {
+        let metrics = InMemoryMetrics::new();
+        metrics.call_completed("user.get", Duration::from_millis(20), Ok(()));
+        metrics.call_completed(
+            "user.get",
+            Duration::from_millis(20),
+            Err(ErrorCode::ServerError(-32001)),
+        );
+
+        let output = metrics.render_prometheus();
+        assert!(output.contains("# TYPE qubit_calls_total counter\n"));
+        assert!(output.contains("qubit_calls_total{method=\"user.get\"} 2\n"));
+        assert!(output.contains("qubit_errors_total{method=\"user.get\",code=\"-32001\"} 1\n"));
+        assert!(
+            output.contains(
+                "qubit_call_duration_seconds_bucket{method=\"user.get\",le=\"0.01\"} 0\n"
+            )
+        );
+        assert!(
+            output.contains(
+                "qubit_call_duration_seconds_bucket{method=\"user.get\",le=\"0.025\"} 2\n"
+            )
+        );
+        assert!(
+            output.contains(
+                "qubit_call_duration_seconds_bucket{method=\"user.get\",le=\"+Inf\"} 2\n"
+            )
+        );
+    }
+
+    #[test]
+    fn escape_label() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -1,6 +1,52 @@
 //! Anything required by the macro.
 
-pub use ::linkme;
+use std::sync::Arc;
+
 pub use ::ts_rs;
 
-pub use crate::reflection::handler::{HANDLER_DEFINITIONS, HandlerKind, HandlerMeta};
+use crate::{
+    FromRequestExtensions, RegisterableHandler, Router,
+    handler::marker::{ResponseMarker, ResponseReturnMarker, StreamReturnMarker},
+};
+
+pub use crate::{
+    concurrency::ConcurrencyLimit,
+    handler::RegisterOptions,
+    rate_limit::RateLimit,
+    reflection::handler::{HandlerKind, HandlerMeta, HandlerMetadata},
+    router::RouterHandler,
+};
+
+/// Insert a query or mutation generated by the macro into the router, ensuring that the handler
+/// returns a single value.
+pub fn insert_response_handler<Ctx, F, MSig, MValue, MReturn>(
+    router: Router<Ctx>,
+    handler: F,
+    meta: &'static HandlerMeta,
+) -> Router<Ctx>
+where
+    Ctx: 'static + Clone + Send + Sync,
+    F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
+    F::Ctx: FromRequestExtensions<Ctx>,
+    MValue: ResponseMarker,
+    MReturn: ResponseReturnMarker,
+{
+    router.insert_handler(handler, Arc::new(meta.clone()))
+}
+
+/// Insert a subscription generated by the macro into the router, ensuring that the handler
+/// returns a stream.
+pub fn insert_stream_handler<Ctx, F, MSig, MValue, MReturn>(
+    router: Router<Ctx>,
+    handler: F,
+    meta: &'static HandlerMeta,
+) -> Router<Ctx>
+where
+    Ctx: 'static + Clone + Send + Sync,
+    F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
+    F::Ctx: FromRequestExtensions<Ctx>,
+    MValue: ResponseMarker,
+    MReturn: StreamReturnMarker,
+{
+    router.insert_handler(handler, Arc::new(meta.clone()))
+}
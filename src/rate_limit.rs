@@ -0,0 +1,421 @@
+//! Rate limiting of handlers, declared with the `rate_limit` attribute of the
+//! [`handler`](crate::handler) macro.
+//!
+//! Limits are enforced by the [`RateLimiter`] provided to [`RpcModule::with_rate_limiter`], which
+//! is required to serve a module with any rate limited handlers (calls made with a
+//! [`TestClient`](crate::TestClient) are not limited without one). Each client receives its own
+//! budget for each handler, where clients are identified by their IP address by default (see
+//! [`RateLimiter::key_by`]).
+//!
+//! The IP address is only available if the server was started with
+//! [`into_make_service_with_connect_info`]. Without it, calls to rate limited handlers will fail
+//! with an internal error rather than sharing a single budget between every client. Behind a proxy
+//! (where every request shares the proxy's address), or when connect info isn't available,
+//! [`RateLimiter::key_by`] should be used to identify clients another way.
+//!
+//! ```
+//! use qubit::{Router, handler, rate_limit::RateLimiter};
+//!
+//! #[handler(mutation, rate_limit = "10/min")]
+//! async fn send_email(ctx: (), address: String) {}
+//!
+//! let rpc = Router::new()
+//!     .handler(send_email)
+//!     .as_rpc(())
+//!     .with_rate_limiter(RateLimiter::in_memory());
+//! ```
+//!
+//! [`RpcModule::with_rate_limiter`]: crate::RpcModule::with_rate_limiter
+//! [`into_make_service_with_connect_info`]: axum::Router::into_make_service_with_connect_info
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::{
+    FutureExt,
+    future::{BoxFuture, ready},
+};
+use http::{Extensions, HeaderValue, StatusCode, header};
+
+use crate::{ErrorCode, FromRequestExtensions, RequestInfo, ResponseParts, RpcError};
+
+/// Maximum number of requests permitted within a period.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Number of requests allowed in each period.
+    pub requests: u32,
+    /// Period over which requests are counted.
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// Create a new limit of `requests` per `period`.
+    pub const fn new(requests: u32, period: Duration) -> Self {
+        Self { requests, period }
+    }
+}
+
+/// Storage of the remaining budget for each client. The default implementation is
+/// [`InMemoryStore`], but this may be implemented to share limits between multiple servers.
+pub trait RateLimitStore: 'static + Send + Sync {
+    /// Attempt to take a single request from the budget identified by `key`. If the budget is
+    /// exhausted, the duration until another request will be permitted should be returned.
+    fn acquire<'a>(&'a self, key: &'a str, limit: RateLimit)
+    -> BoxFuture<'a, Result<(), Duration>>;
+}
+
+/// Minimum number of buckets before any are evicted.
+const MIN_EVICTION_THRESHOLD: usize = 1024;
+
+/// Token bucket for each key, stored in memory. Buckets start full, and refill continuously at a
+/// rate of `requests / period`.
+///
+/// Buckets which have refilled are equivalent to a new bucket, so are periodically evicted to
+/// prevent the store from growing with every client that has ever made a request.
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    buckets: Mutex<Buckets>,
+}
+
+/// Buckets for every key, along with the size at which they will next be swept.
+#[derive(Debug, Default)]
+struct Buckets {
+    buckets: HashMap<String, Bucket>,
+    eviction_threshold: usize,
+}
+
+/// Remaining tokens for a single key.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Instant at which the bucket will have refilled.
+    full_at: Instant,
+}
+
+impl InMemoryStore {
+    /// Create a new, empty, store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a token from the bucket for `key` at the provided instant.
+    fn acquire_at(&self, key: &str, limit: RateLimit, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(limit.requests);
+        let rate = capacity / limit.period.as_secs_f64();
+
+        let mut buckets = self.buckets.lock().expect("rate limit store not poisoned");
+        buckets.evict(now);
+
+        let bucket = buckets.buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            full_at: now,
+        });
+
+        // Refill any tokens accumulated since the last request.
+        bucket.tokens = (bucket.tokens
+            + now.saturating_duration_since(bucket.updated).as_secs_f64() * rate)
+            .min(capacity);
+        bucket.updated = now;
+
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        };
+
+        bucket.full_at = now + Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+
+        result
+    }
+
+    /// Number of buckets currently stored.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.buckets
+            .lock()
+            .expect("rate limit store not poisoned")
+            .buckets
+            .len()
+    }
+}
+
+impl Buckets {
+    /// Remove every bucket that has refilled, once the number of buckets has doubled since the
+    /// last sweep. This keeps the cost of sweeping proportional to the number of insertions.
+    fn evict(&mut self, now: Instant) {
+        if self.buckets.len() < self.eviction_threshold.max(MIN_EVICTION_THRESHOLD) {
+            return;
+        }
+
+        self.buckets.retain(|_, bucket| bucket.full_at > now);
+        self.eviction_threshold = self.buckets.len() * 2;
+    }
+}
+
+impl RateLimitStore for InMemoryStore {
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        limit: RateLimit,
+    ) -> BoxFuture<'a, Result<(), Duration>> {
+        ready(self.acquire_at(key, limit, Instant::now())).boxed()
+    }
+}
+
+/// Produce the key identifying the client that made a request.
+type KeyFn<Ctx> =
+    Arc<dyn Fn(Ctx, Extensions) -> BoxFuture<'static, Result<String, RpcError>> + Send + Sync>;
+
+/// Enforces the rate limits of handlers, using a [`RateLimitStore`].
+pub struct RateLimiter<Ctx> {
+    store: Arc<dyn RateLimitStore>,
+    key: KeyFn<Ctx>,
+}
+
+impl<Ctx> Clone for RateLimiter<Ctx> {
+    fn clone(&self) -> Self {
+        Self {
+            store: Arc::clone(&self.store),
+            key: Arc::clone(&self.key),
+        }
+    }
+}
+
+impl<Ctx> RateLimiter<Ctx>
+where
+    Ctx: 'static + Clone + Send,
+{
+    /// Create a rate limiter backed by the provided store. Clients will be identified by the IP
+    /// address in the [`RequestInfo`]. If the address isn't available, calls to rate limited
+    /// handlers will be rejected with an internal error (see the [module documentation](self)).
+    pub fn new(store: impl RateLimitStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            key: Arc::new(|_ctx, extensions| {
+                ready(
+                    extensions
+                        .get::<RequestInfo>()
+                        .and_then(RequestInfo::remote_addr)
+                        .map(|addr| addr.ip().to_string())
+                        .ok_or_else(|| RpcError {
+                            code: ErrorCode::InternalError,
+                            message: "client address unavailable for rate limiting, start the \
+                                server with `into_make_service_with_connect_info` or use \
+                                `RateLimiter::key_by`"
+                                .to_string(),
+                            data: None,
+                        }),
+                )
+                .boxed()
+            }),
+        }
+    }
+
+    /// Create a rate limiter backed by an [`InMemoryStore`].
+    pub fn in_memory() -> Self {
+        Self::new(InMemoryStore::new())
+    }
+
+    /// Identify clients using a value extracted from the request, such as an authenticated user.
+    /// Any error produced whilst extracting the value will reject the request.
+    ///
+    /// ```
+    /// # use qubit::{RpcError, auth::{Authenticated, Authenticator, BearerToken}, rate_limit::RateLimiter};
+    /// struct User {
+    ///     id: u32,
+    /// }
+    ///
+    /// impl Authenticator<()> for User {
+    ///     type Credentials = BearerToken;
+    ///
+    ///     async fn authenticate(_ctx: (), token: BearerToken) -> Result<Self, RpcError> {
+    ///         Ok(User { id: 1 })
+    ///     }
+    /// }
+    ///
+    /// let rate_limiter = RateLimiter::<()>::in_memory()
+    ///     .key_by(|user: Authenticated<User>| user.id.to_string());
+    /// ```
+    pub fn key_by<T>(mut self, key: impl Fn(T) -> String + 'static + Send + Sync) -> Self
+    where
+        T: 'static + FromRequestExtensions<Ctx>,
+    {
+        let key = Arc::new(key);
+
+        self.key = Arc::new(move |ctx, extensions| {
+            let key = Arc::clone(&key);
+            async move { T::from_request_extensions(ctx, extensions).await.map(&*key) }.boxed()
+        });
+        self
+    }
+
+    /// Take a request from the budget of the client for `method`, rejecting the request if the
+    /// limit has been exceeded. If the call was made over HTTP, the response will also be given a
+    /// `429 Too Many Requests` status.
+    pub(crate) async fn check(
+        &self,
+        method: &str,
+        limit: RateLimit,
+        ctx: Ctx,
+        extensions: &Extensions,
+    ) -> Result<(), RpcError> {
+        let client = (self.key)(ctx, extensions.clone()).await?;
+        let key = format!("{method}:{client}");
+
+        let Err(retry_after) = self.store.acquire(&key, limit).await else {
+            return Ok(());
+        };
+
+        // Round up, so that retrying after the duration will succeed.
+        let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+        ResponseParts::from_extensions(extensions)
+            .set_status(StatusCode::TOO_MANY_REQUESTS)
+            .insert_header(header::RETRY_AFTER, HeaderValue::from(retry_after));
+
+        Err(RpcError::rate_limited(retry_after))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+
+    use axum::extract::ConnectInfo;
+    use http::Request;
+
+    use crate::Transport;
+
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit::new(2, Duration::from_secs(10));
+
+    #[test]
+    fn token_bucket() {
+        let store = InMemoryStore::new();
+        let start = Instant::now();
+
+        assert!(store.acquire_at("a", LIMIT, start).is_ok());
+        assert!(store.acquire_at("a", LIMIT, start).is_ok());
+        assert_eq!(
+            store.acquire_at("a", LIMIT, start),
+            Err(Duration::from_secs(5))
+        );
+
+        // Other keys have their own budget.
+        assert!(store.acquire_at("b", LIMIT, start).is_ok());
+
+        // Tokens are refilled over time.
+        assert!(
+            store
+                .acquire_at("a", LIMIT, start + Duration::from_secs(5))
+                .is_ok()
+        );
+        assert!(
+            store
+                .acquire_at("a", LIMIT, start + Duration::from_secs(5))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn evict_refilled() {
+        let store = InMemoryStore::new();
+        let start = Instant::now();
+
+        store.acquire_at("a", LIMIT, start).unwrap();
+        store.acquire_at("a", LIMIT, start).unwrap();
+        for i in 1..MIN_EVICTION_THRESHOLD {
+            store.acquire_at(&i.to_string(), LIMIT, start).unwrap();
+        }
+        assert_eq!(store.len(), MIN_EVICTION_THRESHOLD);
+
+        // Every bucket with a single request has refilled, but `a` has not.
+        let later = start + Duration::from_secs(6);
+        store.acquire_at("b", LIMIT, later).unwrap();
+        assert_eq!(store.len(), 2);
+
+        // The partially refilled bucket wasn't reset by the sweep.
+        assert!(store.acquire_at("a", LIMIT, later).is_ok());
+        assert_eq!(
+            store.acquire_at("a", LIMIT, later),
+            Err(Duration::from_secs(4))
+        );
+    }
+
+    /// Extensions of a request made from `addr`.
+    fn from_addr(addr: &str) -> Extensions {
+        let req = Request::builder()
+            .extension(ConnectInfo(addr.parse::<SocketAddr>().unwrap()))
+            .body(())
+            .unwrap();
+
+        let mut extensions = Extensions::new();
+        extensions.insert(RequestInfo::from_request(&req, Transport::Http));
+        extensions
+    }
+
+    #[tokio::test]
+    async fn check() {
+        let rate_limiter = RateLimiter::<()>::in_memory();
+        let limit = RateLimit::new(1, Duration::from_secs(60));
+        let extensions = from_addr("127.0.0.1:1000");
+
+        assert!(
+            rate_limiter
+                .check("a", limit, (), &extensions)
+                .await
+                .is_ok()
+        );
+
+        let err = rate_limiter
+            .check("a", limit, (), &extensions)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, RpcError::RATE_LIMITED);
+        assert_eq!(err.data.unwrap()["retry_after"], 60);
+
+        // Limits are tracked per method.
+        assert!(
+            rate_limiter
+                .check("b", limit, (), &extensions)
+                .await
+                .is_ok()
+        );
+
+        // Limits are tracked per IP address, regardless of port.
+        assert!(
+            rate_limiter
+                .check("a", limit, (), &from_addr("127.0.0.1:2000"))
+                .await
+                .is_err()
+        );
+        assert!(
+            rate_limiter
+                .check("a", limit, (), &from_addr("127.0.0.2:1000"))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_addr() {
+        let rate_limiter = RateLimiter::<()>::in_memory();
+        let limit = RateLimit::new(1, Duration::from_secs(60));
+
+        // Clients without an address aren't given a shared budget.
+        for _ in 0..2 {
+            let err = rate_limiter
+                .check("a", limit, (), &Extensions::new())
+                .await
+                .unwrap_err();
+            assert_eq!(err.code, ErrorCode::InternalError);
+        }
+    }
+}
@@ -1,29 +1,14 @@
 //! Anything related to runtime reflection of handlers.
-use std::{any::TypeId, collections::BTreeMap};
+use std::time::Duration;
 
-use lazy_static::lazy_static;
-use linkme::distributed_slice;
+use serde::{Deserialize, Serialize};
 
-use crate::QubitHandler;
-
-/// The key to runtime handler reflection. In order to 'smuggle' information from the proc-macro
-/// back into the runtime, information needs to be stored in the binary. [`linkme`] is used to
-/// create a slice of function pointers, which can be added at compile time as the
-/// [`crate::handler`] proc-macro is expanded.
-#[distributed_slice]
-pub static HANDLER_DEFINITIONS: [fn() -> (TypeId, HandlerMeta)];
-
-lazy_static! {
-    /// Runtime utility to easily lookup [`HandlerMeta`] for a [`TypeId`].
-    static ref HANDLER_DEFINITIONS_MAP: BTreeMap<TypeId, HandlerMeta> = HANDLER_DEFINITIONS
-        .into_iter()
-        .map(|def_fn| def_fn())
-        .collect();
-}
+use crate::{concurrency::ConcurrencyLimit, rate_limit::RateLimit};
 
 /// Kind of the handler. This will correspond with the method the user must call from
 /// TypeScript.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum HandlerKind {
     Query,
     Mutation,
@@ -41,15 +26,42 @@ pub struct HandlerMeta {
     pub name: &'static str,
     /// Name of the parameters for this handler.
     pub param_names: &'static [&'static str],
+    /// Number of trailing parameters which may be omitted by the client.
+    pub optional_params: usize,
+    /// Maximum rate at which each client may call this handler.
+    pub rate_limit: Option<RateLimit>,
+    /// Maximum duration that each call to this handler may run for.
+    pub timeout: Option<Duration>,
+    /// Maximum number of calls to this handler that may run at once.
+    pub max_concurrency: Option<ConcurrencyLimit>,
+    /// Whether calls to this handler run on the blocking thread pool.
+    pub blocking: bool,
 }
 
 impl HandlerMeta {
-    /// Lookup the [`HandlerMeta`] of a handler. If the handler is not found (such as if it wasn't
-    /// registered with [`crate::handler`], then this method will panic).
-    pub(crate) fn of<F, Ctx, MSig>(#[allow(unused)] handler: &F) -> &'static Self
-    where
-        F: QubitHandler<Ctx, MSig>,
-    {
-        HANDLER_DEFINITIONS_MAP.get(&TypeId::of::<F>()).unwrap()
+    /// Create metadata for a handler without any optional parameters, limits, or timeout.
+    pub const fn new(
+        kind: HandlerKind,
+        name: &'static str,
+        param_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            kind,
+            name,
+            param_names,
+            optional_params: 0,
+            rate_limit: None,
+            timeout: None,
+            max_concurrency: None,
+            blocking: false,
+        }
     }
 }
+
+/// Handler with [`HandlerMeta`] known at compile time. The [`handler`](crate::handler) macro
+/// replaces the annotated function with a zero-sized type of the same name, which implements this
+/// trait.
+pub trait HandlerMetadata: 'static + Send + Sync {
+    /// Metadata of the handler.
+    fn meta() -> &'static HandlerMeta;
+}
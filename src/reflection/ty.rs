@@ -2,7 +2,7 @@
 
 use std::fmt::Display;
 
-use ts_rs::{Config, TS};
+use ts_rs::{Config, TS, TypeVisitor};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CodegenType {
@@ -31,6 +31,26 @@ impl CodegenType {
         Self::from_name_and_generics(T::name(&Config::default()))
     }
 
+    /// Type within `T` if it is an [`Option`], otherwise [`None`].
+    pub fn from_option_inner<T: TS + 'static + ?Sized>() -> Option<Self> {
+        /// Captures the last type visited, which for an `Option` is the type within it.
+        struct LastVisited(Option<CodegenType>);
+
+        impl TypeVisitor for LastVisited {
+            fn visit<T: TS + 'static + ?Sized>(&mut self) {
+                self.0 = Some(CodegenType::from_type::<T>());
+            }
+        }
+
+        if !T::IS_OPTION {
+            return None;
+        }
+
+        let mut visitor = LastVisited(None);
+        T::visit_generics(&mut visitor);
+        visitor.0
+    }
+
     fn from_name_and_generics(s: impl AsRef<str>) -> Self {
         let (name, generics) = match s.as_ref().split_once('<') {
             Some((name, generics)) => (
@@ -0,0 +1,144 @@
+//! Results of calls made in a binary encoding. The server only produces JSON, where
+//! [`Bytes`](crate::Bytes) can only be a base64 string, so results are instead serialised directly
+//! into CBOR (where [`Bytes`](crate::Bytes) are raw binary), and travel through the server as an
+//! object which carries the encoded result.
+//!
+//! Every result that a handler produces for such a call is an object of this form, whilst results
+//! produced by the server itself (such as subscription IDs) never are. Results can therefore be
+//! restored as the response is encoded, without inspecting any content produced by a handler.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ciborium::Value as Cbor;
+use http::Extensions;
+use serde::{Serialize, Serializer, ser::Error, ser::SerializeMap};
+use serde_json::Value;
+
+/// Key of the object which carries an encoded result.
+const ENCODED_KEY: &str = "cbor";
+
+/// Marker that responses to calls made with a request or over a connection will be sent in a
+/// binary encoding.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BinaryResponse;
+
+impl BinaryResponse {
+    /// Whether the response to the call made with `extensions` will use a binary encoding.
+    pub(crate) fn is_requested(extensions: &Extensions) -> bool {
+        extensions.get::<Self>().is_some()
+    }
+}
+
+/// A value produced by a handler, which is serialised directly into CBOR if the response will use
+/// a binary encoding.
+#[derive(Clone)]
+pub(crate) struct ResultValue<T> {
+    value: T,
+    binary: bool,
+}
+
+impl<T> ResultValue<T> {
+    pub(crate) fn new(value: T, binary: bool) -> Self {
+        Self { value, binary }
+    }
+}
+
+impl<T: Serialize> Serialize for ResultValue<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.binary {
+            return self.value.serialize(serializer);
+        }
+
+        let mut encoded = Vec::new();
+        ciborium::into_writer(&self.value, &mut encoded).map_err(S::Error::custom)?;
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(ENCODED_KEY, &STANDARD.encode(encoded))?;
+        map.end()
+    }
+}
+
+/// Restore a result which was serialised by [`ResultValue`] for a binary encoding. Any other
+/// result (such as a subscription ID) will produce [`None`].
+pub(crate) fn decode_result(result: &Value) -> Option<Cbor> {
+    let encoded = STANDARD
+        .decode(result.as_object()?.get(ENCODED_KEY)?.as_str()?)
+        .ok()?;
+
+    ciborium::from_reader(encoded.as_slice()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::Bytes;
+
+    use super::*;
+
+    #[derive(Clone, Serialize)]
+    enum Variant {
+        Newtype(Bytes),
+        Tuple(u8, Bytes),
+        Struct { bytes: Bytes },
+    }
+
+    fn restore(value: impl Serialize) -> Option<Cbor> {
+        decode_result(&serde_json::to_value(ResultValue::new(value, true)).unwrap())
+    }
+
+    #[rstest]
+    #[case::bytes(Bytes::from([1, 2]), Cbor::Bytes(vec![1, 2]))]
+    #[case::nested(
+        HashMap::from([("a", vec![Bytes::from([1])])]),
+        Cbor::Map(vec![(
+            Cbor::Text("a".into()),
+            Cbor::Array(vec![Cbor::Bytes(vec![1])]),
+        )]),
+    )]
+    #[case::newtype_variant(
+        Variant::Newtype(Bytes::from([1])),
+        Cbor::Map(vec![(Cbor::Text("Newtype".into()), Cbor::Bytes(vec![1]))]),
+    )]
+    #[case::tuple_variant(
+        Variant::Tuple(1, Bytes::from([1])),
+        Cbor::Map(vec![(
+            Cbor::Text("Tuple".into()),
+            Cbor::Array(vec![Cbor::Integer(1.into()), Cbor::Bytes(vec![1])]),
+        )]),
+    )]
+    #[case::struct_variant(
+        Variant::Struct { bytes: Bytes::from([1]) },
+        Cbor::Map(vec![(
+            Cbor::Text("Struct".into()),
+            Cbor::Map(vec![(Cbor::Text("bytes".into()), Cbor::Bytes(vec![1]))]),
+        )]),
+    )]
+    #[case::base64_string("AP8Q", Cbor::Text("AP8Q".into()))]
+    #[case::encoded_object(
+        json!({ "cbor": "9g==" }),
+        Cbor::Map(vec![(Cbor::Text("cbor".into()), Cbor::Text("9g==".into()))]),
+    )]
+    fn restored(#[case] value: impl Serialize, #[case] expected: Cbor) {
+        assert_eq!(restore(value), Some(expected));
+    }
+
+    #[test]
+    fn not_binary() {
+        assert_eq!(
+            serde_json::to_value(ResultValue::new(Bytes::from([0, 255, 16]), false)).unwrap(),
+            json!("AP8Q")
+        );
+    }
+
+    #[rstest]
+    #[case::subscription_id(json!(1))]
+    #[case::unsubscribed(json!(true))]
+    #[case::other_object(json!({ "a": 1 }))]
+    #[case::invalid(json!({ "cbor": "not base64" }))]
+    fn not_restored(#[case] result: Value) {
+        assert_eq!(decode_result(&result), None);
+    }
+}
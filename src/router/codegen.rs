@@ -1,13 +1,14 @@
 use std::{
     fs::{File, OpenOptions},
     path::Path,
+    sync::Arc,
 };
 
 use ts_rs::TypeVisitor;
 
 use crate::{
     FromRequestExtensions, RegisterableHandler,
-    codegen::{Backend, Codegen, DependentTypes, HandlerCodegen},
+    codegen::{ApiDiff, ApiSnapshot, Backend, Codegen, DependentTypes, HandlerCodegen},
     handler::{marker, response::ResponseValue, ts::TsTypeTuple},
     reflection::handler::HandlerMeta,
     router::{RouterModule, RouterModuleHandler},
@@ -47,6 +48,27 @@ impl CodegenModule {
             .open(output_path)?;
         self.0.generate(&mut file, backend)
     }
+
+    /// Capture a serialisable [`ApiSnapshot`] of this router, which can be compared against
+    /// future versions to detect breaking changes.
+    pub fn snapshot(&self) -> ApiSnapshot {
+        self.0.snapshot()
+    }
+
+    /// Capture a snapshot of this router, and write it to the provided path.
+    ///
+    /// If a file at the path doesn't exist it will be created. If it does exist, it will be
+    /// overwritten. If the directory doesn't exist, an error will be returned.
+    pub fn write_snapshot(&self, output_path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.snapshot().write(output_path)
+    }
+
+    /// Compare this router against a previously written snapshot at the provided path. The
+    /// resulting [`ApiDiff`] can be used to determine whether this router would break clients
+    /// generated from the snapshot.
+    pub fn check_snapshot(&self, snapshot_path: impl AsRef<Path>) -> std::io::Result<ApiDiff> {
+        Ok(ApiSnapshot::read(snapshot_path)?.diff(&self.snapshot()))
+    }
 }
 
 impl Default for CodegenModule {
@@ -67,26 +89,27 @@ impl<Ctx> RouterModule<Ctx> for CodegenModule {
 }
 
 /// All information required to generate the handler type at runtime.
+#[derive(Clone)]
 pub struct HandlerRegister {
     /// Reflected information about the handler.
     handler: HandlerCodegen,
     /// Callback to register dependent types for this handler into the provided [`DependentTypes`]
     /// instance.
-    visit_dependent_types: Box<dyn Fn(&mut DependentTypes)>,
+    visit_dependent_types: Arc<dyn Fn(&mut DependentTypes) + Send + Sync>,
 }
 
 impl<Ctx> RouterModuleHandler<Ctx> for HandlerRegister {
     fn from_handler<F, MSig, MValue: marker::ResponseMarker, MReturn: marker::HandlerReturnMarker>(
         handler: F,
-        meta: &'static HandlerMeta,
+        meta: Arc<HandlerMeta>,
     ) -> Self
     where
         F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
         F::Ctx: FromRequestExtensions<Ctx>,
     {
         Self {
-            handler: HandlerCodegen::from_handler(meta, &handler),
-            visit_dependent_types: Box::new(move |dependent_types: &mut DependentTypes| {
+            handler: HandlerCodegen::from_handler(&meta, &handler),
+            visit_dependent_types: Arc::new(move |dependent_types: &mut DependentTypes| {
                 // Add all parameter types into the dependent types.
                 <F::Params as TsTypeTuple>::visit_tys(dependent_types);
                 // Add the return type into the dependent types.
@@ -0,0 +1,226 @@
+//! Protection against cross-site request forgery (CSRF).
+
+use http::{HeaderMap, HeaderName, Uri, header};
+
+/// Configuration for CSRF protection, enabled with [`RpcModule::with_csrf`].
+///
+/// Browsers will attach cookies to requests made from any site, so handlers that rely on cookies
+/// for authentication can be triggered by a malicious site. Mutations made over HTTP can be
+/// required to include a token (which a cross-site request can't produce), and WebSocket upgrades
+/// can be restricted to a set of allowed origins.
+///
+/// ```
+/// # use qubit::Csrf;
+/// # use http::HeaderName;
+/// let csrf = Csrf::new()
+///     .double_submit_cookie("csrf-token", HeaderName::from_static("x-csrf-token"))
+///     .allow_origin("https://example.com");
+/// ```
+///
+/// [`RpcModule::with_csrf`]: crate::RpcModule::with_csrf
+#[derive(Clone, Debug, Default)]
+pub struct Csrf {
+    /// Token required for mutations.
+    token: Option<CsrfToken>,
+    /// Origins that may upgrade to a WebSocket. If empty, any origin is allowed.
+    allowed_origins: Vec<String>,
+}
+
+/// Method used to verify that a mutation was made by a trusted client.
+#[derive(Clone, Debug)]
+enum CsrfToken {
+    /// Request must include the header. Browsers won't include custom headers on cross-site
+    /// requests without a successful CORS preflight.
+    Header(HeaderName),
+    /// Request must include the header, with the same value as the cookie. Cross-site requests
+    /// can't read the cookie, so can't produce the header.
+    DoubleSubmitCookie { cookie: String, header: HeaderName },
+}
+
+impl Csrf {
+    /// Create a new configuration, which doesn't perform any checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require the header to be present on any request containing a mutation. The value of the
+    /// header is not checked.
+    pub fn require_header(mut self, header: HeaderName) -> Self {
+        self.token = Some(CsrfToken::Header(header));
+        self
+    }
+
+    /// Require the header to be present on any request containing a mutation, and to match the
+    /// value of the cookie.
+    pub fn double_submit_cookie(mut self, cookie: impl ToString, header: HeaderName) -> Self {
+        self.token = Some(CsrfToken::DoubleSubmitCookie {
+            cookie: cookie.to_string(),
+            header,
+        });
+        self
+    }
+
+    /// Allow WebSocket upgrades from the origin (such as `https://example.com`). Once an origin is
+    /// allowed, upgrades from any other origin will be rejected.
+    pub fn allow_origin(mut self, origin: impl ToString) -> Self {
+        self.allowed_origins
+            .push(origin.to_string().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Whether mutations must include a token.
+    pub(crate) fn requires_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Check that the headers of a request containing a mutation include a valid token.
+    pub(crate) fn check_token(&self, headers: &HeaderMap) -> Result<(), &'static str> {
+        match &self.token {
+            None => Ok(()),
+            Some(CsrfToken::Header(header)) => headers
+                .contains_key(header)
+                .then_some(())
+                .ok_or("missing CSRF header"),
+            Some(CsrfToken::DoubleSubmitCookie { cookie, header }) => {
+                let token = headers
+                    .get(header)
+                    .and_then(|token| token.to_str().ok())
+                    .ok_or("missing CSRF header")?;
+
+                let cookie = headers
+                    .get_all(header::COOKIE)
+                    .iter()
+                    .filter_map(|cookies| cookies.to_str().ok())
+                    .flat_map(|cookies| cookies.split(';'))
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(name, _)| name == cookie)
+                    .map(|(_, value)| value)
+                    .ok_or("missing CSRF cookie")?;
+
+                (!token.is_empty() && constant_time_eq(token.as_bytes(), cookie.as_bytes()))
+                    .then_some(())
+                    .ok_or("CSRF header does not match cookie")
+            }
+        }
+    }
+
+    /// Check that the headers of a WebSocket upgrade originate from an allowed origin. The
+    /// `Origin` header will be used if present, otherwise the origin of the `Referer`.
+    pub(crate) fn check_origin(&self, headers: &HeaderMap) -> Result<(), &'static str> {
+        if self.allowed_origins.is_empty() {
+            return Ok(());
+        }
+
+        let origin = headers
+            .get(header::ORIGIN)
+            .and_then(|origin| origin.to_str().ok())
+            .map(|origin| origin.to_string())
+            .or_else(|| {
+                let referer = headers
+                    .get(header::REFERER)?
+                    .to_str()
+                    .ok()?
+                    .parse::<Uri>()
+                    .ok()?;
+
+                Some(format!("{}://{}", referer.scheme()?, referer.authority()?))
+            })
+            .ok_or("missing origin")?;
+
+        self.allowed_origins
+            .contains(&origin)
+            .then_some(())
+            .ok_or("origin not allowed")
+    }
+}
+
+/// Compare two byte strings, without exiting early on the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use http::HeaderValue;
+    use rstest::rstest;
+
+    use super::*;
+
+    fn headers(headers: &[(&'static str, &'static str)]) -> HeaderMap {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case::no_token(Csrf::new(), &[], true)]
+    #[case::header_present(
+        Csrf::new().require_header(HeaderName::from_static("x-csrf")),
+        &[("x-csrf", "1")],
+        true
+    )]
+    #[case::header_missing(
+        Csrf::new().require_header(HeaderName::from_static("x-csrf")),
+        &[],
+        false
+    )]
+    #[case::cookie_matches(
+        Csrf::new().double_submit_cookie("csrf", HeaderName::from_static("x-csrf")),
+        &[("x-csrf", "abc"), ("cookie", "session=123; csrf=abc")],
+        true
+    )]
+    #[case::cookie_mismatch(
+        Csrf::new().double_submit_cookie("csrf", HeaderName::from_static("x-csrf")),
+        &[("x-csrf", "abc"), ("cookie", "csrf=abd")],
+        false
+    )]
+    #[case::cookie_missing(
+        Csrf::new().double_submit_cookie("csrf", HeaderName::from_static("x-csrf")),
+        &[("x-csrf", "abc")],
+        false
+    )]
+    #[case::cookie_header_missing(
+        Csrf::new().double_submit_cookie("csrf", HeaderName::from_static("x-csrf")),
+        &[("cookie", "csrf=abc")],
+        false
+    )]
+    fn check_token(
+        #[case] csrf: Csrf,
+        #[case] request_headers: &[(&'static str, &'static str)],
+        #[case] valid: bool,
+    ) {
+        assert_eq!(csrf.check_token(&headers(request_headers)).is_ok(), valid);
+    }
+
+    #[rstest]
+    #[case::no_allow_list(Csrf::new(), &[("origin", "https://evil.com")], true)]
+    #[case::allowed(
+        Csrf::new().allow_origin("https://example.com/"),
+        &[("origin", "https://example.com")],
+        true
+    )]
+    #[case::not_allowed(
+        Csrf::new().allow_origin("https://example.com"),
+        &[("origin", "https://evil.com")],
+        false
+    )]
+    #[case::referer_allowed(
+        Csrf::new().allow_origin("https://example.com"),
+        &[("referer", "https://example.com/some/page")],
+        true
+    )]
+    #[case::missing(Csrf::new().allow_origin("https://example.com"), &[], false)]
+    fn check_origin(
+        #[case] csrf: Csrf,
+        #[case] request_headers: &[(&'static str, &'static str)],
+        #[case] valid: bool,
+    ) {
+        assert_eq!(csrf.check_origin(&headers(request_headers)).is_ok(), valid);
+    }
+}
@@ -0,0 +1,680 @@
+//! Wire encodings that clients may negotiate in place of JSON. The server only understands JSON, so
+//! payloads in any other encoding are transcoded on their way in and out. JSON requests and
+//! WebSockets are passed to the server untouched.
+
+use std::fmt::{self, Display};
+
+use axum::{
+    body::Body,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use futures::{FutureExt, future::BoxFuture};
+use http::{HeaderMap, HeaderValue, Request, StatusCode, header};
+use http_body_util::LengthLimitError;
+use hyper_util::rt::TokioIo;
+use jsonrpsee::types::{ErrorCode, ErrorObjectOwned};
+use serde::Serialize;
+use serde_json::{Value, json};
+use soketto::{
+    connection::{Receiver, Sender},
+    handshake::{Client, ServerResponse, http::Server},
+};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use tower::{BoxError, Service, ServiceExt};
+
+use super::binary::{self, BinaryResponse};
+
+/// Header used to negotiate a subprotocol when upgrading to a WebSocket.
+const SEC_WEBSOCKET_PROTOCOL: &str = "sec-websocket-protocol";
+
+/// Size of the in-memory buffer between a transcoded WebSocket and the server.
+const TRANSCODE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Encoding of a payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    /// JSON, which is used unless another encoding is negotiated.
+    Json,
+    /// [MessagePack](https://msgpack.org).
+    MessagePack,
+    /// [CBOR](https://cbor.io).
+    Cbor,
+}
+
+impl Encoding {
+    /// Encoding of a request body, from its `Content-Type`. Unknown types are treated as JSON, so
+    /// that the server can reject them.
+    pub(crate) fn from_content_type(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_media_type)
+            .unwrap_or(Self::Json)
+    }
+
+    /// Encoding that a response should use, from the supported type with the highest quality in
+    /// the `Accept` header. Types with a quality of zero are never used, and ties are resolved in
+    /// favour of the first type.
+    pub(crate) fn from_accept(headers: &HeaderMap) -> Option<Self> {
+        let mut accepted: Option<(Self, f32)> = None;
+
+        for media_range in headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+        {
+            let Some(encoding) = Self::from_media_type(media_range) else {
+                continue;
+            };
+
+            let quality = quality(media_range);
+            if quality > 0.0 && accepted.is_none_or(|(_, accepted)| quality > accepted) {
+                accepted = Some((encoding, quality));
+            }
+        }
+
+        accepted.map(|(encoding, _)| encoding)
+    }
+
+    /// Encoding requested as a subprotocol when upgrading to a WebSocket, using the first supported
+    /// protocol.
+    pub(crate) fn from_protocols(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(|protocol| match protocol.trim() {
+                "qubit.json" => Some(Self::Json),
+                "qubit.msgpack" => Some(Self::MessagePack),
+                "qubit.cbor" => Some(Self::Cbor),
+                _ => None,
+            })
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        // Ignore any parameters, such as `charset` or `q`.
+        let media_type = media_type.split(';').next()?.trim();
+
+        Some(match media_type.to_ascii_lowercase().as_str() {
+            "application/json" => Self::Json,
+            "application/msgpack" | "application/vnd.msgpack" | "application/x-msgpack" => {
+                Self::MessagePack
+            }
+            "application/cbor" => Self::Cbor,
+            _ => return None,
+        })
+    }
+
+    /// Media type of payloads in this encoding.
+    pub(crate) fn content_type(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            Self::Json => "application/json",
+            Self::MessagePack => "application/msgpack",
+            Self::Cbor => "application/cbor",
+        })
+    }
+
+    /// WebSocket subprotocol which selects this encoding.
+    fn protocol(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            Self::Json => "qubit.json",
+            Self::MessagePack => "qubit.msgpack",
+            Self::Cbor => "qubit.cbor",
+        })
+    }
+
+    /// Confirm the subprotocol for this encoding in the response to a WebSocket upgrade.
+    pub(crate) fn accept_protocol<B>(self, response: &mut http::Response<B>) {
+        response
+            .headers_mut()
+            .insert(SEC_WEBSOCKET_PROTOCOL, self.protocol());
+    }
+
+    /// Decode a payload in this encoding. Binary values are converted into base64 strings, matching
+    /// how [`Bytes`](crate::Bytes) is represented in JSON.
+    pub(crate) fn decode(self, payload: &[u8]) -> Result<Value, DecodeError> {
+        match self {
+            Self::Json => serde_json::from_slice(payload).ok(),
+            Self::MessagePack => rmp_serde::from_slice(payload).ok().and_then(cbor_to_json),
+            Self::Cbor => ciborium::from_reader(payload).ok().and_then(cbor_to_json),
+        }
+        .ok_or(DecodeError::Invalid(self))
+    }
+
+    /// Encode a value with this encoding.
+    pub(crate) fn encode(self, value: &impl Serialize) -> Vec<u8> {
+        match self {
+            Self::Json => serde_json::to_vec(value).expect("values can be serialised"),
+            Self::MessagePack => rmp_serde::to_vec_named(value).expect("values can be serialised"),
+            Self::Cbor => {
+                let mut payload = Vec::new();
+                ciborium::into_writer(value, &mut payload).expect("values can be serialised");
+                payload
+            }
+        }
+    }
+
+    /// Encode a message from the server with this encoding, restoring any results that were
+    /// serialised for a binary encoding.
+    fn encode_message(self, message: Value) -> Vec<u8> {
+        match self {
+            Self::Json => self.encode(&message),
+            Self::MessagePack | Self::Cbor => self.encode(&message_to_cbor(message)),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "JSON",
+            Self::MessagePack => "MessagePack",
+            Self::Cbor => "CBOR",
+        })
+    }
+}
+
+/// Quality of a media range in an `Accept` header, from its `q` parameter. Defaults to `1` if the
+/// parameter is missing or invalid.
+fn quality(media_range: &str) -> f32 {
+    media_range
+        .split(';')
+        .skip(1)
+        .find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("q")
+                .then(|| value.trim().parse().ok())?
+        })
+        .unwrap_or(1.0)
+}
+
+/// Convert a value decoded from a binary encoding into JSON. CBOR's data model is used as the
+/// intermediate as, unlike JSON, it can represent binary data. Fails if a number can't be
+/// represented in JSON.
+fn cbor_to_json(value: ciborium::Value) -> Option<Value> {
+    use ciborium::Value as Cbor;
+
+    Some(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(value) => Value::Bool(value),
+        Cbor::Integer(value) => {
+            let value = i128::from(value);
+            if let Ok(value) = i64::try_from(value) {
+                Value::from(value)
+            } else {
+                Value::from(u64::try_from(value).ok()?)
+            }
+        }
+        Cbor::Float(value) => Value::Number(serde_json::Number::from_f64(value)?),
+        Cbor::Text(value) => Value::String(value),
+        Cbor::Bytes(value) => Value::String(STANDARD.encode(value)),
+        Cbor::Array(values) => values
+            .into_iter()
+            .map(cbor_to_json)
+            .collect::<Option<_>>()
+            .map(Value::Array)?,
+        Cbor::Map(entries) => entries
+            .into_iter()
+            .map(|(key, value)| {
+                let key = match cbor_to_json(key)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+
+                Some((key, cbor_to_json(value)?))
+            })
+            .collect::<Option<_>>()
+            .map(Value::Object)?,
+        Cbor::Tag(_, value) => cbor_to_json(*value)?,
+        _ => return None,
+    })
+}
+
+/// Convert a message from the server (or a batch of them) into CBOR's data model, restoring the
+/// result of any response or subscription notification that was serialised by [`ResultValue`].
+///
+/// [`ResultValue`]: binary::ResultValue
+fn message_to_cbor(message: Value) -> ciborium::Value {
+    use ciborium::Value as Cbor;
+
+    match message {
+        Value::Array(messages) => Cbor::Array(messages.into_iter().map(message_to_cbor).collect()),
+        Value::Object(entries) => Cbor::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match key.as_str() {
+                        "result" => {
+                            binary::decode_result(&value).unwrap_or_else(|| json_to_cbor(value))
+                        }
+                        // Notifications carry their result within their parameters.
+                        "params" => message_to_cbor(value),
+                        _ => json_to_cbor(value),
+                    };
+
+                    (Cbor::Text(key), value)
+                })
+                .collect(),
+        ),
+        message => json_to_cbor(message),
+    }
+}
+
+/// Convert a JSON value into CBOR's data model.
+fn json_to_cbor(value: Value) -> ciborium::Value {
+    use ciborium::Value as Cbor;
+
+    match value {
+        Value::Null => Cbor::Null,
+        Value::Bool(value) => Cbor::Bool(value),
+        Value::Number(value) => {
+            if let Some(value) = value.as_u64() {
+                Cbor::Integer(value.into())
+            } else if let Some(value) = value.as_i64() {
+                Cbor::Integer(value.into())
+            } else {
+                Cbor::Float(value.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        Value::String(value) => Cbor::Text(value),
+        Value::Array(values) => Cbor::Array(values.into_iter().map(json_to_cbor).collect()),
+        Value::Object(entries) => Cbor::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (Cbor::Text(key), json_to_cbor(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Payload couldn't be decoded with the encoding.
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub(crate) enum DecodeError {
+    /// Payload isn't valid in the encoding.
+    #[error("payload is not valid {0}")]
+    Invalid(Encoding),
+    /// Payload exceeded the maximum size, so wasn't read.
+    #[error("payload is too large")]
+    TooLarge(Encoding),
+}
+
+impl DecodeError {
+    /// Encoding of the payload, which any error response should also use.
+    pub(crate) fn encoding(self) -> Encoding {
+        match self {
+            Self::Invalid(encoding) | Self::TooLarge(encoding) => encoding,
+        }
+    }
+}
+
+/// Whether a body couldn't be read because it exceeded its size limit.
+pub(crate) fn is_too_large(err: &axum::Error) -> bool {
+    std::error::Error::source(err).is_some_and(|source| source.is::<LengthLimitError>())
+}
+
+/// Convert the body of a request into JSON, returning the encoding that the response should use.
+/// Without an `Accept` header, the response will use the same encoding as the request. If the
+/// response uses a binary encoding, every call in the request is marked with [`BinaryResponse`].
+pub(crate) async fn decode_request(
+    mut req: Request<Body>,
+    max_body_size: usize,
+) -> Result<(Request<Body>, Encoding), DecodeError> {
+    let encoding = Encoding::from_content_type(req.headers());
+    let response_encoding = Encoding::from_accept(req.headers()).unwrap_or(encoding);
+
+    if encoding != Encoding::Json {
+        let (mut parts, body) = req.into_parts();
+        let body = axum::body::to_bytes(body, max_body_size)
+            .await
+            .map_err(|err| {
+                if is_too_large(&err) {
+                    DecodeError::TooLarge(encoding)
+                } else {
+                    DecodeError::Invalid(encoding)
+                }
+            })?;
+        let payload = encoding.decode(&body)?;
+
+        parts
+            .headers
+            .insert(header::CONTENT_TYPE, Encoding::Json.content_type());
+        req = Request::from_parts(parts, Body::from(Encoding::Json.encode(&payload)));
+    }
+
+    if response_encoding != Encoding::Json {
+        req.headers_mut()
+            .insert(header::ACCEPT, Encoding::Json.content_type());
+        req.extensions_mut().insert(BinaryResponse);
+    }
+
+    Ok((req, response_encoding))
+}
+
+/// Convert a JSON response from the server into the provided encoding. Responses larger than
+/// `max_body_size` can't be converted, so are replaced with a JSON-RPC error in that encoding.
+pub(crate) async fn encode_response(
+    response: Response,
+    encoding: Encoding,
+    max_body_size: usize,
+) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::from_media_type)
+        == Some(Encoding::Json);
+
+    if encoding == Encoding::Json || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, max_body_size).await {
+        Ok(body) => body,
+        Err(err) => {
+            let message = if is_too_large(&err) {
+                format!(
+                    "response exceeds the maximum size of {max_body_size} bytes that can be sent as {encoding}"
+                )
+            } else {
+                "failed to read response body".to_string()
+            };
+
+            // The response couldn't be read, so the ID of the call is unknown.
+            let error =
+                ErrorObjectOwned::owned::<()>(ErrorCode::InternalError.code(), message, None);
+            let payload = json!({ "jsonrpc": "2.0", "error": error, "id": null });
+
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [(header::CONTENT_TYPE, encoding.content_type())],
+                encoding.encode_message(payload),
+            )
+                .into_response();
+        }
+    };
+    // Bodies which aren't JSON (such as the empty response to a notification) are left as-is.
+    let Ok(payload) = Encoding::Json.decode(&body) else {
+        return Response::from_parts(parts, Body::from(body));
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, encoding.content_type());
+    Response::from_parts(parts, Body::from(encoding.encode_message(payload)))
+}
+
+/// Upgrade a request to a WebSocket which uses the provided binary encoding. The server only
+/// accepts JSON, so the connection is proxied to the server over an in-memory connection,
+/// transcoding every message in either direction. JSON connections should be passed to the server
+/// directly. Every call made over the connection is marked with [`BinaryResponse`].
+pub(crate) fn upgrade<S>(mut req: Request<Body>, encoding: Encoding, service: S) -> Response
+where
+    S: 'static + Clone + Send + Service<Request<Body>>,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    debug_assert_ne!(
+        encoding,
+        Encoding::Json,
+        "JSON connections don't require a proxy"
+    );
+
+    let mut handshake = Server::new();
+    let mut response = match handshake.receive_request(&req) {
+        Ok(response) => response.map(|_| Body::empty()),
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    encoding.accept_protocol(&mut response);
+
+    req.extensions_mut().insert(BinaryResponse);
+
+    let on_upgrade = hyper::upgrade::on(&mut req);
+    let (parts, _) = req.into_parts();
+
+    tokio::spawn(async move {
+        let Ok(upgraded) = on_upgrade.await else {
+            return;
+        };
+
+        let (client_tx, client_rx) = handshake
+            .into_builder(TokioIo::new(upgraded).compat())
+            .finish();
+
+        let Some((server_tx, server_rx)) = connect(parts, service).await else {
+            return;
+        };
+
+        futures::join!(
+            forward(client_rx, server_tx, |message| {
+                // Messages that can't be decoded are left for the server to reject.
+                encoding
+                    .decode(&message)
+                    .map(|payload| Encoding::Json.encode(&payload))
+                    .unwrap_or(message)
+            }),
+            forward(server_rx, client_tx, |message| {
+                Encoding::Json
+                    .decode(&message)
+                    .map(|payload| encoding.encode_message(payload))
+                    .unwrap_or(message)
+            }),
+        );
+    });
+
+    response
+}
+
+/// Open a WebSocket to the server over an in-memory connection, on behalf of the client request
+/// described by `parts`. The upgrade request made to the server carries the headers and
+/// extensions of the client's request.
+async fn connect<S>(
+    parts: http::request::Parts,
+    service: S,
+) -> Option<(
+    Sender<Compat<tokio::io::DuplexStream>>,
+    Receiver<Compat<tokio::io::DuplexStream>>,
+)>
+where
+    S: 'static + Clone + Send + Service<Request<Body>>,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    let (client_io, server_io) = tokio::io::duplex(TRANSCODE_BUFFER_SIZE);
+
+    let serve = hyper::service::service_fn(move |mut req: Request<hyper::body::Incoming>| {
+        // Keep the handshake headers of the in-memory connection, but otherwise appear as the
+        // client's request.
+        let mut headers = parts.headers.clone();
+        headers.remove(SEC_WEBSOCKET_PROTOCOL);
+        for (name, value) in req.headers() {
+            if name.as_str().starts_with("sec-websocket") {
+                headers.insert(name, value.clone());
+            }
+        }
+        *req.headers_mut() = headers;
+        req.extensions_mut().extend(parts.extensions.clone());
+
+        let service = service.clone();
+        async move {
+            service
+                .oneshot(req.map(Body::new))
+                .await
+                .map(IntoResponse::into_response)
+        }
+        .boxed() as BoxFuture<'static, Result<Response, S::Error>>
+    });
+
+    tokio::spawn(
+        hyper::server::conn::http1::Builder::new()
+            .serve_connection(TokioIo::new(server_io), serve)
+            .with_upgrades(),
+    );
+
+    let mut client = Client::new(client_io.compat(), "localhost", "/");
+    match client.handshake().await {
+        Ok(ServerResponse::Accepted { .. }) => Some(client.into_builder().finish()),
+        _ => None,
+    }
+}
+
+/// Forward every message from `rx` to `tx` as binary, converting each with `transcode`. Once either
+/// side closes, the other is closed.
+async fn forward<R, W>(
+    mut rx: Receiver<R>,
+    mut tx: Sender<W>,
+    transcode: impl Fn(Vec<u8>) -> Vec<u8>,
+) where
+    R: futures::AsyncRead + futures::AsyncWrite + Unpin,
+    W: futures::AsyncRead + futures::AsyncWrite + Unpin,
+{
+    loop {
+        let mut message = Vec::new();
+        if rx.receive_data(&mut message).await.is_err() {
+            break;
+        }
+
+        let sent = tx.send_binary_mut(transcode(message)).await;
+        if sent.is_err() || tx.flush().await.is_err() {
+            break;
+        }
+    }
+
+    let _ = tx.close().await;
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::*;
+
+    fn headers(name: header::HeaderName, value: &'static str) -> HeaderMap {
+        HeaderMap::from_iter([(name, HeaderValue::from_static(value))])
+    }
+
+    #[rstest]
+    #[case::json("application/json", Encoding::Json)]
+    #[case::json_charset("application/json; charset=utf-8", Encoding::Json)]
+    #[case::msgpack("application/msgpack", Encoding::MessagePack)]
+    #[case::vnd_msgpack("application/vnd.msgpack", Encoding::MessagePack)]
+    #[case::x_msgpack("application/x-msgpack", Encoding::MessagePack)]
+    #[case::cbor("application/cbor", Encoding::Cbor)]
+    #[case::uppercase("Application/CBOR", Encoding::Cbor)]
+    #[case::unknown("text/plain", Encoding::Json)]
+    fn content_type(#[case] value: &'static str, #[case] expected: Encoding) {
+        assert_eq!(
+            Encoding::from_content_type(&headers(header::CONTENT_TYPE, value)),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::single("application/cbor", Some(Encoding::Cbor))]
+    #[case::first_supported(
+        "text/html, application/msgpack, application/json",
+        Some(Encoding::MessagePack)
+    )]
+    #[case::json("application/json, application/cbor", Some(Encoding::Json))]
+    #[case::quality(
+        "text/html, application/msgpack;q=0.9, application/json",
+        Some(Encoding::Json)
+    )]
+    #[case::quality_order("application/json;q=0.5, application/cbor;q=0.8", Some(Encoding::Cbor))]
+    #[case::quality_tie(
+        "application/cbor;q=0.5, application/json; q=0.5",
+        Some(Encoding::Cbor)
+    )]
+    #[case::quality_uppercase("application/json;Q=0.1, application/cbor", Some(Encoding::Cbor))]
+    #[case::quality_invalid("application/json;q=high, application/cbor", Some(Encoding::Json))]
+    #[case::not_acceptable("application/cbor;q=0", None)]
+    #[case::not_acceptable_fallback(
+        "application/cbor;q=0, application/json;q=0.1",
+        Some(Encoding::Json)
+    )]
+    #[case::unsupported("*/*", None)]
+    fn accept(#[case] value: &'static str, #[case] expected: Option<Encoding>) {
+        assert_eq!(
+            Encoding::from_accept(&headers(header::ACCEPT, value)),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::msgpack("qubit.msgpack", Some(Encoding::MessagePack))]
+    #[case::first_supported("graphql-ws, qubit.cbor, qubit.msgpack", Some(Encoding::Cbor))]
+    #[case::json("qubit.json", Some(Encoding::Json))]
+    #[case::unsupported("graphql-ws", None)]
+    fn protocols(#[case] value: &'static str, #[case] expected: Option<Encoding>) {
+        assert_eq!(
+            Encoding::from_protocols(&headers(
+                header::HeaderName::from_static(SEC_WEBSOCKET_PROTOCOL),
+                value
+            )),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn round_trip(
+        #[values(Encoding::Json, Encoding::MessagePack, Encoding::Cbor)] encoding: Encoding,
+    ) {
+        let value = json!({
+            "jsonrpc": "2.0",
+            "method": "get_values",
+            "params": [1, -2, 3.5, "four", [true, null], { "five": 5 }],
+            "id": 1,
+        });
+
+        assert_eq!(encoding.decode(&encoding.encode(&value)).unwrap(), value);
+    }
+
+    #[rstest]
+    #[case::msgpack(Encoding::MessagePack, rmp_serde::to_vec_named(&crate::Bytes::from([0, 255, 16])).unwrap())]
+    #[case::cbor(Encoding::Cbor, {
+        let mut payload = Vec::new();
+        ciborium::into_writer(&crate::Bytes::from([0, 255, 16]), &mut payload).unwrap();
+        payload
+    })]
+    fn binary_to_base64(#[case] encoding: Encoding, #[case] payload: Vec<u8>) {
+        assert_eq!(encoding.decode(&payload).unwrap(), json!("AP8Q"));
+    }
+
+    #[rstest]
+    fn invalid(
+        #[values(Encoding::Json, Encoding::MessagePack, Encoding::Cbor)] encoding: Encoding,
+    ) {
+        assert!(encoding.decode(&[0xc1]).is_err());
+    }
+
+    #[tokio::test]
+    async fn oversized_response() {
+        let response = ([(header::CONTENT_TYPE, "application/json")], "[1, 2, 3]").into_response();
+
+        let response = encode_response(response, Encoding::Cbor, 4).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            Encoding::Cbor.content_type()
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let payload = Encoding::Cbor.decode(&body).unwrap();
+        assert_eq!(payload["error"]["code"], ErrorCode::InternalError.code());
+        assert_eq!(
+            payload["error"]["message"],
+            "response exceeds the maximum size of 4 bytes that can be sent as CBOR"
+        );
+        assert_eq!(payload["id"], Value::Null);
+    }
+}
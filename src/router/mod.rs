@@ -1,14 +1,31 @@
 //! The [`Router`] is the key to the exposed API of Qubit. It provides the core of the hierarchy
 //! structure, but delegates any actual work (codegen, RPC integration) to [`RpcModule`]s.
 
-mod codegen;
+pub(crate) mod binary;
+pub(crate) mod codegen;
+mod csrf;
+mod encoding;
+mod request_info;
+mod response;
 mod rpc;
+mod test_client;
+
+pub use self::{
+    csrf::Csrf,
+    request_info::{RequestInfo, Transport},
+    response::ResponseParts,
+    rpc::RpcModule,
+    test_client::{TestClient, TestSubscription},
+};
+
+use std::sync::Arc;
 
 use crate::{
-    FromRequestExtensions, RegisterableHandler,
+    FromRequestExtensions, HandlerKind, RegisterableHandler,
+    codegen::ParamVisitor,
     handler::marker,
-    reflection::handler::HandlerMeta,
-    router::{codegen::CodegenModule, rpc::RpcModule},
+    reflection::handler::{HandlerMeta, HandlerMetadata},
+    router::codegen::CodegenModule,
     util::Graph,
 };
 
@@ -17,6 +34,24 @@ pub struct Router<Ctx> {
     handlers: Graph<String, Handler<Ctx>>,
 }
 
+/// Handler which can be registered to a [`Router`] with [`Router::handler`]. This is implemented
+/// by the [`handler`](crate::handler) macro, for routers with any context that the handler's
+/// `ctx` can be extracted from.
+///
+/// This isn't intended to be implemented manually. Handlers without the macro (such as closures)
+/// can instead be registered with [`Router::query`], [`Router::mutation`] or
+/// [`Router::subscription`].
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be registered to a `Router<{Ctx}>`",
+    label = "not a handler for `{Ctx}`",
+    note = "the function must be annotated with `#[handler]`, and its `ctx` must implement `FromRequestExtensions<{Ctx}>`",
+    note = "use `Router::query`, `Router::mutation` or `Router::subscription` to register a handler without the macro"
+)]
+pub trait RouterHandler<Ctx>: HandlerMetadata {
+    /// Insert this handler into the router.
+    fn register(self, router: Router<Ctx>) -> Router<Ctx>;
+}
+
 /// Actual information stored for each handler added to the router. Each [`RpcModule`] will have
 /// its own handler representation, used to type-erase the actual handler.
 struct Handler<Ctx> {
@@ -35,17 +70,118 @@ where
         }
     }
 
-    /// Register the provided handler to this router.
-    pub fn handler<F, MSig, MValue: marker::ResponseMarker, MReturn: marker::HandlerReturnMarker>(
-        mut self,
+    /// Register the provided handler to this router. The handler must be annotated with the
+    /// [`handler`](crate::handler) macro.
+    pub fn handler(self, handler: impl RouterHandler<Ctx>) -> Self {
+        handler.register(self)
+    }
+
+    /// Register a query without the [`handler`](crate::handler) macro, such as a closure. The
+    /// first parameter of the handler is the context, and the name of every other parameter must
+    /// be provided in order.
+    ///
+    /// ```
+    /// use qubit::Router;
+    ///
+    /// let router = Router::new().query("add", &["a", "b"], |ctx: (), a: u32, b: u32| a + b);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameter names doesn't match the handler.
+    pub fn query<F, MSig, MValue: marker::ResponseMarker, MReturn: marker::ResponseReturnMarker>(
+        self,
+        name: &'static str,
+        param_names: &'static [&'static str],
+        handler: F,
+    ) -> Self
+    where
+        F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
+        F::Ctx: FromRequestExtensions<Ctx>,
+    {
+        self.explicit_handler(HandlerKind::Query, name, param_names, handler)
+    }
+
+    /// Register a mutation without the [`handler`](crate::handler) macro. See [`Router::query`].
+    pub fn mutation<
+        F,
+        MSig,
+        MValue: marker::ResponseMarker,
+        MReturn: marker::ResponseReturnMarker,
+    >(
+        self,
+        name: &'static str,
+        param_names: &'static [&'static str],
+        handler: F,
+    ) -> Self
+    where
+        F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
+        F::Ctx: FromRequestExtensions<Ctx>,
+    {
+        self.explicit_handler(HandlerKind::Mutation, name, param_names, handler)
+    }
+
+    /// Register a subscription without the [`handler`](crate::handler) macro. See
+    /// [`Router::query`].
+    pub fn subscription<
+        F,
+        MSig,
+        MValue: marker::ResponseMarker,
+        MReturn: marker::StreamReturnMarker,
+    >(
+        self,
+        name: &'static str,
+        param_names: &'static [&'static str],
+        handler: F,
+    ) -> Self
+    where
+        F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
+        F::Ctx: FromRequestExtensions<Ctx>,
+    {
+        self.explicit_handler(HandlerKind::Subscription, name, param_names, handler)
+    }
+
+    /// Register a handler with explicitly provided metadata, rather than metadata generated by
+    /// the [`handler`](crate::handler) macro.
+    fn explicit_handler<
+        F,
+        MSig,
+        MValue: marker::ResponseMarker,
+        MReturn: marker::HandlerReturnMarker,
+    >(
+        self,
+        kind: HandlerKind,
+        name: &'static str,
+        param_names: &'static [&'static str],
         handler: F,
     ) -> Self
     where
         F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
         F::Ctx: FromRequestExtensions<Ctx>,
     {
-        let handler_meta = HandlerMeta::of(&handler);
+        // Catch mismatched names now, rather than when the router is used.
+        if let Err(e) = ParamVisitor::visit::<F::Params>(param_names) {
+            panic!("parameter names provided for `{name}` don't match the handler: {e}");
+        }
 
+        self.insert_handler(handler, Arc::new(HandlerMeta::new(kind, name, param_names)))
+    }
+
+    /// Insert a handler into this router, using the provided metadata.
+    pub(crate) fn insert_handler<
+        F,
+        MSig,
+        MValue: marker::ResponseMarker,
+        MReturn: marker::HandlerReturnMarker,
+    >(
+        mut self,
+        handler: F,
+        handler_meta: Arc<HandlerMeta>,
+    ) -> Self
+    where
+        F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
+        F::Ctx: FromRequestExtensions<Ctx>,
+    {
         // Insert a prefix corresponding with the handler's name.
         let prefix = self
             .handlers
@@ -57,10 +193,10 @@ where
             Handler {
                 codegen: <CodegenModule as RouterModule<Ctx>>::Handler::from_handler(
                     handler.clone(),
-                    handler_meta,
+                    Arc::clone(&handler_meta),
                 ),
                 rpc: <RpcModule<Ctx> as RouterModule<Ctx>>::Handler::from_handler(
-                    handler.clone(),
+                    handler,
                     handler_meta,
                 ),
             },
@@ -79,7 +215,40 @@ where
 
     /// Build an [`RpcModule`] from this router. This is required in order to start the RPC server.
     pub fn as_rpc(&self, ctx: Ctx) -> RpcModule<Ctx> {
-        self.as_module(RpcModule::new(ctx), |handler| &handler.rpc)
+        // Describing the API requires generating every type, so is deferred until the module needs
+        // it (if ever).
+        let handlers = self
+            .handlers
+            .iter()
+            .map(|(path, handler)| {
+                (
+                    path.into_iter().cloned().collect::<Vec<_>>(),
+                    handler.codegen.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let api = move || {
+            handlers
+                .iter()
+                .fold(CodegenModule::new(), |mut module, (path, handler)| {
+                    RouterModule::<Ctx>::visit_handler(
+                        &mut module,
+                        &path.iter().map(String::as_str).collect::<Vec<_>>(),
+                        handler,
+                    );
+
+                    module
+                })
+                .snapshot()
+        };
+
+        self.as_module(RpcModule::new(ctx, api), |handler| &handler.rpc)
+    }
+
+    /// Build a [`TestClient`] from this router, which can call handlers without a server. Use
+    /// [`RpcModule::into_test_client`] to configure the module first.
+    pub fn test_client(&self, ctx: Ctx) -> TestClient {
+        self.as_rpc(ctx).into_test_client()
     }
 
     /// Build a [`CodegenModule`] From this router. This is required to generate types for the
@@ -135,7 +304,7 @@ trait RouterModuleHandler<Ctx> {
     /// handler.
     fn from_handler<F, MSig, MValue: marker::ResponseMarker, MReturn: marker::HandlerReturnMarker>(
         handler: F,
-        meta: &'static HandlerMeta,
+        meta: Arc<HandlerMeta>,
     ) -> Self
     where
         F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
@@ -147,9 +316,7 @@ mod test {
     use jsonrpsee::RpcModule;
     use serde::Deserialize;
 
-    use std::any::{Any, TypeId};
-
-    use crate::reflection::handler::{HANDLER_DEFINITIONS, HandlerKind};
+    use crate::reflection::handler::HandlerKind;
 
     use super::*;
 
@@ -168,17 +335,31 @@ mod test {
         assert_eq!(module.method_names().count(), 0);
     }
 
-    /// Manually register the provided handler, with the associated [`HandlerMeta`]. This will
-    /// normally be done with the [`crate::handler`] proc-macro.
+    /// Manually define a handler type with the associated [`HandlerMeta`]. This will normally be
+    /// done with the [`crate::handler`] proc-macro.
     macro_rules! define_handler {
         (|| $body:expr, $meta:expr $(,)?) => {{
-            fn handler() -> u32 {
-                $body
+            #[derive(Clone, Copy)]
+            struct DefinedHandler;
+
+            impl HandlerMetadata for DefinedHandler {
+                fn meta() -> &'static HandlerMeta {
+                    static META: HandlerMeta = $meta;
+                    &META
+                }
+            }
+
+            impl RouterHandler<()> for DefinedHandler {
+                fn register(self, router: Router<()>) -> Router<()> {
+                    fn handler() -> u32 {
+                        $body
+                    }
+
+                    router.insert_handler(handler, Arc::new(Self::meta().clone()))
+                }
             }
 
-            #[linkme::distributed_slice(HANDLER_DEFINITIONS)]
-            static DEF: fn() -> (TypeId, HandlerMeta) = || (Any::type_id(&handler), $meta);
-            handler
+            DefinedHandler
         }};
     }
 
@@ -187,11 +368,7 @@ mod test {
         let module = Router::new()
             .handler(define_handler! {
                 || 123u32,
-                HandlerMeta {
-                    kind: HandlerKind::Query,
-                    name: "handler",
-                    param_names: &[],
-                },
+                HandlerMeta::new(HandlerKind::Query, "handler", &[]),
             })
             .as_rpc(())
             .into_module();
@@ -205,19 +382,11 @@ mod test {
         let module = Router::new()
             .handler(define_handler! {
                 || 123u32,
-                HandlerMeta {
-                    kind: HandlerKind::Query,
-                    name: "handler_1",
-                    param_names: &[],
-                },
+                HandlerMeta::new(HandlerKind::Query, "handler_1", &[]),
             })
             .handler(define_handler! {
                 || 321u32,
-                HandlerMeta {
-                    kind: HandlerKind::Query,
-                    name: "handler_2",
-                    param_names: &[],
-                },
+                HandlerMeta::new(HandlerKind::Query, "handler_2", &[]),
             })
             .as_rpc(())
             .into_module();
@@ -234,11 +403,7 @@ mod test {
                 "nested",
                 Router::new().handler(define_handler! {
                     || 123u32,
-                    HandlerMeta {
-                        kind: HandlerKind::Query,
-                        name: "handler",
-                        param_names: &[],
-                    },
+                    HandlerMeta::new(HandlerKind::Query, "handler", &[]),
                 }),
             )
             .as_rpc(())
@@ -255,22 +420,14 @@ mod test {
                 "nested_1",
                 Router::new().handler(define_handler! {
                     || 123u32,
-                    HandlerMeta {
-                        kind: HandlerKind::Query,
-                        name: "handler",
-                        param_names: &[],
-                    },
+                    HandlerMeta::new(HandlerKind::Query, "handler", &[]),
                 }),
             )
             .nest(
                 "nested_2",
                 Router::new().handler(define_handler! {
                     || 321u32,
-                    HandlerMeta {
-                        kind: HandlerKind::Query,
-                        name: "handler",
-                        param_names: &[],
-                    },
+                    HandlerMeta::new(HandlerKind::Query, "handler", &[]),
                 }),
             )
             .as_rpc(())
@@ -286,40 +443,24 @@ mod test {
         let module = Router::new()
             .handler(define_handler! {
                 || 123u32,
-                HandlerMeta {
-                    kind: HandlerKind::Query,
-                    name: "handler_1",
-                    param_names: &[],
-                },
+                HandlerMeta::new(HandlerKind::Query, "handler_1", &[]),
             })
             .handler(define_handler! {
                 || 321u32,
-                HandlerMeta {
-                    kind: HandlerKind::Query,
-                    name: "handler_2",
-                    param_names: &[],
-                },
+                HandlerMeta::new(HandlerKind::Query, "handler_2", &[]),
             })
             .nest(
                 "nested_1",
                 Router::new().handler(define_handler! {
                     || 456u32,
-                    HandlerMeta {
-                        kind: HandlerKind::Query,
-                        name: "handler",
-                        param_names: &[],
-                    },
+                    HandlerMeta::new(HandlerKind::Query, "handler", &[]),
                 }),
             )
             .nest(
                 "nested_2",
                 Router::new().handler(define_handler! {
                     || 654u32,
-                    HandlerMeta {
-                        kind: HandlerKind::Query,
-                        name: "handler",
-                        param_names: &[],
-                    },
+                    HandlerMeta::new(HandlerKind::Query, "handler", &[]),
                 }),
             )
             .as_rpc(())
@@ -0,0 +1,183 @@
+//! Metadata describing the request that triggered a call, available to every handler.
+
+use std::{
+    net::SocketAddr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use axum::extract::ConnectInfo;
+use http::{HeaderMap, Request, Uri};
+use jsonrpsee::{
+    server::middleware::rpc::{
+        Batch, BatchEntry, Notification, Request as RpcRequest, RpcServiceT,
+    },
+    types::Id,
+};
+
+/// Source of unique connection IDs.
+static CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Information about the request that triggered a call, inserted into the [`Extensions`] of every
+/// call made through a service created with [`RpcModule::into_service`]. A context can retrieve it
+/// whilst implementing [`FromRequestExtensions`].
+///
+/// ```
+/// use qubit::{Extensions, FromRequestExtensions, RequestInfo, RpcError};
+///
+/// #[derive(Clone)]
+/// struct Ctx;
+///
+/// struct ClientIp(Option<std::net::IpAddr>);
+///
+/// impl FromRequestExtensions<Ctx> for ClientIp {
+///     async fn from_request_extensions(_ctx: Ctx, extensions: Extensions) -> Result<Self, RpcError> {
+///         let ip = extensions
+///             .get::<RequestInfo>()
+///             .and_then(RequestInfo::remote_addr)
+///             .map(|addr| addr.ip());
+///
+///         Ok(ClientIp(ip))
+///     }
+/// }
+/// ```
+///
+/// [`Extensions`]: crate::Extensions
+/// [`FromRequestExtensions`]: crate::FromRequestExtensions
+/// [`RpcModule::into_service`]: crate::RpcModule::into_service
+#[derive(Clone, Debug)]
+pub struct RequestInfo {
+    method: String,
+    id: Id<'static>,
+    headers: HeaderMap,
+    uri: Uri,
+    remote_addr: Option<SocketAddr>,
+    transport: Transport,
+    connection_id: u64,
+}
+
+/// Transport that a call was made over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    /// Regular HTTP request, where the connection only lasts for a single request (or batch).
+    Http,
+    /// WebSocket connection, which may be used for many calls.
+    Ws,
+}
+
+impl RequestInfo {
+    /// Capture the information of a HTTP request, before any call has been parsed from it. Each
+    /// request will be assigned a new connection ID.
+    pub(crate) fn from_request<B>(req: &Request<B>, transport: Transport) -> Self {
+        Self {
+            method: String::new(),
+            id: Id::Null,
+            headers: req.headers().clone(),
+            uri: req.uri().clone(),
+            remote_addr: req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| *addr),
+            transport,
+            connection_id: CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Path of the method being called, such as `user.get`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// ID of the JSON-RPC request, which will be [`Id::Null`] for notifications.
+    pub fn id(&self) -> &Id<'static> {
+        &self.id
+    }
+
+    /// Headers of the HTTP request. For WebSockets, these are the headers of the upgrade request.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// URI of the HTTP request. For WebSockets, this is the URI of the upgrade request.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Address of the client. This is only available if the server was started with
+    /// [`into_make_service_with_connect_info`], otherwise it will be [`None`].
+    ///
+    /// [`into_make_service_with_connect_info`]: axum::Router::into_make_service_with_connect_info
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Transport that the call was made over.
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    /// Unique ID of the connection. Every call made over the same WebSocket (or within the same
+    /// HTTP batch) will share the same ID.
+    pub fn connection_id(&self) -> u64 {
+        self.connection_id
+    }
+}
+
+/// RPC middleware which completes the [`RequestInfo`] of each call with its method and ID.
+#[derive(Clone)]
+pub(crate) struct RequestInfoService<S>(pub S);
+
+impl<S> RequestInfoService<S> {
+    /// Update the [`RequestInfo`] of a call, if present.
+    fn complete(request: &mut RpcRequest<'_>) {
+        let method = request.method_name().to_string();
+        let id = request.id().into_owned();
+
+        if let Some(info) = request.extensions_mut().get_mut::<RequestInfo>() {
+            info.method = method;
+            info.id = id;
+        }
+    }
+}
+
+impl<S> RpcServiceT for RequestInfoService<S>
+where
+    S: RpcServiceT + Send + Sync,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        mut request: RpcRequest<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        Self::complete(&mut request);
+        self.0.call(request)
+    }
+
+    fn batch<'a>(
+        &self,
+        mut requests: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        // The inner service won't call back into this middleware for each call in the batch.
+        for request in requests.iter_mut() {
+            if let Ok(BatchEntry::Call(request)) = request {
+                Self::complete(request);
+            }
+        }
+
+        self.0.batch(requests)
+    }
+
+    fn notification<'a>(
+        &self,
+        mut notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        let method = notification.method_name().to_string();
+        if let Some(info) = notification.extensions_mut().get_mut::<RequestInfo>() {
+            info.method = method;
+        }
+
+        self.0.notification(notification)
+    }
+}
@@ -0,0 +1,222 @@
+//! Modifications that handlers can make to the HTTP response of the request that called them.
+
+use std::sync::{Arc, Mutex};
+
+use axum::response::Response;
+use http::{
+    Extensions, HeaderMap, HeaderName, HeaderValue, StatusCode, header, header::InvalidHeaderValue,
+};
+
+use crate::{FromRequestExtensions, RpcError};
+
+/// Modifications to apply to the HTTP response, shared between the service and every call made in
+/// the request.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResponseHandle(Arc<Mutex<PendingResponse>>);
+
+/// Status and headers set by handlers, which have not yet been applied to the response.
+#[derive(Debug, Default)]
+struct PendingResponse {
+    status: Option<StatusCode>,
+    headers: HeaderMap,
+}
+
+impl ResponseHandle {
+    /// Apply any modifications made by handlers to the response.
+    pub(crate) fn apply(&self, response: &mut Response) {
+        let mut pending = self.0.lock().expect("response handle not poisoned");
+
+        if let Some(status) = pending.status.take() {
+            *response.status_mut() = status;
+        }
+
+        let mut name = None;
+        for (next_name, value) in std::mem::take(&mut pending.headers) {
+            // Only the first value of each header will include the name. It replaces any value set
+            // by the server, and any further values are appended to it.
+            match next_name {
+                Some(next_name) => {
+                    response.headers_mut().insert(&next_name, value);
+                    name = Some(next_name);
+                }
+                None => {
+                    let name = name.clone().expect("first header includes name");
+                    response.headers_mut().append(name, value);
+                }
+            }
+        }
+    }
+}
+
+/// Handle to modify the HTTP response of the request that called the handler, such as to set a
+/// cookie after logging in.
+///
+/// Modifications can only be made when the call arrived over HTTP. WebSocket connections have
+/// already sent their response by the time a call is made, so this will be detached and any
+/// modifications will be ignored (see [`ResponseParts::is_detached`]). If a HTTP request contains
+/// a batch, every call in the batch will share the same response.
+///
+/// ```
+/// use qubit::{ResponseParts, handler};
+///
+/// #[handler(mutation)]
+/// async fn logout(mut response: ResponseParts) {
+///     response
+///         .set_cookie("session=; Path=/; Max-Age=0")
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ResponseParts {
+    handle: Option<ResponseHandle>,
+}
+
+impl ResponseParts {
+    /// Retrieve the response from the extensions of a call. If the call wasn't made over HTTP,
+    /// it will be detached.
+    pub(crate) fn from_extensions(extensions: &Extensions) -> Self {
+        Self {
+            handle: extensions.get::<ResponseHandle>().cloned(),
+        }
+    }
+
+    /// Whether modifications to this response will be ignored, as the call wasn't made over HTTP.
+    pub fn is_detached(&self) -> bool {
+        self.handle.is_none()
+    }
+
+    /// Set the status code of the response. By default, the status code produced by the server
+    /// will be used.
+    pub fn set_status(&mut self, status: StatusCode) -> &mut Self {
+        self.modify(|pending| pending.status = Some(status));
+        self
+    }
+
+    /// Insert a header into the response, replacing any previous values (including any set by the
+    /// server).
+    pub fn insert_header(&mut self, name: HeaderName, value: HeaderValue) -> &mut Self {
+        self.modify(|pending| {
+            pending.headers.insert(name, value);
+        });
+        self
+    }
+
+    /// Append a header to the response, preserving any previous values set by handlers. Any values
+    /// set by the server will be replaced.
+    pub fn append_header(&mut self, name: HeaderName, value: HeaderValue) -> &mut Self {
+        self.modify(|pending| {
+            pending.headers.append(name, value);
+        });
+        self
+    }
+
+    /// Add a `Set-Cookie` header to the response, containing the cookie and its attributes (such
+    /// as `session=abc; Path=/; HttpOnly`).
+    pub fn set_cookie(&mut self, cookie: impl AsRef<str>) -> Result<&mut Self, InvalidHeaderValue> {
+        let value = HeaderValue::from_str(cookie.as_ref())?;
+        Ok(self.append_header(header::SET_COOKIE, value))
+    }
+
+    /// Apply a modification to the pending response, if attached.
+    fn modify(&self, f: impl FnOnce(&mut PendingResponse)) {
+        if let Some(handle) = &self.handle {
+            f(&mut handle.0.lock().expect("response handle not poisoned"));
+        }
+    }
+}
+
+impl<Ctx> FromRequestExtensions<Ctx> for ResponseParts
+where
+    Ctx: Clone + Send,
+{
+    async fn from_request_extensions(_ctx: Ctx, extensions: Extensions) -> Result<Self, RpcError> {
+        Ok(Self::from_extensions(&extensions))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::body::Body;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn apply() {
+        let handle = ResponseHandle::default();
+        let mut extensions = Extensions::new();
+        extensions.insert(handle.clone());
+
+        let mut parts = ResponseParts::from_request_extensions((), extensions)
+            .await
+            .unwrap();
+        assert!(!parts.is_detached());
+
+        parts
+            .set_status(StatusCode::CREATED)
+            .insert_header(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        parts.set_cookie("a=1").unwrap().set_cookie("b=2").unwrap();
+
+        let mut response = Response::new(Body::empty());
+        handle.apply(&mut response);
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(
+            response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+    }
+
+    #[test]
+    fn apply_replaces_server_headers() {
+        let handle = ResponseHandle::default();
+        let mut parts = ResponseParts {
+            handle: Some(handle.clone()),
+        };
+        parts
+            .insert_header(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))
+            .append_header(header::VARY, HeaderValue::from_static("a"))
+            .append_header(header::VARY, HeaderValue::from_static("b"));
+
+        let mut response = Response::new(Body::empty());
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("max-age=60"),
+        );
+        headers.insert(header::VARY, HeaderValue::from_static("origin"));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+
+        handle.apply(&mut response);
+
+        let values = |name| {
+            response
+                .headers()
+                .get_all(name)
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(header::CACHE_CONTROL), ["no-store"]);
+        assert_eq!(values(header::VARY), ["a", "b"]);
+        assert_eq!(values(header::CONTENT_TYPE), ["application/json"]);
+    }
+
+    #[tokio::test]
+    async fn detached() {
+        let mut parts = ResponseParts::from_request_extensions((), Extensions::new())
+            .await
+            .unwrap();
+        assert!(parts.is_detached());
+
+        // Modifications are ignored.
+        parts.set_status(StatusCode::CREATED);
+    }
+}
@@ -1,96 +1,357 @@
-use std::{collections::HashMap, convert::Infallible};
+use std::{collections::HashMap, convert::Infallible, sync::Arc, time::Duration};
 
-use axum::response::IntoResponse;
+use axum::{
+    Json,
+    body::Body,
+    response::{IntoResponse, Response},
+};
 use futures::FutureExt;
-use http::{HeaderValue, Method, Request, header};
+use http::{HeaderValue, Method, Request, StatusCode, header};
 use jsonrpsee::{
     RpcModule as JsonRpseeModule,
-    server::{Server, ServerHandle, stop_channel, ws::is_upgrade_request},
+    server::{
+        Server, ServerHandle, middleware::rpc::RpcServiceBuilder, stop_channel,
+        ws::is_upgrade_request,
+    },
+    types::{ErrorObjectOwned, ResponsePayload, error::ErrorCode},
 };
-use tower::{Service, ServiceBuilder, service_fn};
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+use tower::{Service, service_fn};
 
 use crate::{
-    FromRequestExtensions, RegisterableHandler,
-    handler::marker,
-    reflection::handler::HandlerMeta,
-    router::{RouterModule, RouterModuleHandler},
+    ApiSnapshot, FromRequestExtensions, RegisterableHandler,
+    concurrency::ConcurrencyLimit,
+    handler::{RegisterOptions, cancellation::ConnectionToken, marker},
+    metrics::Metrics,
+    rate_limit::RateLimiter,
+    reflection::handler::{HandlerKind, HandlerMeta},
+    router::{
+        RouterModule, RouterModuleHandler,
+        binary::{BinaryResponse, ResultValue},
+        csrf::Csrf,
+        encoding::{self, DecodeError, Encoding},
+        request_info::{RequestInfo, RequestInfoService, Transport},
+        response::ResponseHandle,
+        test_client::TestClient,
+    },
 };
 
+/// Method that describes the API of the module, if introspection is enabled.
+const SCHEMA_METHOD: &str = "qubit.schema";
+
+/// Method that produces an OpenRPC document for the module, if introspection is enabled.
+const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// Maximum size of a request body that will be buffered in order to perform CSRF checks or to
+/// transcode it, which also applies to transcoded responses. This matches the default limits of
+/// the underlying server.
+const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
 /// Integration between [`Router`] and [`JsonRpseeModule`].
 ///
 /// [`Router`]: crate::Router
-pub struct RpcModule<Ctx>(JsonRpseeModule<Ctx>);
+pub struct RpcModule<Ctx> {
+    ctx: Ctx,
+    /// Handlers to register once the module is built, keyed by method name.
+    handlers: Vec<(String, Handler<Ctx>)>,
+    /// Kind of each registered handler, keyed by method name.
+    kinds: HashMap<String, HandlerKind>,
+    /// CSRF protection to apply to requests, if enabled.
+    csrf: Option<Csrf>,
+    /// Options to register each handler with.
+    options: RegisterOptions<Ctx>,
+    /// Produces a description of every handler in the module, which is only required for
+    /// introspection.
+    api: Arc<dyn Fn() -> ApiSnapshot + Send + Sync>,
+    /// Title and version of the API, along with the description of the API, to include in
+    /// introspection responses, if enabled.
+    introspection: Option<(String, String, ApiSnapshot)>,
+}
 
 impl<Ctx> RpcModule<Ctx> {
     /// Create a new instance.
-    pub(crate) fn new(ctx: Ctx) -> Self {
-        Self(JsonRpseeModule::new(ctx))
+    pub(crate) fn new(ctx: Ctx, api: impl 'static + Fn() -> ApiSnapshot + Send + Sync) -> Self {
+        Self {
+            ctx,
+            handlers: Vec::new(),
+            kinds: HashMap::new(),
+            csrf: None,
+            options: RegisterOptions::default(),
+            api: Arc::new(api),
+            introspection: None,
+        }
+    }
+
+    /// Enable CSRF protection for the service produced by [`RpcModule::into_service`]. See
+    /// [`Csrf`] for available options.
+    pub fn with_csrf(mut self, csrf: Csrf) -> Self {
+        self.csrf = Some(csrf);
+        self
+    }
+
+    /// Record [`Metrics`] for every call made to this module, such as [`InMemoryMetrics`].
+    ///
+    /// [`InMemoryMetrics`]: crate::metrics::InMemoryMetrics
+    pub fn with_metrics(mut self, metrics: impl Metrics) -> Self {
+        self.options.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Enforce the rate limits of handlers (declared with the `rate_limit` attribute) using the
+    /// provided [`RateLimiter`]. This is required in order to produce a service with
+    /// [`RpcModule::into_service`] if any handler declares a rate limit.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter<Ctx>) -> Self {
+        self.options.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Cancel any query or mutation that doesn't complete within `timeout`, responding with
+    /// [`RpcError::timed_out`]. Handlers may override this with the `timeout` attribute.
+    ///
+    /// [`RpcError::timed_out`]: crate::RpcError::timed_out
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Run every synchronous query and mutation on the blocking thread pool, as if each had the
+    /// `blocking` attribute. This prevents handlers which perform CPU-heavy work or blocking I/O
+    /// from stalling other calls.
+    pub fn with_blocking(mut self) -> Self {
+        self.options.blocking = true;
+        self
+    }
+
+    /// Limit the number of concurrent calls to the handler at `method` (such as `posts.create`),
+    /// replacing any limit declared with the `max_concurrency` attribute.
+    ///
+    /// # Panics
+    ///
+    /// If there is no handler at `method`, or the limit is invalid (see [`ConcurrencyLimit::new`]).
+    pub fn with_concurrency_limit(
+        mut self,
+        method: impl Into<String>,
+        limit: ConcurrencyLimit,
+    ) -> Self {
+        let method = method.into();
+        assert!(
+            self.handlers.iter().any(|(path, _)| *path == method),
+            "cannot limit concurrency of `{method}`, as there is no handler with that name"
+        );
+        limit.assert_valid();
+
+        Arc::make_mut(&mut self.options.concurrency_limits).insert(method, limit);
+        self
+    }
+
+    /// Register methods which describe the API of this module at runtime, so that tooling can
+    /// discover the API of a running server:
+    ///
+    /// - `qubit.schema` responds with the [`ApiSnapshot`] of the module, including every handler
+    ///   and the TypeScript definition of each dependent type. This can be compared against the
+    ///   snapshot that a client was generated from with [`ApiSnapshot::diff`].
+    ///
+    /// - `rpc.discover` responds with an [OpenRPC](https://spec.open-rpc.org) document, using the
+    ///   provided title and version.
+    ///
+    /// Both methods are queries, so may also be called with a `GET` request.
+    pub fn with_introspection(mut self, title: impl ToString, version: impl ToString) -> Self {
+        self.introspection = Some((title.to_string(), version.to_string(), (self.api)()));
+
+        for method in [SCHEMA_METHOD, DISCOVER_METHOD] {
+            self.kinds.insert(method.to_string(), HandlerKind::Query);
+        }
+
+        self
     }
 
     /// Consume this module, and expose the underlying [`JsonRpseeModule`].
     pub fn into_module(self) -> JsonRpseeModule<Ctx> {
-        self.0
+        let mut module = JsonRpseeModule::new(self.ctx);
+
+        for (path, handler) in self.handlers {
+            (handler.register)(&mut module, path, &self.options);
+        }
+
+        if let Some((title, version, api)) = self.introspection {
+            // Registered to a separate module, as the methods don't require the context.
+            let mut introspection = JsonRpseeModule::new(());
+
+            // Both documents are static, so only need to be serialised once for each kind of
+            // response.
+            for (method, document) in [
+                (SCHEMA_METHOD, serde_json::to_value(&api)),
+                (DISCOVER_METHOD, Ok(api.to_open_rpc(&title, &version))),
+            ] {
+                let document = document.expect("API description is valid JSON");
+                let [json, binary] = [false, true].map(|binary| {
+                    serde_json::value::to_raw_value(&ResultValue::new(&document, binary))
+                        .expect("API description can be serialised")
+                });
+
+                introspection
+                    .register_method(method, move |_, _, extensions| {
+                        ResponsePayload::success(if BinaryResponse::is_requested(extensions) {
+                            binary.clone()
+                        } else {
+                            json.clone()
+                        })
+                    })
+                    .expect("introspection methods are unique");
+            }
+
+            module.merge(introspection).unwrap_or_else(|_| {
+                panic!("`{SCHEMA_METHOD}` and `{DISCOVER_METHOD}` are reserved for introspection")
+            });
+        }
+
+        module
+    }
+
+    /// Consume this module, and produce a [`TestClient`] which can call handlers without a
+    /// server.
+    pub fn into_test_client(self) -> TestClient {
+        TestClient::new(self.into_module())
     }
 
     /// Consume this module, and produce a [`Service`].
+    ///
+    /// Queries may be made with a `GET` request, where the JSON-RPC request is provided in the
+    /// `input` query parameter. Any other handler kind will be rejected with
+    /// [`StatusCode::METHOD_NOT_ALLOWED`], so that side effects can't be triggered by a `GET`.
+    ///
+    /// Payloads are JSON by default, but may instead be MessagePack or CBOR. A request body is
+    /// decoded according to its `Content-Type` (`application/msgpack` or `application/cbor`), and
+    /// the response uses the supported type with the highest quality in `Accept`, falling back to
+    /// the encoding of the request. WebSockets select an encoding with the `qubit.json`,
+    /// `qubit.msgpack` or `qubit.cbor` subprotocol, in which case every message (including
+    /// subscription notifications) is sent in that encoding. Values are transcoded through JSON, so
+    /// handlers use their existing `Serialize` and `Deserialize` implementations. Any
+    /// [`Bytes`](crate::Bytes) in a result are tagged as it is serialised, so that they are sent
+    /// as raw binary. As a result, binary encodings carry some overhead compared with
+    /// JSON, particularly for WebSockets which are proxied in-process in order to transcode each
+    /// message.
+    ///
+    /// # Panics
+    ///
+    /// If any handler declares a rate limit, but no [`RateLimiter`] was provided with
+    /// [`RpcModule::with_rate_limiter`].
     pub fn into_service(
-        self,
+        mut self,
     ) -> (
         impl Service<
-            Request<axum::body::Body>,
+            Request<Body>,
             Error = Infallible,
             Future = impl Send,
             Response = impl IntoResponse,
         > + Clone,
         ServerHandle,
     ) {
-        let module = self.into_module();
+        // Declared limits must be enforced, rather than silently ignored.
+        if self.options.rate_limiter.is_none()
+            && let Some((method, _)) = self
+                .handlers
+                .iter()
+                .find(|(_, handler)| handler.rate_limited)
+        {
+            panic!(
+                "`{method}` declares a rate limit, but no rate limiter was provided with `RpcModule::with_rate_limiter`"
+            );
+        }
+
+        let kinds = Arc::new(std::mem::take(&mut self.kinds));
+        let csrf = self.csrf.take().map(Arc::new);
         let (stop_handle, server_handle) = stop_channel();
 
-        let mut tower_service = Server::builder()
-            .set_http_middleware(ServiceBuilder::new().map_request(|mut req: Request<_>| {
-                // Check if this is a GET request, and if it is convert it to a regular POST.
-                if matches!(req.method(), &Method::GET) && !is_upgrade_request(&req) {
-                    // Change this request into a regular POST request, and indicate that it should
-                    // be a query.
-                    *req.method_mut() = Method::POST;
-
-                    // Update the headers.
-                    let headers = req.headers_mut();
-                    headers.insert(
-                        header::CONTENT_TYPE,
-                        HeaderValue::from_static("application/json"),
-                    );
-                    headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
-
-                    // Convert the `input` field of the query string into the request body.
-                    if let Some(body) = req
-                        // Extract the query string.
-                        .uri()
-                        .query()
-                        // Parse the query string.
-                        .and_then(|query| serde_qs::from_str::<HashMap<String, String>>(query).ok())
-                        // Take out the input.
-                        .and_then(|mut query| query.remove("input"))
-                        // URL decode the input.
-                        .map(|input| urlencoding::decode(&input).unwrap_or_default().to_string())
-                    {
-                        // TODO: Replace `axum` with something else.
-                        *req.body_mut() = axum::body::Body::from(body);
+        let tower_service = Server::builder()
+            .set_rpc_middleware(RpcServiceBuilder::new().layer_fn(RequestInfoService))
+            .to_service_builder()
+            .build(self.into_module(), stop_handle);
+
+        let service = service_fn(move |req: Request<Body>| {
+            let kinds = Arc::clone(&kinds);
+            let csrf = csrf.clone();
+            let mut tower_service = tower_service.clone();
+
+            async move {
+                let upgrade = is_upgrade_request(&req);
+
+                // Payloads in other encodings are converted to JSON for the server.
+                let (req, encoding) = if upgrade {
+                    (req, Encoding::Json)
+                } else {
+                    match encoding::decode_request(req, MAX_BODY_SIZE).await {
+                        Ok(decoded) => decoded,
+                        Err(err) => {
+                            let response = match err {
+                                DecodeError::Invalid(encoding) => Rejection::Undecodable(encoding),
+                                DecodeError::TooLarge(_) => Rejection::TooLarge,
+                            }
+                            .into_response();
+                            return Ok(encoding::encode_response(
+                                response,
+                                err.encoding(),
+                                MAX_BODY_SIZE,
+                            )
+                            .await);
+                        }
                     }
                 };
 
-                req
-            }))
-            .to_service_builder()
-            .build(module, stop_handle);
+                let mut req = match prepare_request(req, &kinds, csrf.as_deref()).await {
+                    Ok(req) => req,
+                    Err(rejection) => {
+                        let response = rejection.into_response();
+                        return Ok(
+                            encoding::encode_response(response, encoding, MAX_BODY_SIZE).await
+                        );
+                    }
+                };
 
-        let service = service_fn(move |req| {
-            let call = tower_service.call(req);
+                // Calls made over a WebSocket outlive the request, so must be cancelled once the
+                // connection closes.
+                if upgrade {
+                    let token = CancellationToken::new();
+                    req.extensions_mut().insert(ConnectionToken(token.clone()));
 
-            async move {
-                match call.await {
-                    Ok(response) => Ok::<_, Infallible>(response),
+                    let closed = tower_service.on_session_closed();
+                    tokio::spawn(async move {
+                        closed.await;
+                        token.cancel();
+                    });
+
+                    // Connections which negotiate a binary encoding are transcoded by a proxy.
+                    if let Some(encoding) = Encoding::from_protocols(req.headers())
+                        && encoding != Encoding::Json
+                    {
+                        return Ok(encoding::upgrade(req, encoding, tower_service));
+                    }
+                }
+
+                // JSON connections are served directly, but must still confirm the subprotocol.
+                let json_protocol =
+                    upgrade && Encoding::from_protocols(req.headers()) == Some(Encoding::Json);
+
+                let response_handle = req.extensions().get::<ResponseHandle>().cloned();
+
+                match tower_service.call(req).await {
+                    Ok(response) => {
+                        let mut response = response.into_response();
+
+                        // Apply any changes that handlers made to the response.
+                        if let Some(response_handle) = response_handle {
+                            response_handle.apply(&mut response);
+                        }
+
+                        if json_protocol && response.status() == StatusCode::SWITCHING_PROTOCOLS {
+                            Encoding::Json.accept_protocol(&mut response);
+                        }
+
+                        Ok::<_, Infallible>(
+                            encoding::encode_response(response, encoding, MAX_BODY_SIZE).await,
+                        )
+                    }
                     // TODO: This should probably be an internal error
                     Err(_) => unreachable!(),
                 }
@@ -102,35 +363,308 @@ impl<Ctx> RpcModule<Ctx> {
     }
 }
 
+/// Prepare a request before it is passed to the server, rejecting it if it is not allowed.
+async fn prepare_request(
+    mut req: Request<Body>,
+    kinds: &HashMap<String, HandlerKind>,
+    csrf: Option<&Csrf>,
+) -> Result<Request<Body>, Rejection> {
+    let upgrade = is_upgrade_request(&req);
+
+    // Make the request available to handlers, which will be propagated to each call.
+    let info = RequestInfo::from_request(
+        &req,
+        if upgrade {
+            Transport::Ws
+        } else {
+            Transport::Http
+        },
+    );
+    req.extensions_mut().insert(info);
+
+    if upgrade {
+        if let Some(csrf) = csrf {
+            csrf.check_origin(req.headers())
+                .map_err(|reason| Rejection::Csrf {
+                    reason,
+                    id: Value::Null,
+                })?;
+        }
+
+        return Ok(req);
+    }
+
+    // Allow handlers to modify the response, which isn't possible once upgraded.
+    req.extensions_mut().insert(ResponseHandle::default());
+
+    match *req.method() {
+        // Check if this is a GET request, and if it is convert it to a regular POST.
+        Method::GET => {
+            get_to_post(&mut req, kinds)?;
+        }
+        // Mutations must include a CSRF token, which requires the body to find the methods.
+        Method::POST if csrf.is_some_and(Csrf::requires_token) => {
+            let csrf = csrf.expect("csrf is enabled");
+            let (parts, body) = req.into_parts();
+            let body = axum::body::to_bytes(body, MAX_BODY_SIZE)
+                .await
+                .map_err(|err| {
+                    if encoding::is_too_large(&err) {
+                        Rejection::TooLarge
+                    } else {
+                        Rejection::InvalidBody
+                    }
+                })?;
+
+            // Malformed requests are left for the server to respond to.
+            if let Ok(payload) = serde_json::from_slice::<Value>(&body)
+                && methods(&payload).any(|method| kinds.get(method) == Some(&HandlerKind::Mutation))
+            {
+                csrf.check_token(&parts.headers)
+                    .map_err(|reason| Rejection::Csrf {
+                        reason,
+                        id: request_id(&payload),
+                    })?;
+            }
+
+            req = Request::from_parts(parts, Body::from(body));
+        }
+        _ => {}
+    }
+
+    Ok(req)
+}
+
+/// Convert a `GET` request into a regular `POST` request, using the `input` field of the query
+/// string as the body. Will be rejected if the request attempts to call any handler that isn't a
+/// query.
+fn get_to_post(
+    req: &mut Request<Body>,
+    kinds: &HashMap<String, HandlerKind>,
+) -> Result<(), Rejection> {
+    // Convert the `input` field of the query string into the request body.
+    let body = req
+        // Extract the query string.
+        .uri()
+        .query()
+        // Parse the query string.
+        .and_then(|query| serde_qs::from_str::<HashMap<String, String>>(query).ok())
+        // Take out the input.
+        .and_then(|mut query| query.remove("input"))
+        // URL decode the input.
+        .map(|input| urlencoding::decode(&input).unwrap_or_default().to_string());
+
+    // Malformed requests are left for the server to respond to.
+    if let Some(payload) = body
+        .as_ref()
+        .and_then(|body| serde_json::from_str::<Value>(body).ok())
+        && let Some(method) = methods(&payload).find(|method| {
+            kinds
+                .get(*method)
+                .is_some_and(|kind| *kind != HandlerKind::Query)
+        })
+    {
+        return Err(Rejection::MethodNotAllowed {
+            method: method.to_string(),
+            id: request_id(&payload),
+        });
+    }
+
+    // Change this request into a regular POST request, and indicate that it should be a query.
+    *req.method_mut() = Method::POST;
+
+    // Update the headers.
+    let headers = req.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+    if let Some(body) = body {
+        // TODO: Replace `axum` with something else.
+        *req.body_mut() = Body::from(body);
+    }
+
+    Ok(())
+}
+
+/// Name of every method called in a JSON-RPC payload, which may be a batch.
+fn methods(payload: &Value) -> impl Iterator<Item = &str> {
+    match payload {
+        Value::Array(calls) => calls.iter().collect(),
+        call => vec![call],
+    }
+    .into_iter()
+    .filter_map(|call| call.get("method")?.as_str())
+}
+
+/// ID of a JSON-RPC payload, or `null` if it is a batch.
+fn request_id(payload: &Value) -> Value {
+    match payload {
+        Value::Object(call) => call.get("id").cloned().unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
+
+/// Reasons that a request may be rejected before reaching the server.
+enum Rejection {
+    /// A `GET` request to a handler that isn't a query.
+    MethodNotAllowed {
+        /// Method that was called.
+        method: String,
+        /// ID of the request, if it wasn't a batch.
+        id: Value,
+    },
+    /// Request failed CSRF checks.
+    Csrf {
+        /// Reason that the check failed.
+        reason: &'static str,
+        /// ID of the request, if it wasn't a batch.
+        id: Value,
+    },
+    /// Body of the request could not be read.
+    InvalidBody,
+    /// Body of the request exceeded [`MAX_BODY_SIZE`].
+    TooLarge,
+    /// Body of the request could not be decoded with its encoding.
+    Undecodable(Encoding),
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let (status, code, message, id) = match self {
+            Self::MethodNotAllowed { method, id } => (
+                StatusCode::METHOD_NOT_ALLOWED,
+                ErrorCode::InvalidRequest,
+                format!("`{method}` is not a query, and must be called with POST"),
+                id,
+            ),
+            Self::Csrf { reason, id } => (
+                StatusCode::FORBIDDEN,
+                ErrorCode::InvalidRequest,
+                format!("CSRF check failed: {reason}"),
+                id,
+            ),
+            Self::InvalidBody => (
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidRequest,
+                "failed to read request body".to_string(),
+                Value::Null,
+            ),
+            Self::TooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorCode::OversizedRequest,
+                format!("request body exceeds the maximum size of {MAX_BODY_SIZE} bytes"),
+                Value::Null,
+            ),
+            Self::Undecodable(encoding) => (
+                StatusCode::BAD_REQUEST,
+                ErrorCode::ParseError,
+                format!("request body is not valid {encoding}"),
+                Value::Null,
+            ),
+        };
+
+        let error = ErrorObjectOwned::owned::<()>(code.code(), message, None);
+
+        let mut response = (
+            status,
+            Json(json!({
+                "jsonrpc": "2.0",
+                "error": error,
+                "id": id,
+            })),
+        )
+            .into_response();
+
+        if status == StatusCode::METHOD_NOT_ALLOWED {
+            response
+                .headers_mut()
+                .insert(header::ALLOW, HeaderValue::from_static("POST"));
+        }
+
+        response
+    }
+}
+
 impl<Ctx> RouterModule<Ctx> for RpcModule<Ctx> {
     type Handler = Handler<Ctx>;
 
     fn visit_handler(&mut self, path: &[&str], handler: &Self::Handler) {
-        (handler.0)(&mut self.0, path.join("."));
+        let path = path.join(".");
+        self.kinds.insert(path.clone(), handler.kind);
+        self.handlers.push((path, handler.clone()));
     }
 }
 
 /// Callback function to register a handler against the provided [`JsonRpseeModule`] at the
-/// specified path.
+/// specified path, with the provided options.
 ///
 /// This is a type-erased closure, so it's expected that the closure creator had ownership on the
 /// handler implementation, and can move it into the closure.
-type HandlerRegistrationFn<Ctx> = Box<dyn Fn(&mut JsonRpseeModule<Ctx>, String)>;
+type HandlerRegistrationFn<Ctx> =
+    Arc<dyn Fn(&mut JsonRpseeModule<Ctx>, String, &RegisterOptions<Ctx>)>;
 
-/// Handler representation, which just contains the registration callback.
-pub struct Handler<Ctx>(HandlerRegistrationFn<Ctx>);
+/// Handler representation, containing the registration callback and the kind of the handler.
+pub struct Handler<Ctx> {
+    register: HandlerRegistrationFn<Ctx>,
+    kind: HandlerKind,
+    /// Whether the handler declares a rate limit.
+    rate_limited: bool,
+}
+
+impl<Ctx> Clone for Handler<Ctx> {
+    fn clone(&self) -> Self {
+        Self {
+            register: Arc::clone(&self.register),
+            kind: self.kind,
+            rate_limited: self.rate_limited,
+        }
+    }
+}
 
 impl<Ctx> RouterModuleHandler<Ctx> for Handler<Ctx> {
     fn from_handler<F, MSig, MValue: marker::ResponseMarker, MReturn: marker::HandlerReturnMarker>(
         handler: F,
-        _meta: &'static HandlerMeta,
+        meta: Arc<HandlerMeta>,
     ) -> Self
     where
         F: RegisterableHandler<Ctx, MSig, MValue, MReturn>,
         F::Ctx: FromRequestExtensions<Ctx>,
     {
-        Self(Box::new(move |module, path| {
-            handler.clone().register(module, path);
-        }))
+        let kind = meta.kind;
+        let rate_limited = meta.rate_limit.is_some();
+
+        Self {
+            register: Arc::new(move |module, path, options| {
+                handler
+                    .clone()
+                    .register(module, path, Arc::clone(&meta), options);
+            }),
+            kind,
+            rate_limited,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Module which panics if the API is described.
+    fn module() -> RpcModule<()> {
+        RpcModule::new((), || panic!("API was described"))
+    }
+
+    #[test]
+    fn api_not_described() {
+        module().into_module();
+    }
+
+    #[test]
+    #[should_panic(expected = "API was described")]
+    fn api_described_for_introspection() {
+        module().with_introspection("test", "0.0.0");
     }
 }
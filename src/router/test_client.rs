@@ -0,0 +1,227 @@
+//! In-process client for calling handlers without a server.
+
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{Stream, StreamExt, stream::BoxStream};
+use http::{HeaderMap, Request};
+use jsonrpsee::{
+    Extensions, Methods,
+    core::{
+        server::{MethodsError, Subscription},
+        traits::ToRpcParams,
+    },
+    types::error::ErrorCode,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, value::RawValue};
+
+use crate::{RequestInfo, RpcError, Transport};
+
+/// Client which calls the handlers of a [`Router`] directly, without a server or any sockets.
+/// Intended for tests.
+///
+/// Parameters are provided as a tuple (or `()` if the handler takes no parameters), and results
+/// are deserialised into the requested type.
+///
+/// ```
+/// use futures::{Stream, StreamExt, stream};
+/// use qubit::{Router, handler};
+///
+/// #[handler(query)]
+/// async fn add(ctx: (), a: u32, b: u32) -> u32 {
+///     a + b
+/// }
+///
+/// #[handler(subscription)]
+/// fn countdown(ctx: ()) -> impl Stream<Item = u32> {
+///     stream::iter([3, 2, 1])
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let client = Router::new().handler(add).handler(countdown).test_client(());
+///
+/// assert_eq!(client.call::<u32>("add", (1, 2)).await.unwrap(), 3);
+///
+/// let mut subscription = client.subscribe::<u32>("countdown", ()).await.unwrap();
+/// assert_eq!((&mut subscription).collect::<Vec<_>>().await, [3, 2, 1]);
+/// assert_eq!(subscription.closed(), Some(3));
+/// # }
+/// ```
+///
+/// [`Router`]: crate::Router
+#[derive(Clone, Debug)]
+pub struct TestClient {
+    methods: Methods,
+}
+
+impl TestClient {
+    /// Create a client for the provided methods.
+    pub(crate) fn new(methods: impl Into<Methods>) -> Self {
+        Self {
+            methods: methods.into(),
+        }
+    }
+
+    /// Produce a client which provides `extensions` to every call, in place of any previously
+    /// provided. This can be used to provide values to extractors, such as headers for
+    /// authentication.
+    pub fn with_extensions(&self, extensions: Extensions) -> Self {
+        let mut methods = self.methods.clone();
+        *methods.extensions_mut() = extensions;
+
+        Self { methods }
+    }
+
+    /// Produce a client which makes every call as if it were a HTTP request with the provided
+    /// headers, such as for extractors in [`auth`](crate::auth).
+    pub fn with_headers(&self, headers: HeaderMap) -> Self {
+        let mut request = Request::new(());
+        *request.headers_mut() = headers;
+
+        let mut methods = self.methods.clone();
+        methods
+            .extensions_mut()
+            .insert(RequestInfo::from_request(&request, Transport::Http));
+
+        Self { methods }
+    }
+
+    /// Call the handler at `method` (such as `posts.get`), deserialising the result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result can't be deserialised as `R`.
+    pub async fn call<R>(&self, method: &str, params: impl Serialize) -> Result<R, RpcError>
+    where
+        R: DeserializeOwned + Clone,
+    {
+        self.methods
+            .call(method, TestParams(params))
+            .await
+            .map_err(|e| into_rpc_error(method, e))
+    }
+
+    /// Subscribe to the handler at `method`, producing a stream of every item.
+    pub async fn subscribe<T>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<TestSubscription<T>, RpcError>
+    where
+        T: DeserializeOwned,
+    {
+        let subscription = self
+            .methods
+            .subscribe_unbounded(method, TestParams(params))
+            .await
+            .map_err(|e| into_rpc_error(method, e))?;
+
+        Ok(TestSubscription::new(method, subscription))
+    }
+}
+
+/// Stream of items from a subscription made with [`TestClient::subscribe`]. The stream will end
+/// once the handler's stream completes.
+///
+/// # Panics
+///
+/// The stream will panic if an item can't be deserialised as `T`.
+pub struct TestSubscription<T> {
+    method: String,
+    notifications: BoxStream<'static, Value>,
+    /// Number of items reported by the close notification, once received.
+    closed: Option<usize>,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T> TestSubscription<T> {
+    /// Create a new instance from the underlying subscription.
+    fn new(method: &str, subscription: Subscription) -> Self {
+        Self {
+            method: method.to_string(),
+            notifications: futures::stream::unfold(subscription, |mut subscription| async move {
+                let (notification, _) = subscription.next::<Value>().await?.ok()?;
+                Some((notification, subscription))
+            })
+            .boxed(),
+            closed: None,
+            item: PhantomData,
+        }
+    }
+
+    /// Number of items that the server reported sending when it closed the subscription, or
+    /// `None` if the close notification hasn't been received.
+    pub fn closed(&self) -> Option<usize> {
+        self.closed
+    }
+}
+
+impl<T> Stream for TestSubscription<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.closed.is_some() {
+            return Poll::Ready(None);
+        }
+
+        let Some(notification) = std::task::ready!(self.notifications.poll_next_unpin(cx)) else {
+            return Poll::Ready(None);
+        };
+
+        // The final notification indicates that the stream has closed.
+        if let Some(count) = notification
+            .get("close_stream")
+            .and(notification.get("count"))
+            .and_then(Value::as_u64)
+        {
+            self.closed = Some(count as usize);
+            return Poll::Ready(None);
+        }
+
+        Poll::Ready(Some(serde_json::from_value(notification).unwrap_or_else(
+            |e| panic!("invalid item from `{}`: {e}", self.method),
+        )))
+    }
+}
+
+/// Parameters for a call, serialised from a tuple.
+struct TestParams<P>(P);
+
+impl<P: Serialize> ToRpcParams for TestParams<P> {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+        // Handlers without parameters expect an empty array, rather than `null`.
+        let params = match serde_json::to_value(self.0)? {
+            Value::Null => Value::Array(Vec::new()),
+            params => params,
+        };
+
+        serde_json::value::to_raw_value(&params).map(Some)
+    }
+}
+
+/// Convert an error from calling a method into the error produced by the handler.
+///
+/// # Panics
+///
+/// Panics if the response couldn't be parsed, as this indicates that the requested result type
+/// doesn't match the handler.
+fn into_rpc_error(method: &str, error: MethodsError) -> RpcError {
+    match error {
+        MethodsError::JsonRpc(error) => RpcError {
+            code: ErrorCode::from(error.code()),
+            message: error.message().to_string(),
+            data: error
+                .data()
+                .and_then(|data| serde_json::from_str(data.get()).ok()),
+        },
+        error => panic!("invalid response from `{method}`: {error}"),
+    }
+}
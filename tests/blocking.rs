@@ -0,0 +1,115 @@
+#![allow(unused_variables)]
+
+use std::time::Duration;
+
+use qubit::*;
+
+#[handler(query, blocking)]
+fn blocking_thread(ctx: ()) -> String {
+    format!("{:?}", std::thread::current().id())
+}
+
+#[handler(query)]
+fn inline_thread(ctx: ()) -> String {
+    format!("{:?}", std::thread::current().id())
+}
+
+#[handler(mutation, blocking)]
+fn slow(ctx: ()) -> u32 {
+    std::thread::sleep(Duration::from_millis(100));
+    1
+}
+
+#[handler(query)]
+async fn fast(ctx: ()) -> u32 {
+    2
+}
+
+#[handler(mutation, blocking, max_concurrency = 1, timeout = "10ms")]
+fn exclusive(ctx: ()) -> u32 {
+    std::thread::sleep(Duration::from_millis(100));
+    3
+}
+
+fn router() -> Router<()> {
+    Router::new()
+        .handler(blocking_thread)
+        .handler(inline_thread)
+        .handler(slow)
+        .handler(fast)
+        .handler(exclusive)
+}
+
+fn current_thread() -> String {
+    format!("{:?}", std::thread::current().id())
+}
+
+#[tokio::test]
+async fn blocking_handler() {
+    let module = router().as_rpc(()).into_module();
+
+    let thread = module
+        .call::<_, String>("blocking_thread", [(); 0])
+        .await
+        .unwrap();
+    assert_ne!(thread, current_thread());
+}
+
+#[tokio::test]
+async fn inline_handler() {
+    let module = router().as_rpc(()).into_module();
+
+    let thread = module
+        .call::<_, String>("inline_thread", [(); 0])
+        .await
+        .unwrap();
+    assert_eq!(thread, current_thread());
+}
+
+#[tokio::test]
+async fn module_blocking() {
+    let module = router().as_rpc(()).with_blocking().into_module();
+
+    let thread = module
+        .call::<_, String>("inline_thread", [(); 0])
+        .await
+        .unwrap();
+    assert_ne!(thread, current_thread());
+}
+
+#[tokio::test]
+async fn blocking_doesnt_stall() {
+    let module = router().as_rpc(()).into_module();
+
+    // The runtime has a single thread, so the fast call can only complete while the slow call is
+    // running if the slow call has been moved off of it.
+    let slow_call = async {
+        let value = module.call::<_, u32>("slow", [(); 0]).await.unwrap();
+        (value, std::time::Instant::now())
+    };
+    let fast_call = async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let value = module.call::<_, u32>("fast", [(); 0]).await.unwrap();
+        (value, std::time::Instant::now())
+    };
+
+    let ((slow_value, slow_at), (fast_value, fast_at)) = tokio::join!(slow_call, fast_call);
+    assert_eq!((slow_value, fast_value), (1, 2));
+    assert!(fast_at < slow_at);
+}
+
+#[tokio::test]
+async fn timed_out_holds_permit() {
+    let client = router().test_client(());
+
+    let err = client.call::<u32>("exclusive", ()).await.unwrap_err();
+    assert_eq!(err.code, RpcError::TIMED_OUT);
+
+    // The blocking task is still running, so it continues to hold the only permit.
+    let err = client.call::<u32>("exclusive", ()).await.unwrap_err();
+    assert_eq!(err.code, RpcError::SERVER_BUSY);
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let err = client.call::<u32>("exclusive", ()).await.unwrap_err();
+    assert_eq!(err.code, RpcError::TIMED_OUT);
+}
@@ -0,0 +1,121 @@
+#![allow(unused_variables)]
+
+use std::{sync::Mutex, time::Duration};
+
+use futures::{Stream, stream};
+use jsonrpsee::core::server::MethodsError;
+use qubit::*;
+use tokio_util::sync::CancellationToken;
+
+/// Tokens received by handlers.
+static TOKENS: Mutex<Vec<CancellationToken>> = Mutex::new(Vec::new());
+
+fn take_token() -> CancellationToken {
+    TOKENS.lock().unwrap().pop().unwrap()
+}
+
+#[handler(query, timeout = "10ms")]
+async fn slow(cancellation: Cancellation) -> u32 {
+    TOKENS.lock().unwrap().push(cancellation.into_inner());
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    1
+}
+
+#[handler(query)]
+async fn sleep_for(ctx: (), millis: u64) -> u64 {
+    tokio::time::sleep(Duration::from_millis(millis)).await;
+    millis
+}
+
+#[handler(mutation)]
+async fn spawn_task(cancellation: Cancellation) {
+    TOKENS.lock().unwrap().push(cancellation.into_inner());
+}
+
+#[handler(subscription)]
+fn forever(cancellation: Cancellation) -> impl Stream<Item = u32> {
+    TOKENS.lock().unwrap().push(cancellation.into_inner());
+    stream::pending()
+}
+
+fn module(timeout: Option<Duration>) -> jsonrpsee::RpcModule<()> {
+    let rpc = Router::new()
+        .handler(slow)
+        .handler(sleep_for)
+        .handler(spawn_task)
+        .handler(forever)
+        .as_rpc(());
+
+    match timeout {
+        Some(timeout) => rpc.with_timeout(timeout),
+        None => rpc,
+    }
+    .into_module()
+}
+
+/// Code of the JSON-RPC error produced by a call.
+fn error_code(err: MethodsError) -> i32 {
+    match err {
+        MethodsError::JsonRpc(err) => err.code(),
+        err => panic!("expected JSON-RPC error, found {err:?}"),
+    }
+}
+
+/// Tests share the tokens, so must run one after another.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[tokio::test]
+async fn handler_timeout() {
+    let _serial = SERIAL.lock().await;
+
+    // The handler's timeout takes precedence over the module.
+    let err = module(Some(Duration::from_secs(60)))
+        .call::<_, u32>("slow", [(); 0])
+        .await
+        .unwrap_err();
+    assert_eq!(error_code(err), RpcError::TIMED_OUT.code());
+    assert!(take_token().is_cancelled());
+}
+
+#[tokio::test]
+async fn module_timeout() {
+    let module = module(Some(Duration::from_millis(10)));
+
+    assert_eq!(module.call::<_, u64>("sleep_for", [0]).await.unwrap(), 0);
+
+    let err = module
+        .call::<_, u64>("sleep_for", [1000])
+        .await
+        .unwrap_err();
+    assert_eq!(error_code(err), RpcError::TIMED_OUT.code());
+}
+
+#[tokio::test]
+async fn completed_not_cancelled() {
+    let _serial = SERIAL.lock().await;
+
+    module(None)
+        .call::<_, ()>("spawn_task", [(); 0])
+        .await
+        .unwrap();
+
+    assert!(!take_token().is_cancelled());
+}
+
+#[tokio::test]
+async fn subscription_closed() {
+    let _serial = SERIAL.lock().await;
+
+    let subscription = module(None)
+        .subscribe_unbounded("forever", [(); 0])
+        .await
+        .unwrap();
+    let token = take_token();
+    assert!(!token.is_cancelled());
+
+    drop(subscription);
+
+    tokio::time::timeout(Duration::from_secs(1), token.cancelled())
+        .await
+        .expect("token cancelled once subscription closed");
+}
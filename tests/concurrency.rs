@@ -0,0 +1,97 @@
+#![allow(unused_variables)]
+
+use std::time::Duration;
+
+use jsonrpsee::core::server::MethodsError;
+use qubit::{concurrency::ConcurrencyLimit, *};
+
+#[handler(mutation, max_concurrency = 1)]
+async fn exclusive(ctx: ()) -> u32 {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    1
+}
+
+#[handler(mutation, max_concurrency = 1, max_wait = "1s")]
+async fn queued(ctx: ()) -> u32 {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    1
+}
+
+fn router() -> Router<()> {
+    Router::new().handler(exclusive).handler(queued)
+}
+
+/// Make two calls to `method` at the same time.
+async fn call_twice(
+    module: &jsonrpsee::RpcModule<()>,
+    method: &str,
+) -> (Result<u32, MethodsError>, Result<u32, MethodsError>) {
+    tokio::join!(module.call(method, [(); 0]), async {
+        // Ensure the first call is running before making the second.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        module.call(method, [(); 0]).await
+    })
+}
+
+/// Code of the JSON-RPC error produced by a call.
+fn error_code(err: MethodsError) -> i32 {
+    match err {
+        MethodsError::JsonRpc(err) => err.code(),
+        err => panic!("expected JSON-RPC error, found {err:?}"),
+    }
+}
+
+#[tokio::test]
+async fn reject() {
+    let module = router().as_rpc(()).into_module();
+
+    let (first, second) = call_twice(&module, "exclusive").await;
+    assert_eq!(first.unwrap(), 1);
+    assert_eq!(
+        error_code(second.unwrap_err()),
+        RpcError::SERVER_BUSY.code()
+    );
+
+    // Calls may run once the previous call completes.
+    assert_eq!(
+        module.call::<_, u32>("exclusive", [(); 0]).await.unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn queue() {
+    let module = router().as_rpc(()).into_module();
+
+    let (first, second) = call_twice(&module, "queued").await;
+    assert_eq!(first.unwrap(), 1);
+    assert_eq!(second.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn module_override() {
+    let module = router()
+        .as_rpc(())
+        .with_concurrency_limit("queued", ConcurrencyLimit::new(1))
+        .with_concurrency_limit("exclusive", ConcurrencyLimit::new(2))
+        .into_module();
+
+    let (first, second) = call_twice(&module, "queued").await;
+    assert_eq!(first.unwrap(), 1);
+    assert_eq!(
+        error_code(second.unwrap_err()),
+        RpcError::SERVER_BUSY.code()
+    );
+
+    let (first, second) = call_twice(&module, "exclusive").await;
+    assert_eq!(first.unwrap(), 1);
+    assert_eq!(second.unwrap(), 1);
+}
+
+#[test]
+#[should_panic(expected = "there is no handler with that name")]
+fn module_override_unknown_method() {
+    router()
+        .as_rpc(())
+        .with_concurrency_limit("missing", ConcurrencyLimit::new(1));
+}
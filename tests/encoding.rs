@@ -0,0 +1,463 @@
+#![allow(unused_variables)]
+
+use std::time::Duration;
+
+use axum::{body::Body, response::IntoResponse};
+use futures::{Stream, stream};
+use http::{HeaderMap, Method, Request, StatusCode, header};
+use jsonrpsee::server::ServerHandle;
+use qubit::*;
+use serde_json::{Value, json};
+use soketto::handshake::{Client, ServerResponse};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use tower::ServiceExt;
+
+#[qubit::ts]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct Payload {
+    name: String,
+    values: Vec<i32>,
+    ratio: f64,
+    nested: Nested,
+}
+
+#[qubit::ts]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct Nested {
+    flag: bool,
+    none: Option<u32>,
+}
+
+#[handler(query)]
+fn echo(ctx: (), payload: Payload) -> Payload {
+    payload
+}
+
+#[handler(subscription)]
+fn count(ctx: (), to: u32) -> impl Stream<Item = u32> {
+    stream::iter(1..=to)
+}
+
+#[handler(query)]
+fn reverse(ctx: (), bytes: Bytes) -> Bytes {
+    bytes.iter().rev().copied().collect::<Vec<_>>().into()
+}
+
+#[handler(subscription)]
+fn chunks(ctx: (), to: u8) -> impl Stream<Item = Bytes> {
+    stream::iter((1..=to).map(|i| Bytes::from(vec![i; i as usize])))
+}
+
+fn router() -> Router<()> {
+    Router::new()
+        .handler(echo)
+        .handler(count)
+        .handler(reverse)
+        .handler(chunks)
+}
+
+/// Response containing binary data.
+#[derive(serde::Deserialize)]
+struct BytesResponse {
+    result: Bytes,
+}
+
+fn call(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 })
+}
+
+fn encode_msgpack(value: &Value) -> Vec<u8> {
+    rmp_serde::to_vec_named(value).unwrap()
+}
+
+fn encode_cbor(value: &Value) -> Vec<u8> {
+    let mut payload = Vec::new();
+    ciborium::into_writer(value, &mut payload).unwrap();
+    payload
+}
+
+/// Send a `POST` request with the provided body and headers, returning the status, headers and
+/// raw body of the response.
+async fn post(body: Vec<u8>, headers: &[(&str, &str)]) -> (StatusCode, HeaderMap, Vec<u8>) {
+    let (service, _handle) = router().as_rpc(()).into_service();
+
+    let mut req = Request::builder().method(Method::POST).uri("/");
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+
+    let response = service
+        .oneshot(req.body(Body::from(body)).unwrap())
+        .await
+        .unwrap()
+        .into_response();
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (status, headers, body.to_vec())
+}
+
+fn payload() -> Value {
+    json!({
+        "name": "qubit",
+        "values": [1, -2, 3],
+        "ratio": 3.5,
+        "nested": { "flag": true, "none": null },
+    })
+}
+
+#[tokio::test]
+async fn msgpack_request() {
+    let (status, headers, body) = post(
+        encode_msgpack(&call("echo", json!([payload()]))),
+        &[("content-type", "application/msgpack")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/msgpack");
+
+    let body: Value = rmp_serde::from_slice(&body).unwrap();
+    assert_eq!(body["result"], payload());
+}
+
+#[tokio::test]
+async fn cbor_request() {
+    let (status, headers, body) = post(
+        encode_cbor(&call("echo", json!([payload()]))),
+        &[("content-type", "application/cbor")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/cbor");
+
+    let body: Value = ciborium::from_reader(body.as_slice()).unwrap();
+    assert_eq!(body["result"], payload());
+}
+
+#[tokio::test]
+async fn accept_overrides_request_encoding() {
+    let (status, headers, body) = post(
+        call("echo", json!([payload()])).to_string().into_bytes(),
+        &[
+            ("content-type", "application/json"),
+            ("accept", "application/cbor"),
+        ],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/cbor");
+
+    let body: Value = ciborium::from_reader(body.as_slice()).unwrap();
+    assert_eq!(body["result"], payload());
+}
+
+#[tokio::test]
+async fn json_unchanged() {
+    let (status, headers, body) = post(
+        call("echo", json!([payload()])).to_string().into_bytes(),
+        &[("content-type", "application/json")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        headers[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("application/json")
+    );
+
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["result"], payload());
+}
+
+#[tokio::test]
+async fn invalid_body_rejected() {
+    let (status, headers, body) =
+        post(vec![0xc1], &[("content-type", "application/msgpack")]).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/msgpack");
+
+    let body: Value = rmp_serde::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], ErrorCode::ParseError.code());
+    assert_eq!(
+        body["error"]["message"],
+        "request body is not valid MessagePack"
+    );
+}
+
+#[tokio::test]
+async fn oversized_body_rejected() {
+    let (status, headers, body) = post(
+        vec![0; 10 * 1024 * 1024 + 1],
+        &[("content-type", "application/msgpack")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/msgpack");
+
+    let body: Value = rmp_serde::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], ErrorCode::OversizedRequest.code());
+}
+
+#[tokio::test]
+async fn json_bytes() {
+    let (status, _, body) = post(
+        call("reverse", json!(["AAEC"])).to_string().into_bytes(),
+        &[("content-type", "application/json")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["result"], "AgEA");
+}
+
+#[tokio::test]
+async fn msgpack_bytes() {
+    #[derive(serde::Serialize)]
+    struct Call {
+        jsonrpc: &'static str,
+        method: &'static str,
+        params: (Bytes,),
+        id: u32,
+    }
+
+    // Parameters may be raw binary, rather than a base64 string.
+    let payload = rmp_serde::to_vec_named(&Call {
+        jsonrpc: "2.0",
+        method: "reverse",
+        params: (Bytes::from([0, 1, 2]),),
+        id: 1,
+    })
+    .unwrap();
+    assert!(payload.windows(5).any(|bin| bin == [0xc4, 3, 0, 1, 2]));
+
+    let (status, _, body) = post(payload, &[("content-type", "application/msgpack")]).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    // The result is also raw binary.
+    assert!(body.windows(5).any(|bin| bin == [0xc4, 3, 2, 1, 0]));
+    let body: BytesResponse = rmp_serde::from_slice(&body).unwrap();
+    assert_eq!(body.result, Bytes::from([2, 1, 0]));
+}
+
+#[tokio::test]
+async fn cbor_bytes() {
+    let (status, _, body) = post(
+        encode_cbor(&call("reverse", json!(["AAEC"]))),
+        &[("content-type", "application/cbor")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    // Byte string of the length.
+    assert!(body.windows(4).any(|bin| bin == [0x43, 2, 1, 0]));
+    let body: BytesResponse = ciborium::from_reader(body.as_slice()).unwrap();
+    assert_eq!(body.result, Bytes::from([2, 1, 0]));
+}
+
+#[tokio::test]
+async fn cbor_bytes_batch() {
+    // Each response carries its own result, even if the IDs are reused.
+    let (status, _, body) = post(
+        encode_cbor(&json!([
+            call("reverse", json!(["AAEC"])),
+            call("reverse", json!(["AwQ="])),
+        ])),
+        &[("content-type", "application/cbor")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    // Both results are byte strings.
+    assert!(body.windows(4).any(|bin| bin == [0x43, 2, 1, 0]));
+    assert!(body.windows(3).any(|bin| bin == [0x42, 4, 3]));
+    let mut body: Vec<BytesResponse> = ciborium::from_reader(body.as_slice()).unwrap();
+    body.sort_by_key(|response| response.result.len());
+    assert_eq!(body[0].result, Bytes::from([4, 3]));
+    assert_eq!(body[1].result, Bytes::from([2, 1, 0]));
+}
+
+#[tokio::test]
+async fn cbor_strings_unchanged() {
+    // Strings produced by a handler are never mistaken for binary data.
+    let mut payload = payload();
+    payload["name"] = json!("AAEC");
+
+    let (status, _, body) = post(
+        encode_cbor(&call("echo", json!([payload]))),
+        &[("content-type", "application/cbor")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let body: ciborium::Value = ciborium::from_reader(body.as_slice()).unwrap();
+    let result = body
+        .as_map()
+        .unwrap()
+        .iter()
+        .find(|(key, _)| key.as_text() == Some("result"))
+        .unwrap()
+        .1
+        .as_map()
+        .unwrap();
+    assert!(
+        result
+            .iter()
+            .any(|(key, value)| key.as_text() == Some("name") && value.as_text() == Some("AAEC"))
+    );
+}
+
+/// Serve the router on a random port, and connect to it over a WebSocket with the provided
+/// subprotocol. The server will stop once the returned handle is dropped.
+async fn connect(
+    protocol: &'static str,
+) -> (
+    soketto::Sender<Compat<TcpStream>>,
+    soketto::Receiver<Compat<TcpStream>>,
+    ServerHandle,
+) {
+    let (service, handle) = router().as_rpc(()).into_service();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, axum::Router::new().fallback_service(service))
+            .await
+            .unwrap();
+    });
+
+    let socket = TcpStream::connect(address).await.unwrap();
+    let mut client = Client::new(socket.compat(), "localhost", "/");
+    client.add_protocol(protocol);
+
+    match client.handshake().await.unwrap() {
+        ServerResponse::Accepted {
+            protocol: accepted, ..
+        } => assert_eq!(accepted.as_deref(), Some(protocol)),
+        _ => panic!("WebSocket upgrade was rejected"),
+    }
+
+    let (tx, rx) = client.into_builder().finish();
+    (tx, rx, handle)
+}
+
+/// Receive the next message, failing if it doesn't arrive promptly.
+async fn receive(rx: &mut soketto::Receiver<Compat<TcpStream>>) -> (Vec<u8>, bool) {
+    let mut message = Vec::new();
+    let kind = tokio::time::timeout(Duration::from_secs(5), rx.receive_data(&mut message))
+        .await
+        .unwrap()
+        .unwrap();
+
+    (message, kind.is_binary())
+}
+
+#[tokio::test]
+async fn msgpack_ws() {
+    let (mut tx, mut rx, _handle) = connect("qubit.msgpack").await;
+
+    tx.send_binary(&encode_msgpack(&call("echo", json!([payload()]))))
+        .await
+        .unwrap();
+    tx.flush().await.unwrap();
+
+    let (message, binary) = receive(&mut rx).await;
+    assert!(binary);
+    let message: Value = rmp_serde::from_slice(&message).unwrap();
+    assert_eq!(message["result"], payload());
+
+    tx.send_binary(&encode_msgpack(&call("count", json!([2]))))
+        .await
+        .unwrap();
+    tx.flush().await.unwrap();
+
+    let (message, _) = receive(&mut rx).await;
+    let message: Value = rmp_serde::from_slice(&message).unwrap();
+    let subscription = message["result"].clone();
+    assert!(!subscription.is_null());
+
+    for expected in 1..=2 {
+        let (message, _) = receive(&mut rx).await;
+        let message: Value = rmp_serde::from_slice(&message).unwrap();
+        assert_eq!(message["params"]["subscription"], subscription);
+        assert_eq!(message["params"]["result"], expected);
+    }
+}
+
+#[tokio::test]
+async fn msgpack_ws_bytes() {
+    let (mut tx, mut rx, _handle) = connect("qubit.msgpack").await;
+
+    tx.send_binary(&encode_msgpack(&call("chunks", json!([2]))))
+        .await
+        .unwrap();
+    tx.flush().await.unwrap();
+
+    let (message, _) = receive(&mut rx).await;
+    let message: Value = rmp_serde::from_slice(&message).unwrap();
+    assert!(!message["result"].is_null());
+
+    for (expected, bin) in [(vec![1], &[0xc4, 1, 1][..]), (vec![2, 2], &[0xc4, 2, 2, 2])] {
+        let (message, _) = receive(&mut rx).await;
+        assert!(message.windows(bin.len()).any(|window| window == bin));
+
+        #[derive(serde::Deserialize)]
+        struct Notification {
+            params: BytesResponse,
+        }
+        let message: Notification = rmp_serde::from_slice(&message).unwrap();
+        assert_eq!(message.params.result, Bytes::from(expected));
+    }
+
+    // The close notification is also restored from its encoded form.
+    let (message, _) = receive(&mut rx).await;
+    let message: Value = rmp_serde::from_slice(&message).unwrap();
+    assert_eq!(message["params"]["result"]["count"], 2);
+}
+
+#[tokio::test]
+async fn cbor_ws() {
+    let (mut tx, mut rx, _handle) = connect("qubit.cbor").await;
+
+    tx.send_binary(&encode_cbor(&call("echo", json!([payload()]))))
+        .await
+        .unwrap();
+    tx.flush().await.unwrap();
+
+    let (message, binary) = receive(&mut rx).await;
+    assert!(binary);
+    let message: Value = ciborium::from_reader(message.as_slice()).unwrap();
+    assert_eq!(message["result"], payload());
+}
+
+#[tokio::test]
+async fn json_ws() {
+    let (mut tx, mut rx, _handle) = connect("qubit.json").await;
+
+    tx.send_text(&call("echo", json!([payload()])).to_string())
+        .await
+        .unwrap();
+    tx.flush().await.unwrap();
+
+    let (message, binary) = receive(&mut rx).await;
+    assert!(!binary);
+    let message: Value = serde_json::from_slice(&message).unwrap();
+    assert_eq!(message["result"], payload());
+}
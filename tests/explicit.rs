@@ -0,0 +1,65 @@
+#![allow(unused_variables)]
+
+use futures::{StreamExt, stream};
+use qubit::*;
+
+fn client() -> TestClient {
+    Router::new()
+        .query("add", &["a", "b"], |ctx: (), a: u32, b: u32| a + b)
+        .mutation(
+            "echo",
+            &["message"],
+            |ctx: (), message: String| async move { message },
+        )
+        .nest(
+            "stream",
+            Router::new().subscription("countdown", &["from"], |ctx: (), from: u32| {
+                stream::iter((1..=from).rev())
+            }),
+        )
+        .test_client(())
+}
+
+#[tokio::test]
+async fn query() {
+    assert_eq!(client().call::<u32>("add", (1, 2)).await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn mutation() {
+    assert_eq!(
+        client().call::<String>("echo", ("hello",)).await.unwrap(),
+        "hello"
+    );
+}
+
+#[tokio::test]
+async fn subscription() {
+    let mut subscription = client()
+        .subscribe::<u32>("stream.countdown", (3,))
+        .await
+        .unwrap();
+
+    assert_eq!((&mut subscription).collect::<Vec<_>>().await, [3, 2, 1]);
+    assert_eq!(subscription.closed(), Some(3));
+}
+
+#[test]
+fn snapshot() {
+    let snapshot = Router::<()>::new()
+        .query("add", &["a", "b"], |ctx: (), a: u32, b: u32| a + b)
+        .as_codegen()
+        .snapshot();
+
+    let add = &snapshot.handlers["add"];
+    assert_eq!(add.kind, HandlerKind::Query);
+    assert_eq!(add.params[0].name, "a");
+    assert_eq!(add.params[1].name, "b");
+    assert_eq!(add.return_ty, "number");
+}
+
+#[test]
+#[should_panic(expected = "parameter names provided for `add` don't match the handler")]
+fn mismatched_param_names() {
+    let _ = Router::new().query("add", &["a"], |ctx: (), a: u32, b: u32| a + b);
+}
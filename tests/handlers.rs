@@ -83,7 +83,7 @@ fn primitive_parameters() {
     #[handler(query)]
     fn handler(ctx: (), a: i32, b: String, c: Option<bool>) {}
 
-    test_handler!(handler = Query<[a: number, b: string, c: boolean | null], null>);
+    test_handler!(handler = Query<[a: number, b: string, c?: boolean], null>);
 }
 
 #[test]
@@ -144,3 +144,165 @@ fn everything_subscription() {
 
     test_handler!(handler<Ctx>(other_name) = Subscription<[param_1: number, param_2: string], boolean>);
 }
+
+#[test]
+fn multiple_extractors() {
+    #[derive(Clone)]
+    struct Ctx;
+
+    struct Db;
+    impl FromRequestExtensions<Ctx> for Db {
+        async fn from_request_extensions(
+            ctx: Ctx,
+            extensions: Extensions,
+        ) -> Result<Self, RpcError> {
+            Ok(Db)
+        }
+    }
+
+    #[handler(query)]
+    async fn handler(param_1: u32, #[ctx] db: Db, #[ctx] ctx: Ctx, param_2: String) -> bool {
+        todo!()
+    }
+
+    test_handler!(handler<Ctx> = Query<[param_1: number, param_2: string], boolean>);
+}
+
+#[test]
+fn destructured_params() {
+    #[handler(query)]
+    async fn handler(
+        ctx: (),
+        #[name = "pair"] (a, b): (u32, u32),
+        #[name = "count"] n: u32,
+    ) -> u32 {
+        a + b + n
+    }
+
+    test_handler!(handler = Query<[pair: [number, number], count: number], number>);
+}
+
+#[tokio::test]
+async fn destructured_call() {
+    #[qubit::ts]
+    #[derive(Clone, serde::Deserialize)]
+    struct GeoPoint {
+        lat: f64,
+        lng: f64,
+    }
+
+    #[handler(query)]
+    async fn handler(ctx: (), GeoPoint { lat, lng }: GeoPoint, scale: f64) -> f64 {
+        (lat + lng) * scale
+    }
+
+    let router = Router::new().handler(handler);
+
+    let snapshot = router.as_codegen().snapshot();
+    assert_eq!(snapshot.handlers["handler"].params[0].name, "geo_point");
+
+    let sum = router
+        .test_client(())
+        .call::<f64>(
+            "handler",
+            (serde_json::json!({ "lat": 1.0, "lng": 2.0 }), 2.0),
+        )
+        .await
+        .unwrap();
+    assert_eq!(sum, 6.0);
+}
+
+#[test]
+fn optional_params() {
+    #[handler(query)]
+    fn handler(ctx: (), a: Option<i32>, b: String, c: Option<bool>, #[default] d: u32) {}
+
+    test_handler!(handler = Query<[a: number | null, b: string, c?: boolean, d?: number], null>);
+}
+
+#[tokio::test]
+async fn optional_call() {
+    #[handler(query)]
+    async fn handler(ctx: (), a: u32, b: Option<u32>, #[default] c: u32) -> u32 {
+        a + b.unwrap_or(10) + c
+    }
+
+    let client = Router::new().handler(handler).test_client(());
+
+    assert_eq!(client.call::<u32>("handler", (1,)).await.unwrap(), 11);
+    assert_eq!(client.call::<u32>("handler", (1, 2)).await.unwrap(), 3);
+    assert_eq!(client.call::<u32>("handler", (1, 2, 3)).await.unwrap(), 6);
+    assert_eq!(
+        client
+            .call::<u32>("handler", (1, None::<u32>, None::<u32>))
+            .await
+            .unwrap(),
+        11
+    );
+}
+
+#[test]
+fn no_ctx() {
+    #[handler(query, no_ctx)]
+    fn handler(a: u32, b: u32) -> u32 {
+        a + b
+    }
+
+    test_handler!(handler = Query<[a: number, b: number], number>);
+}
+
+#[tokio::test]
+async fn no_ctx_call() {
+    #[derive(Clone)]
+    struct Ctx;
+
+    #[handler(query, no_ctx)]
+    async fn handler(a: u32, b: u32) -> u32 {
+        a + b
+    }
+
+    let sum = Router::new()
+        .handler(handler)
+        .test_client(Ctx)
+        .call::<u32>("handler", (1, 2))
+        .await
+        .unwrap();
+    assert_eq!(sum, 3);
+}
+
+#[tokio::test]
+async fn primitive_ctx() {
+    // A primitive `ctx` must be marked explicitly.
+    #[handler(query)]
+    async fn handler(#[ctx] ctx: u32, a: u32) -> u32 {
+        ctx + a
+    }
+
+    let sum = Router::new()
+        .handler(handler)
+        .test_client(1)
+        .call::<u32>("handler", (2,))
+        .await
+        .unwrap();
+    assert_eq!(sum, 3);
+}
+
+#[tokio::test]
+async fn invalid_params_call() {
+    #[handler(query)]
+    async fn handler(ctx: (), a: u32, b: Option<u32>) -> u32 {
+        a + b.unwrap_or_default()
+    }
+
+    let client = Router::new().handler(handler).test_client(());
+
+    let err = client.call::<u32>("handler", ("hello",)).await.unwrap_err();
+    assert_eq!(err.code, ErrorCode::InvalidParams);
+
+    let err = client.call::<u32>("handler", ()).await.unwrap_err();
+    assert_eq!(err.code, ErrorCode::InvalidParams);
+    assert_eq!(
+        err.data,
+        Some(serde_json::json!("missing required parameter `a`"))
+    );
+}
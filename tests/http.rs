@@ -0,0 +1,468 @@
+#![allow(unused_variables)]
+
+use std::net::SocketAddr;
+
+use axum::{body::Body, extract::ConnectInfo, response::IntoResponse};
+use http::{HeaderMap, HeaderName, Method, Request, StatusCode, header};
+use qubit::*;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+#[handler(query)]
+fn get_count(ctx: ()) -> u32 {
+    1
+}
+
+#[handler(mutation)]
+fn increment(ctx: ()) -> u32 {
+    2
+}
+
+struct User {
+    name: String,
+}
+
+impl auth::Authenticator<()> for User {
+    type Credentials = auth::BearerToken;
+
+    async fn authenticate(_ctx: (), token: auth::BearerToken) -> Result<Self, RpcError> {
+        match token.as_str() {
+            "abc-123" => Ok(User {
+                name: "user".to_string(),
+            }),
+            _ => Err(RpcError::unauthorised("invalid token")),
+        }
+    }
+}
+
+#[handler(query)]
+async fn whoami(user: auth::Authenticated<User>) -> String {
+    user.name.clone()
+}
+
+#[handler(query)]
+async fn greet(
+    #[ctx] user: auth::Authenticated<User>,
+    greeting: String,
+    #[ctx] token: auth::BearerToken,
+) -> String {
+    format!("{greeting} {} ({})", user.name, token.as_str())
+}
+
+struct Info(RequestInfo);
+
+impl FromRequestExtensions<()> for Info {
+    async fn from_request_extensions(_ctx: (), extensions: Extensions) -> Result<Self, RpcError> {
+        Ok(Self(extensions.get::<RequestInfo>().unwrap().clone()))
+    }
+}
+
+#[handler(query)]
+async fn describe(info: Info) -> String {
+    let Info(info) = info;
+
+    format!(
+        "{} {} {:?} {} {}",
+        info.method(),
+        serde_json::to_string(info.id()).unwrap(),
+        info.transport(),
+        info.uri(),
+        info.headers()["x-test"].to_str().unwrap(),
+    )
+}
+
+#[handler(mutation)]
+async fn login(mut response: ResponseParts, name: String) -> bool {
+    if name != "user" {
+        response.set_status(StatusCode::UNAUTHORIZED);
+        return false;
+    }
+
+    response
+        .set_cookie("session=abc; Path=/; HttpOnly")
+        .unwrap();
+    true
+}
+
+#[handler(mutation, rate_limit = "2/min")]
+fn send_email(ctx: ()) {}
+
+fn router() -> Router<()> {
+    Router::new()
+        .handler(get_count)
+        .handler(increment)
+        .handler(whoami)
+        .handler(greet)
+        .handler(login)
+        .nest("info", Router::new().handler(describe))
+}
+
+/// Send the request to a service created from the router, returning the status, headers and
+/// JSON body of the response.
+async fn send(rpc: RpcModule<()>, req: Request<Body>) -> (StatusCode, HeaderMap, Value) {
+    let (service, _handle) = rpc.into_service();
+
+    let response = service.oneshot(req).await.unwrap().into_response();
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (
+        status,
+        headers,
+        serde_json::from_slice(&body).unwrap_or(Value::Null),
+    )
+}
+
+/// Send a `GET` request with the provided JSON-RPC payload, in the same way as the client.
+async fn get(payload: Value) -> (StatusCode, HeaderMap, Value) {
+    let input = urlencoding::encode(&urlencoding::encode(&payload.to_string())).to_string();
+
+    send(
+        router().as_rpc(()),
+        Request::builder()
+            .method(Method::GET)
+            .uri(format!("/?input={input}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+}
+
+/// Send a `POST` request with the provided JSON-RPC payload and headers.
+async fn post(
+    rpc: RpcModule<()>,
+    payload: Value,
+    headers: &[(&str, &str)],
+) -> (StatusCode, HeaderMap, Value) {
+    let mut req = Request::builder()
+        .method(Method::POST)
+        .uri("/")
+        .header(header::CONTENT_TYPE, "application/json");
+
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+
+    send(rpc, req.body(Body::from(payload.to_string())).unwrap()).await
+}
+
+/// Send a `POST` request with the provided JSON-RPC payload, as though made from `addr`.
+async fn post_from(
+    rpc: RpcModule<()>,
+    payload: Value,
+    addr: &str,
+) -> (StatusCode, HeaderMap, Value) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/")
+        .header(header::CONTENT_TYPE, "application/json")
+        .extension(ConnectInfo(addr.parse::<SocketAddr>().unwrap()))
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+
+    send(rpc, req).await
+}
+
+fn call(method: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": [], "id": 1 })
+}
+
+#[tokio::test]
+async fn get_query() {
+    let (status, _, body) = get(call("get_count")).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"], 1);
+}
+
+#[tokio::test]
+async fn get_mutation_rejected() {
+    let (status, headers, body) = get(call("increment")).await;
+
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(headers[header::ALLOW], "POST");
+    assert_eq!(body["id"], 1);
+    assert_eq!(body["error"]["code"], ErrorCode::InvalidRequest.code());
+}
+
+#[tokio::test]
+async fn get_batch_with_mutation_rejected() {
+    let (status, _, _) = get(json!([call("get_count"), call("increment")])).await;
+
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn post_mutation() {
+    let (status, _, body) = post(router().as_rpc(()), call("increment"), &[]).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"], 2);
+}
+
+fn csrf_header() -> Csrf {
+    Csrf::new().require_header(HeaderName::from_static("x-csrf"))
+}
+
+#[tokio::test]
+async fn csrf_header_missing() {
+    let (status, _, body) = post(
+        router().as_rpc(()).with_csrf(csrf_header()),
+        call("increment"),
+        &[],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["id"], 1);
+}
+
+#[tokio::test]
+async fn csrf_header_present() {
+    let (status, _, body) = post(
+        router().as_rpc(()).with_csrf(csrf_header()),
+        call("increment"),
+        &[("x-csrf", "1")],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"], 2);
+}
+
+#[tokio::test]
+async fn csrf_not_required_for_query() {
+    let (status, _, body) = post(
+        router().as_rpc(()).with_csrf(csrf_header()),
+        call("get_count"),
+        &[],
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"], 1);
+}
+
+#[tokio::test]
+async fn csrf_double_submit_cookie() {
+    let rpc = || {
+        router()
+            .as_rpc(())
+            .with_csrf(Csrf::new().double_submit_cookie("csrf", HeaderName::from_static("x-csrf")))
+    };
+
+    let (status, _, _) = post(
+        rpc(),
+        call("increment"),
+        &[("x-csrf", "token"), ("cookie", "csrf=token")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = post(
+        rpc(),
+        call("increment"),
+        &[("x-csrf", "token"), ("cookie", "csrf=other")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn csrf_websocket_origin_rejected() {
+    let (status, _, _) = send(
+        router()
+            .as_rpc(())
+            .with_csrf(Csrf::new().allow_origin("https://example.com")),
+        Request::builder()
+            .method(Method::GET)
+            .uri("/")
+            .header(header::ORIGIN, "https://evil.com")
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn authenticated() {
+    let (_, _, body) = post(
+        router().as_rpc(()),
+        call("whoami"),
+        &[("authorization", "Bearer abc-123")],
+    )
+    .await;
+    assert_eq!(body["result"], "user");
+
+    let (_, _, body) = post(
+        router().as_rpc(()),
+        call("whoami"),
+        &[("authorization", "Bearer invalid")],
+    )
+    .await;
+    assert_eq!(body["error"]["code"], RpcError::UNAUTHORISED.code());
+}
+
+#[tokio::test]
+async fn multiple_extractors() {
+    let (_, _, body) = post(
+        router().as_rpc(()),
+        json!({ "jsonrpc": "2.0", "method": "greet", "params": ["Hello"], "id": 1 }),
+        &[("authorization", "Bearer abc-123")],
+    )
+    .await;
+
+    assert_eq!(body["result"], "Hello user (abc-123)");
+}
+
+#[tokio::test]
+async fn request_info() {
+    let (_, _, body) = post(
+        router().as_rpc(()),
+        json!({ "jsonrpc": "2.0", "method": "info.describe", "params": [], "id": "abc" }),
+        &[("x-test", "value")],
+    )
+    .await;
+
+    assert_eq!(body["result"], r#"info.describe "abc" Http / value"#);
+}
+
+#[tokio::test]
+async fn request_info_batch() {
+    let (_, _, body) = post(
+        router().as_rpc(()),
+        json!([call("info.describe"), { "jsonrpc": "2.0", "method": "info.describe", "params": [], "id": 2 }]),
+        &[("x-test", "value")],
+    )
+    .await;
+
+    assert_eq!(body[0]["result"], "info.describe 1 Http / value");
+    assert_eq!(body[1]["result"], "info.describe 2 Http / value");
+}
+
+#[tokio::test]
+async fn response_parts() {
+    let (status, headers, body) = post(
+        router().as_rpc(()),
+        json!({ "jsonrpc": "2.0", "method": "login", "params": ["user"], "id": 1 }),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::SET_COOKIE], "session=abc; Path=/; HttpOnly");
+    assert_eq!(body["result"], true);
+
+    let (status, headers, body) = post(
+        router().as_rpc(()),
+        json!({ "jsonrpc": "2.0", "method": "login", "params": ["nobody"], "id": 1 }),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(!headers.contains_key(header::SET_COOKIE));
+    assert_eq!(body["result"], false);
+}
+
+#[tokio::test]
+async fn rate_limit() {
+    let rate_limiter = rate_limit::RateLimiter::in_memory();
+
+    for _ in 0..2 {
+        let (status, _, body) = post_from(
+            router()
+                .handler(send_email)
+                .as_rpc(())
+                .with_rate_limiter(rate_limiter.clone()),
+            call("send_email"),
+            "127.0.0.1:1000",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["result"], Value::Null);
+    }
+
+    let (status, headers, body) = post_from(
+        router()
+            .handler(send_email)
+            .as_rpc(())
+            .with_rate_limiter(rate_limiter.clone()),
+        call("send_email"),
+        "127.0.0.1:1000",
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(headers[header::RETRY_AFTER], "30");
+    assert_eq!(body["error"]["code"], RpcError::RATE_LIMITED.code());
+    assert_eq!(body["error"]["data"]["retry_after"], 30);
+
+    // Other clients have their own budget.
+    let (status, _, _) = post_from(
+        router()
+            .handler(send_email)
+            .as_rpc(())
+            .with_rate_limiter(rate_limiter.clone()),
+        call("send_email"),
+        "127.0.0.2:1000",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Handlers without a limit are unaffected.
+    let (status, _, body) = post_from(
+        router()
+            .handler(send_email)
+            .as_rpc(())
+            .with_rate_limiter(rate_limiter.clone()),
+        call("get_count"),
+        "127.0.0.1:1000",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"], 1);
+
+    // Without connect info, clients can't be told apart.
+    let (_, _, body) = post(
+        router()
+            .handler(send_email)
+            .as_rpc(())
+            .with_rate_limiter(rate_limiter),
+        call("send_email"),
+        &[],
+    )
+    .await;
+    assert_eq!(body["error"]["code"], ErrorCode::InternalError.code());
+}
+
+#[test]
+#[should_panic(expected = "`send_email` declares a rate limit")]
+fn rate_limit_without_limiter() {
+    let _ = router().handler(send_email).as_rpc(()).into_service();
+}
+
+#[tokio::test]
+async fn introspection_get() {
+    let input =
+        urlencoding::encode(&urlencoding::encode(&call("qubit.schema").to_string())).to_string();
+
+    let (status, _, body) = send(
+        router().as_rpc(()).with_introspection("test", "0.0.0"),
+        Request::builder()
+            .method(Method::GET)
+            .uri(format!("/?input={input}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"]["handlers"]["get_count"]["kind"], "query");
+}
@@ -0,0 +1,94 @@
+#![allow(unused_variables)]
+
+use futures::{Stream, stream};
+use qubit::{metrics::InMemoryMetrics, *};
+
+#[handler(query)]
+fn get_count(ctx: ()) -> u32 {
+    1
+}
+
+#[handler(query)]
+async fn secret(token: auth::BearerToken) -> String {
+    token.into_inner()
+}
+
+#[handler(query)]
+async fn stall(ctx: ()) {
+    std::future::pending::<()>().await
+}
+
+#[handler(subscription)]
+fn countdown(ctx: ()) -> impl Stream<Item = u32> {
+    stream::iter([3, 2, 1])
+}
+
+fn module(metrics: &InMemoryMetrics) -> jsonrpsee::RpcModule<()> {
+    Router::new()
+        .handler(get_count)
+        .handler(secret)
+        .handler(stall)
+        .handler(countdown)
+        .as_rpc(())
+        .with_metrics(metrics.clone())
+        .into_module()
+}
+
+#[tokio::test]
+async fn calls() {
+    let metrics = InMemoryMetrics::new();
+    let module = module(&metrics);
+
+    let _: u32 = module.call("get_count", [(); 0]).await.unwrap();
+    let _: u32 = module.call("get_count", [(); 0]).await.unwrap();
+    module
+        .call::<_, String>("secret", [(); 0])
+        .await
+        .unwrap_err();
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot["get_count"].calls, 2);
+    assert!(snapshot["get_count"].errors.is_empty());
+    assert_eq!(snapshot["get_count"].latency.count(), 2);
+
+    assert_eq!(snapshot["secret"].calls, 1);
+    assert_eq!(snapshot["secret"].errors[&RpcError::UNAUTHORISED.code()], 1);
+}
+
+#[tokio::test]
+async fn dropped_call() {
+    let metrics = InMemoryMetrics::new();
+    let module = module(&metrics);
+
+    // The call is dropped before it completes, as a HTTP request would be if the client went away.
+    tokio::time::timeout(
+        std::time::Duration::from_millis(10),
+        module.call::<_, ()>("stall", [(); 0]),
+    )
+    .await
+    .unwrap_err();
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot["stall"].calls, 1);
+    assert_eq!(snapshot["stall"].errors[&RpcError::CANCELLED.code()], 1);
+    assert_eq!(snapshot["stall"].latency.count(), 1);
+}
+
+#[tokio::test]
+async fn subscription() {
+    let metrics = InMemoryMetrics::new();
+    let module = module(&metrics);
+
+    let mut subscription = module.subscribe("countdown", [(); 0], 4).await.unwrap();
+    while let Some(Ok((item, _))) = subscription.next::<serde_json::Value>().await {
+        if item.get("close_stream").is_some() {
+            break;
+        }
+    }
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot["countdown"].calls, 1);
+    assert_eq!(snapshot["countdown"].active_subscriptions, 0);
+    assert_eq!(snapshot["countdown"].subscription_items.count(), 1);
+    assert_eq!(snapshot["countdown"].subscription_items.sum(), 3.0);
+}
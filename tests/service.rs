@@ -0,0 +1,175 @@
+#![allow(unused_variables)]
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use futures::{Stream, StreamExt, stream};
+use qubit::*;
+
+/// Service which is used directly as the context.
+#[derive(Clone, Default)]
+struct Counter {
+    count: Arc<AtomicU32>,
+}
+
+#[service]
+impl Counter {
+    #[query]
+    fn get(&self) -> u32 {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    #[mutation(name = "add")]
+    async fn increment(&self, amount: u32) -> u32 {
+        self.count.fetch_add(amount, Ordering::SeqCst) + amount
+    }
+
+    #[subscription]
+    fn countdown(&self, from: u32) -> impl Stream<Item = u32> + use<> {
+        stream::iter((1..=from).rev())
+    }
+
+    #[query]
+    fn scaled(&self, #[name = "factors"] (a, b): (u32, u32)) -> u32 {
+        self.count.load(Ordering::SeqCst) * a * b
+    }
+
+    /// Methods without an attribute are left alone.
+    fn reset(&self) {
+        self.count.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Context of the application, which the services are extracted from.
+#[derive(Clone)]
+struct AppCtx {
+    users: Arc<UserService>,
+    admin: bool,
+}
+
+struct UserService {
+    names: Vec<String>,
+}
+
+impl FromRequestExtensions<AppCtx> for Arc<UserService> {
+    async fn from_request_extensions(
+        ctx: AppCtx,
+        _extensions: Extensions,
+    ) -> Result<Self, RpcError> {
+        Ok(ctx.users)
+    }
+}
+
+/// Extractor which only succeeds for admins.
+struct Admin;
+
+impl FromRequestExtensions<AppCtx> for Admin {
+    async fn from_request_extensions(
+        ctx: AppCtx,
+        _extensions: Extensions,
+    ) -> Result<Self, RpcError> {
+        if ctx.admin {
+            Ok(Admin)
+        } else {
+            Err(RpcError::forbidden("admins only"))
+        }
+    }
+}
+
+#[service]
+impl UserService {
+    #[query]
+    async fn get(self: Arc<Self>, id: usize) -> Option<String> {
+        self.names.get(id).cloned()
+    }
+
+    #[query]
+    async fn count(self: Arc<Self>, #[ctx] admin: Admin) -> usize {
+        self.names.len()
+    }
+}
+
+#[tokio::test]
+async fn service_ctx() {
+    let counter = Counter::default();
+    let client = Router::new()
+        .nest("counter", Counter::router())
+        .test_client(counter.clone());
+
+    assert_eq!(client.call::<u32>("counter.add", (2,)).await.unwrap(), 2);
+    assert_eq!(client.call::<u32>("counter.get", ()).await.unwrap(), 2);
+    assert_eq!(
+        client
+            .call::<u32>("counter.scaled", ((3, 4),))
+            .await
+            .unwrap(),
+        24
+    );
+
+    // The methods remain available.
+    counter.reset();
+    assert_eq!(client.call::<u32>("counter.get", ()).await.unwrap(), 0);
+
+    let subscription = client
+        .subscribe::<u32>("counter.countdown", (3,))
+        .await
+        .unwrap();
+    assert_eq!(subscription.collect::<Vec<_>>().await, [3, 2, 1]);
+}
+
+#[tokio::test]
+async fn extracted_service() {
+    let ctx = |admin| AppCtx {
+        users: Arc::new(UserService {
+            names: vec!["alice".to_string(), "bob".to_string()],
+        }),
+        admin,
+    };
+
+    let router = Router::new().nest("users", UserService::router());
+
+    let client = router.test_client(ctx(false));
+    assert_eq!(
+        client
+            .call::<Option<String>>("users.get", (1,))
+            .await
+            .unwrap(),
+        Some("bob".to_string())
+    );
+
+    // Additional extractors are still run.
+    let err = client.call::<usize>("users.count", ()).await.unwrap_err();
+    assert_eq!(err.code, RpcError::FORBIDDEN);
+
+    let client = router.test_client(ctx(true));
+    assert_eq!(client.call::<usize>("users.count", ()).await.unwrap(), 2);
+}
+
+#[test]
+fn snapshot() {
+    let snapshot = Router::<Counter>::new()
+        .nest("counter", Counter::router())
+        .as_codegen()
+        .snapshot();
+
+    assert_eq!(
+        snapshot.handlers.keys().collect::<Vec<_>>(),
+        [
+            "counter.add",
+            "counter.countdown",
+            "counter.get",
+            "counter.scaled"
+        ]
+    );
+    assert_eq!(
+        snapshot.handlers["counter.scaled"].params[0].name,
+        "factors"
+    );
+    assert_eq!(snapshot.handlers["counter.add"].params[0].name, "amount");
+    assert_eq!(
+        snapshot.handlers["counter.countdown"].kind,
+        HandlerKind::Subscription
+    );
+}
@@ -0,0 +1,126 @@
+#![allow(unused_variables)]
+
+use qubit::*;
+
+#[qubit::ts]
+#[derive(Clone, serde::Serialize)]
+struct User {
+    id: u32,
+    name: String,
+}
+
+#[handler(query)]
+fn get_user(ctx: (), id: u32) -> User {
+    todo!()
+}
+
+#[handler(mutation)]
+fn delete_user(ctx: (), id: u32) {}
+
+#[test]
+fn capture_snapshot() {
+    let snapshot = Router::<()>::new()
+        .nest("user", Router::new().handler(get_user))
+        .handler(delete_user)
+        .as_codegen()
+        .snapshot();
+
+    assert_eq!(
+        snapshot.handlers.keys().collect::<Vec<_>>(),
+        ["delete_user", "user.get_user"]
+    );
+
+    let handler = &snapshot.handlers["user.get_user"];
+    assert_eq!(handler.kind, HandlerKind::Query);
+    assert_eq!(handler.params[0].name, "id");
+    assert_eq!(handler.params[0].ty, "number");
+    assert_eq!(handler.return_ty, "User");
+
+    assert_eq!(snapshot.types["User"], "{ id: number, name: string, }");
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let snapshot = Router::<()>::new()
+        .handler(get_user)
+        .as_codegen()
+        .snapshot();
+
+    // Unique to this run, so that concurrent runs don't overwrite each other.
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!(
+        "qubit-snapshot-round-trip-{}-{nanos}.json",
+        std::process::id()
+    ));
+    snapshot.write(&path).unwrap();
+
+    let read = ApiSnapshot::read(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read.unwrap(), snapshot);
+}
+
+#[test]
+fn detect_breaking_change() {
+    let previous = Router::<()>::new()
+        .handler(get_user)
+        .handler(delete_user)
+        .as_codegen()
+        .snapshot();
+
+    let current = Router::<()>::new()
+        .handler(get_user)
+        .as_codegen()
+        .snapshot();
+
+    let diff = previous.diff(&current);
+    assert!(diff.is_breaking());
+    assert_eq!(
+        diff.changes,
+        [ApiChange::HandlerRemoved {
+            path: "delete_user".to_string()
+        }]
+    );
+
+    // Adding a handler is compatible.
+    assert!(!current.diff(&previous).is_breaking());
+}
+
+fn router() -> Router<()> {
+    Router::new()
+        .nest("user", Router::new().handler(get_user))
+        .handler(delete_user)
+}
+
+#[tokio::test]
+async fn introspection() {
+    let client = router()
+        .as_rpc(())
+        .with_introspection("users", "1.2.3")
+        .into_test_client();
+
+    let snapshot = client
+        .call::<ApiSnapshot>("qubit.schema", ())
+        .await
+        .unwrap();
+    assert_eq!(snapshot, router().as_codegen().snapshot());
+
+    let document = client
+        .call::<serde_json::Value>("rpc.discover", ())
+        .await
+        .unwrap();
+    assert_eq!(document["info"]["title"], "users");
+    assert_eq!(document["info"]["version"], "1.2.3");
+}
+
+#[tokio::test]
+async fn introspection_disabled() {
+    let err = router()
+        .test_client(())
+        .call::<ApiSnapshot>("qubit.schema", ())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+}
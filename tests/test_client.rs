@@ -0,0 +1,126 @@
+#![allow(unused_variables)]
+
+use futures::{Stream, StreamExt, stream};
+use http::{HeaderMap, HeaderValue, header};
+use qubit::*;
+
+#[handler(query)]
+fn add(ctx: (), a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[handler(query)]
+async fn whoami(token: auth::BearerToken) -> String {
+    token.into_inner()
+}
+
+/// Tenant provided to the extensions of a call.
+#[derive(Clone)]
+struct TenantId(String);
+
+struct Tenant(String);
+
+impl FromRequestExtensions<()> for Tenant {
+    async fn from_request_extensions(_ctx: (), extensions: Extensions) -> Result<Self, RpcError> {
+        extensions
+            .get::<TenantId>()
+            .map(|id| Tenant(id.0.clone()))
+            .ok_or_else(|| RpcError::forbidden("missing tenant"))
+    }
+}
+
+#[handler(query)]
+async fn tenant(tenant: Tenant) -> String {
+    tenant.0
+}
+
+#[handler(subscription)]
+fn countdown(ctx: (), from: u32) -> impl Stream<Item = u32> {
+    stream::iter((1..=from).rev())
+}
+
+#[handler(subscription)]
+async fn private_countdown(token: auth::BearerToken) -> impl Stream<Item = u32> {
+    stream::iter([1])
+}
+
+fn client() -> TestClient {
+    Router::new()
+        .handler(add)
+        .handler(whoami)
+        .handler(tenant)
+        .nest(
+            "stream",
+            Router::new().handler(countdown).handler(private_countdown),
+        )
+        .test_client(())
+}
+
+#[tokio::test]
+async fn call() {
+    assert_eq!(client().call::<u32>("add", (1, 2)).await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn call_error() {
+    let err = client().call::<String>("whoami", ()).await.unwrap_err();
+    assert_eq!(err.code, RpcError::UNAUTHORISED);
+
+    let err = client().call::<u32>("missing", ()).await.unwrap_err();
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+}
+
+#[tokio::test]
+async fn headers() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_static("Bearer abc"),
+    );
+
+    let client = client().with_headers(headers);
+    assert_eq!(client.call::<String>("whoami", ()).await.unwrap(), "abc");
+}
+
+#[tokio::test]
+async fn extensions() {
+    let mut extensions = Extensions::new();
+    extensions.insert(TenantId("acme".to_string()));
+
+    let client = client();
+    assert_eq!(
+        client
+            .with_extensions(extensions)
+            .call::<String>("tenant", ())
+            .await
+            .unwrap(),
+        "acme"
+    );
+
+    // Extensions only apply to the client they were provided to.
+    let err = client.call::<String>("tenant", ()).await.unwrap_err();
+    assert_eq!(err.code, RpcError::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn subscribe() {
+    let mut subscription = client()
+        .subscribe::<u32>("stream.countdown", (3,))
+        .await
+        .unwrap();
+    assert_eq!(subscription.closed(), None);
+
+    assert_eq!((&mut subscription).collect::<Vec<_>>().await, [3, 2, 1]);
+    assert_eq!(subscription.closed(), Some(3));
+}
+
+#[tokio::test]
+async fn subscribe_error() {
+    let Err(err) = client()
+        .subscribe::<u32>("stream.private_countdown", ())
+        .await
+    else {
+        panic!("expected subscription to be rejected");
+    };
+    assert_eq!(err.code, RpcError::UNAUTHORISED);
+}